@@ -0,0 +1,12 @@
+ALTER TABLE registration_requests
+    ADD COLUMN status_token TEXT;
+
+-- Backfill tokens so applicants who are still waiting can be sent a status link
+UPDATE registration_requests
+    SET status_token = REPLACE(UUID_GENERATE_V4()::TEXT, '-', '') || REPLACE(UUID_GENERATE_V4()::TEXT, '-', '')
+    WHERE rejected_at IS NULL
+    AND invalidated_at IS NULL;
+
+CREATE UNIQUE INDEX registration_request_status_token ON registration_requests(status_token);
+
+COMMENT ON COLUMN registration_requests.status_token IS 'Value used by applicant to check status of application and correct their data. Unlike confirmation_token it is kept after confirmation';
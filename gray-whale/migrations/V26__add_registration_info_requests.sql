@@ -0,0 +1,38 @@
+CREATE TABLE registration_info_requests
+    ( id UUID PRIMARY KEY DEFAULT UUID_GENERATE_V4()
+    , registration_request_id UUID NOT NULL REFERENCES registration_requests(id) ON DELETE CASCADE
+    , question TEXT NOT NULL
+    , answer TEXT
+    , created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    , answered_at TIMESTAMPTZ
+    );
+
+CREATE INDEX registration_info_requests_registration_request_id ON registration_info_requests(registration_request_id);
+CREATE INDEX registration_info_requests_answered_at ON registration_info_requests(answered_at);
+
+COMMENT ON TABLE registration_info_requests IS 'Questions reviewers sent to applicants together with their answers';
+COMMENT ON COLUMN registration_info_requests.answered_at IS 'Time applicant answered. Application with unanswered question needs more information';
+
+GRANT SELECT, INSERT, UPDATE ON TABLE registration_info_requests TO orca;
+
+CREATE OR REPLACE VIEW registration_requests_needs_info AS
+    SELECT * FROM registration_requests rr
+    WHERE rr.confirmed_at IS NOT NULL
+    AND rr.rejected_at IS NULL
+    AND rr.invalidated_at IS NULL
+    AND NOT EXISTS (SELECT id FROM members m WHERE rr.id = m.registration_request_id)
+    AND EXISTS (SELECT id FROM registration_info_requests ri WHERE rr.id = ri.registration_request_id AND ri.answered_at IS NULL);
+
+COMMENT ON VIEW registration_requests_needs_info IS 'All confirmed registrations waiting for applicant to answer question of reviewer';
+
+GRANT SELECT ON registration_requests_needs_info TO orca;
+
+CREATE OR REPLACE VIEW registration_requests_processing AS
+    SELECT * FROM registration_requests rr
+    WHERE rr.confirmed_at IS NOT NULL
+    AND rr.rejected_at IS NULL
+    AND rr.invalidated_at IS NULL
+    AND NOT EXISTS (SELECT id FROM members m WHERE rr.id = m.registration_request_id)
+    AND NOT EXISTS (SELECT id FROM registration_info_requests ri WHERE rr.id = ri.registration_request_id AND ri.answered_at IS NULL);
+
+COMMENT ON VIEW registration_requests_processing IS 'All registrations which are confirmed by applicant but are not yet either rejected or accepted';
@@ -0,0 +1,37 @@
+CREATE TABLE listmonk_subscription_requests
+    ( member_id UUID PRIMARY KEY REFERENCES members(id) ON DELETE CASCADE
+    , status TEXT NOT NULL DEFAULT 'pending'
+    , attempts INTEGER NOT NULL DEFAULT 0
+    , last_error TEXT
+    , created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    , updated_at TIMESTAMPTZ
+    );
+
+-- Members who were subscribed synchronously during acceptance
+INSERT INTO listmonk_subscription_requests (member_id, status)
+    SELECT DISTINCT member_id, 'subscribed'
+    FROM email_subscriptions
+    WHERE member_id IS NOT NULL;
+
+CREATE INDEX listmonk_subscription_requests_status ON listmonk_subscription_requests(status);
+
+COMMENT ON TABLE listmonk_subscription_requests IS 'Subscriptions of members to Listmonk which are processed in background';
+COMMENT ON COLUMN listmonk_subscription_requests.status IS 'One of pending, subscribed or failed (retries were exhausted)';
+
+GRANT SELECT, INSERT, UPDATE ON TABLE listmonk_subscription_requests TO orca;
+
+CREATE TABLE listmonk_sync_requests
+    ( member_id UUID PRIMARY KEY REFERENCES members(id) ON DELETE CASCADE
+    , status TEXT NOT NULL DEFAULT 'pending'
+    , attempts INTEGER NOT NULL DEFAULT 0
+    , last_error TEXT
+    , created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    , updated_at TIMESTAMPTZ
+    );
+
+CREATE INDEX listmonk_sync_requests_status ON listmonk_sync_requests(status);
+
+COMMENT ON TABLE listmonk_sync_requests IS 'Pushes of member changes to Listmonk which failed and are retried in background';
+COMMENT ON COLUMN listmonk_sync_requests.status IS 'Either pending or failed (retries were exhausted). Row is removed once push succeeds';
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON TABLE listmonk_sync_requests TO orca;
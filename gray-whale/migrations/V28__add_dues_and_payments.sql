@@ -0,0 +1,51 @@
+CREATE TABLE fee_schedules
+    ( id UUID PRIMARY KEY DEFAULT UUID_GENERATE_V4()
+    , name TEXT NOT NULL
+    , valid_from DATE NOT NULL UNIQUE
+    , currency TEXT NOT NULL DEFAULT 'CZK'
+    , flat_amount BIGINT
+    , created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+
+COMMENT ON TABLE fee_schedules IS 'Monthly membership dues. Schedule applies from valid_from until next schedule starts';
+COMMENT ON COLUMN fee_schedules.flat_amount IS 'Monthly fee in minor units (hundredths) of currency for schedules without income bands';
+
+CREATE TABLE fee_schedule_bands
+    ( id UUID PRIMARY KEY DEFAULT UUID_GENERATE_V4()
+    , fee_schedule_id UUID NOT NULL REFERENCES fee_schedules(id) ON DELETE CASCADE
+    , income_from BIGINT NOT NULL
+    , amount BIGINT NOT NULL
+    , UNIQUE (fee_schedule_id, income_from)
+    );
+
+COMMENT ON TABLE fee_schedule_bands IS 'Income dependent monthly fees of fee schedule';
+COMMENT ON COLUMN fee_schedule_bands.income_from IS 'Lowest monthly income (in whole units of currency) the band applies to';
+COMMENT ON COLUMN fee_schedule_bands.amount IS 'Monthly fee in minor units (hundredths) of currency';
+
+ALTER TABLE members
+    ADD COLUMN declared_income BIGINT;
+
+COMMENT ON COLUMN members.declared_income IS 'Monthly income (in whole units of currency) member declared for computing dues';
+
+CREATE TABLE payments
+    ( id UUID PRIMARY KEY DEFAULT UUID_GENERATE_V4()
+    , member_number INT NOT NULL REFERENCES members(member_number)
+    , amount BIGINT NOT NULL
+    , currency TEXT NOT NULL DEFAULT 'CZK'
+    , paid_on DATE NOT NULL
+    , source TEXT NOT NULL DEFAULT 'manual'
+    , reference TEXT
+    , note TEXT
+    , created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+
+CREATE INDEX payments_member_number ON payments(member_number);
+
+COMMENT ON TABLE payments IS 'Membership dues paid by members';
+COMMENT ON COLUMN payments.member_number IS 'Member number which is used as variable symbol of bank transfers';
+COMMENT ON COLUMN payments.amount IS 'Amount in minor units (hundredths) of currency';
+COMMENT ON COLUMN payments.source IS 'How the payment was recorded';
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON TABLE fee_schedules TO orca;
+GRANT SELECT, INSERT, UPDATE, DELETE ON TABLE fee_schedule_bands TO orca;
+GRANT SELECT, INSERT, UPDATE, DELETE ON TABLE payments TO orca;
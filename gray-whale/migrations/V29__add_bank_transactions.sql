@@ -0,0 +1,28 @@
+CREATE TABLE bank_transactions
+    ( id UUID PRIMARY KEY DEFAULT UUID_GENERATE_V4()
+    , bank_reference TEXT NOT NULL UNIQUE
+    , booked_on DATE NOT NULL
+    , amount BIGINT NOT NULL
+    , currency TEXT NOT NULL
+    , variable_symbol TEXT
+    , counterparty_account TEXT
+    , counterparty_name TEXT
+    , message TEXT
+    , status TEXT NOT NULL
+    , imported_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    , resolved_at TIMESTAMPTZ
+    );
+
+CREATE INDEX bank_transactions_status ON bank_transactions(status);
+
+COMMENT ON TABLE bank_transactions IS 'Incoming transactions imported from bank statements';
+COMMENT ON COLUMN bank_transactions.bank_reference IS 'Identifier assigned by bank. Makes repeated imports of the same statement harmless';
+COMMENT ON COLUMN bank_transactions.amount IS 'Amount in minor units (hundredths) of currency';
+COMMENT ON COLUMN bank_transactions.status IS 'matched, unmatched or ambiguous after import. resolved or ignored after manual review';
+
+ALTER TABLE payments
+    ADD COLUMN bank_transaction_id UUID UNIQUE REFERENCES bank_transactions(id);
+
+COMMENT ON COLUMN payments.bank_transaction_id IS 'Bank transaction the payment was recorded from';
+
+GRANT SELECT, INSERT, UPDATE ON TABLE bank_transactions TO orca;
@@ -0,0 +1,25 @@
+CREATE TABLE dues_reminders
+    ( id UUID PRIMARY KEY DEFAULT UUID_GENERATE_V4()
+    , member_id UUID NOT NULL REFERENCES members(id) ON DELETE CASCADE
+    , level TEXT NOT NULL
+    , arrears BIGINT NOT NULL
+    , automatic BOOLEAN NOT NULL
+    , sent_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+
+CREATE INDEX dues_reminders_member_id ON dues_reminders(member_id);
+
+COMMENT ON TABLE dues_reminders IS 'Log of reminders of unpaid dues sent to members';
+COMMENT ON COLUMN dues_reminders.level IS 'first, second or final notice before membership lapse';
+COMMENT ON COLUMN dues_reminders.arrears IS 'Arrears in minor units (hundredths) of currency at the time reminder was sent';
+COMMENT ON COLUMN dues_reminders.automatic IS 'Sent by periodic campaign rather than manually by treasurer';
+
+ALTER TABLE members
+    ADD COLUMN dues_reminders_paused_at TIMESTAMPTZ
+    , ADD COLUMN dues_reminders_paused_until DATE
+    , ADD COLUMN dues_reminders_pause_reason TEXT;
+
+COMMENT ON COLUMN members.dues_reminders_paused_at IS 'Automatic dues reminders are not sent to member (for example in case of hardship)';
+COMMENT ON COLUMN members.dues_reminders_paused_until IS 'Last day of the pause, NULL pauses reminders until resumed';
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON TABLE dues_reminders TO orca;
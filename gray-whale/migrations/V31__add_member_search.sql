@@ -0,0 +1,36 @@
+CREATE EXTENSION IF NOT EXISTS unaccent;
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- unaccent is only STABLE (dictionary can change) which prevents its use in indexes.
+-- Dictionary is given explicitly so it's safe to treat the function as IMMUTABLE.
+CREATE FUNCTION search_normalize(value TEXT) RETURNS TEXT
+    LANGUAGE SQL IMMUTABLE PARALLEL SAFE STRICT
+    AS $$ SELECT lower(public.unaccent('public.unaccent'::REGDICTIONARY, value)) $$;
+
+COMMENT ON FUNCTION search_normalize IS 'Lowercase text without diacritics used for searching';
+
+CREATE FUNCTION member_search_text
+    ( first_name TEXT
+    , last_name TEXT
+    , email TEXT
+    , phone_number TEXT
+    , city TEXT
+    ) RETURNS TEXT
+    LANGUAGE SQL IMMUTABLE PARALLEL SAFE
+    AS $$
+SELECT search_normalize(concat_ws(' '
+    , first_name
+    , last_name
+    , email
+    , regexp_replace(phone_number, '\s', '', 'g')
+    , city
+    ))
+$$;
+
+COMMENT ON FUNCTION member_search_text IS 'Searchable text of member. Needs to match expression of members_search index';
+
+CREATE INDEX members_search ON members
+    USING GIN (member_search_text(first_name, last_name, email, phone_number, city) gin_trgm_ops);
+
+CREATE INDEX occupations_company_name_search ON occupations
+    USING GIN (search_normalize(company_name) gin_trgm_ops);
@@ -0,0 +1,42 @@
+CREATE TABLE membership_periods
+    ( id UUID PRIMARY KEY DEFAULT UUID_GENERATE_V4()
+    , member_id UUID NOT NULL REFERENCES members(id) ON DELETE CASCADE
+    , joined_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    , left_at TIMESTAMPTZ
+    , left_reason TEXT
+    , note TEXT
+    );
+
+CREATE INDEX membership_periods_member_id ON membership_periods(member_id);
+CREATE UNIQUE INDEX membership_periods_open ON membership_periods(member_id) WHERE left_at IS NULL;
+
+COMMENT ON TABLE membership_periods IS 'Periods of membership. Member who left and re-joined has multiple periods under the same member number';
+COMMENT ON COLUMN membership_periods.left_at IS 'NULL for the ongoing period, members can have at most one';
+COMMENT ON COLUMN membership_periods.left_reason IS 'resigned, expelled, lapsed (for non-payment) or deceased. NULL for periods which ended before reasons were recorded';
+
+-- Every member so far had single period
+INSERT INTO membership_periods (member_id, joined_at, left_at)
+SELECT id, created_at, left_at
+FROM members;
+
+-- Open the first period no matter how member was created
+CREATE FUNCTION open_first_membership_period()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO membership_periods (member_id, joined_at) VALUES (NEW.id, NEW.created_at);
+    RETURN NEW;
+END;
+$$ LANGUAGE 'plpgsql';
+
+CREATE TRIGGER open_first_membership_period_members
+    AFTER INSERT ON members
+    FOR EACH ROW EXECUTE PROCEDURE open_first_membership_period();
+
+ALTER TABLE members
+    ADD COLUMN suspended_at TIMESTAMPTZ
+    , ADD COLUMN suspension_reason TEXT;
+
+COMMENT ON COLUMN members.left_at IS 'End of the last membership period, NULL for current members. Kept in sync with membership_periods';
+COMMENT ON COLUMN members.suspended_at IS 'Membership is suspended but member has not left';
+
+GRANT SELECT, INSERT, UPDATE ON TABLE membership_periods TO orca;
@@ -0,0 +1,19 @@
+CREATE TABLE profile_change_requests
+    ( id UUID PRIMARY KEY DEFAULT UUID_GENERATE_V4()
+    , member_id UUID NOT NULL REFERENCES members(id) ON DELETE CASCADE
+    , first_name TEXT
+    , last_name TEXT
+    , date_of_birth DATE
+    , status TEXT NOT NULL DEFAULT 'pending'
+    , created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    , resolved_at TIMESTAMPTZ
+    );
+
+CREATE INDEX profile_change_requests_member_id ON profile_change_requests(member_id);
+CREATE INDEX profile_change_requests_status ON profile_change_requests(status);
+
+COMMENT ON TABLE profile_change_requests IS 'Changes of sensitive personal data requested by members themselves which need approval of the board';
+COMMENT ON COLUMN profile_change_requests.first_name IS 'New value, NULL when the field is not being changed. Same for other fields';
+COMMENT ON COLUMN profile_change_requests.status IS 'pending, approved or rejected';
+
+GRANT SELECT, INSERT, UPDATE ON TABLE profile_change_requests TO orca;
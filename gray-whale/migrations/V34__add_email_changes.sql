@@ -0,0 +1,18 @@
+CREATE TABLE email_changes
+    ( id UUID PRIMARY KEY DEFAULT UUID_GENERATE_V4()
+    , member_id UUID NOT NULL REFERENCES members(id) ON DELETE CASCADE
+    , old_email TEXT
+    , new_email TEXT NOT NULL
+    , confirmation_token TEXT NOT NULL UNIQUE
+    , created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    , confirmed_at TIMESTAMPTZ
+    , cancelled_at TIMESTAMPTZ
+    );
+
+CREATE INDEX email_changes_member_id ON email_changes(member_id);
+
+COMMENT ON TABLE email_changes IS 'Changes of member email which need to be confirmed from the new address';
+COMMENT ON COLUMN email_changes.confirmation_token IS 'Secret included in the confirmation link sent to the new address';
+COMMENT ON COLUMN email_changes.cancelled_at IS 'Change was superseded by a newer request before it was confirmed';
+
+GRANT SELECT, INSERT, UPDATE ON TABLE email_changes TO orca;
@@ -0,0 +1,17 @@
+ALTER TABLE occupations
+    ADD COLUMN started_on DATE
+    , ADD COLUMN ended_on DATE
+    , ADD COLUMN is_current BOOLEAN NOT NULL DEFAULT TRUE
+    , ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+CREATE INDEX occupations_member_id ON occupations(member_id);
+
+CREATE TRIGGER update_updated_at_occupations
+    BEFORE UPDATE ON occupations
+    FOR EACH ROW EXECUTE PROCEDURE update_updated_at();
+
+COMMENT ON COLUMN occupations.started_on IS 'When member started working for the company, NULL if unknown';
+COMMENT ON COLUMN occupations.ended_on IS 'When member stopped working for the company, NULL if unknown or still employed';
+COMMENT ON COLUMN occupations.is_current IS 'Member currently works for the company. Members can have more current employers';
+
+GRANT DELETE ON TABLE occupations TO orca;
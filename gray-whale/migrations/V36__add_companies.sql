@@ -0,0 +1,92 @@
+-- Legal form suffixes are dropped so that "Google s.r.o." and "Google" are the same name
+CREATE FUNCTION company_normalize(value TEXT) RETURNS TEXT
+    LANGUAGE SQL IMMUTABLE PARALLEL SAFE STRICT
+    AS $$
+SELECT trim(regexp_replace(
+    regexp_replace(search_normalize(value), '[^a-z0-9]+', ' ', 'g')
+    , '(\s+(spol|s r o|sro|a s|k s|v o s|z s|se|inc|ltd|llc|gmbh|plc|corp|co))+\s*$'
+    , ''
+    ))
+$$;
+
+COMMENT ON FUNCTION company_normalize IS 'Company name without diacritics, punctuation and legal form used for matching aliases';
+
+CREATE TABLE companies
+    ( id UUID PRIMARY KEY DEFAULT UUID_GENERATE_V4()
+    , name TEXT NOT NULL UNIQUE
+    , registration_number TEXT UNIQUE
+    , created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    , updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+
+CREATE TRIGGER update_updated_at_companies
+    BEFORE UPDATE ON companies
+    FOR EACH ROW EXECUTE PROCEDURE update_updated_at();
+
+COMMENT ON TABLE companies IS 'Canonical employers free text company names are normalized to';
+COMMENT ON COLUMN companies.registration_number IS 'Czech company identification number (IČO) when known';
+
+CREATE TABLE company_aliases
+    ( id UUID PRIMARY KEY DEFAULT UUID_GENERATE_V4()
+    , company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE
+    , name TEXT NOT NULL
+    , normalized_name TEXT NOT NULL UNIQUE GENERATED ALWAYS AS (company_normalize(name)) STORED
+    , created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+
+CREATE INDEX company_aliases_company_id ON company_aliases(company_id);
+CREATE INDEX company_aliases_normalized_name_search ON company_aliases
+    USING GIN (normalized_name gin_trgm_ops);
+
+COMMENT ON TABLE company_aliases IS 'Names under which company is known. Canonical name of company is one of its aliases';
+
+ALTER TABLE occupations
+    ADD COLUMN company_id UUID REFERENCES companies(id) ON DELETE SET NULL;
+ALTER TABLE registration_requests
+    ADD COLUMN company_id UUID REFERENCES companies(id) ON DELETE SET NULL;
+
+CREATE INDEX occupations_company_id ON occupations(company_id);
+CREATE INDEX registration_requests_company_id ON registration_requests(company_id);
+
+COMMENT ON COLUMN occupations.company_id IS 'Canonical company of company_name. Matched automatically and can be corrected by board';
+COMMENT ON COLUMN registration_requests.company_id IS 'Canonical company suggested for company_name, copied to occupation when application is accepted';
+
+-- Exact alias match wins, otherwise the most similar alias if it's similar enough
+CREATE FUNCTION match_company(value TEXT) RETURNS UUID
+    LANGUAGE SQL STABLE STRICT
+    AS $$
+SELECT company_id
+FROM company_aliases
+WHERE normalized_name = company_normalize(value)
+    OR similarity(normalized_name, company_normalize(value)) >= 0.6
+ORDER BY normalized_name = company_normalize(value) DESC
+    , similarity(normalized_name, company_normalize(value)) DESC
+LIMIT 1
+$$;
+
+COMMENT ON FUNCTION match_company IS 'Canonical company suggested for free text company name';
+
+CREATE FUNCTION assign_company()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF (TG_OP = 'INSERT' AND NEW.company_id IS NULL)
+        OR (TG_OP = 'UPDATE'
+            AND NEW.company_name IS DISTINCT FROM OLD.company_name
+            AND NEW.company_id IS NOT DISTINCT FROM OLD.company_id)
+    THEN
+        NEW.company_id := match_company(NEW.company_name);
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE 'plpgsql';
+
+CREATE TRIGGER assign_company_occupations
+    BEFORE INSERT OR UPDATE ON occupations
+    FOR EACH ROW EXECUTE PROCEDURE assign_company();
+
+CREATE TRIGGER assign_company_registration_requests
+    BEFORE INSERT OR UPDATE ON registration_requests
+    FOR EACH ROW EXECUTE PROCEDURE assign_company();
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON TABLE companies TO orca;
+GRANT SELECT, INSERT, UPDATE, DELETE ON TABLE company_aliases TO orca;
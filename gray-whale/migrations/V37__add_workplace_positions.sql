@@ -0,0 +1,26 @@
+CREATE TABLE workplace_positions
+    ( id UUID PRIMARY KEY DEFAULT UUID_GENERATE_V4()
+    , workplace_id UUID NOT NULL REFERENCES workplaces(id) ON DELETE CASCADE
+    , member_id UUID NOT NULL REFERENCES members(id) ON DELETE CASCADE
+    , position TEXT NOT NULL CHECK (position IN ('rep', 'deputy', 'steward', 'safety_officer'))
+    , started_on DATE NOT NULL DEFAULT CURRENT_DATE
+    , ended_on DATE
+    , note TEXT
+    , created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    , updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+
+CREATE INDEX workplace_positions_workplace_id ON workplace_positions(workplace_id);
+CREATE INDEX workplace_positions_member_id ON workplace_positions(member_id);
+CREATE UNIQUE INDEX workplace_positions_held ON workplace_positions(workplace_id, member_id, position)
+    WHERE ended_on IS NULL;
+
+CREATE TRIGGER update_updated_at_workplace_positions
+    BEFORE UPDATE ON workplace_positions
+    FOR EACH ROW EXECUTE PROCEDURE update_updated_at();
+
+COMMENT ON TABLE workplace_positions IS 'Positions members hold in workplace including past ones';
+COMMENT ON COLUMN workplace_positions.position IS 'rep, deputy, steward or safety_officer. Reps and deputies can see and manage members of workplace even when they do not belong to it themselves';
+COMMENT ON COLUMN workplace_positions.ended_on IS 'Last day in position, NULL while member holds it';
+
+GRANT SELECT, INSERT, UPDATE ON TABLE workplace_positions TO orca;
@@ -0,0 +1,7 @@
+ALTER TABLE workplaces
+    ADD COLUMN archived_at TIMESTAMPTZ;
+
+COMMENT ON COLUMN workplaces.archived_at IS 'Workplace was closed down. Members and positions are kept for history';
+
+-- Workplaces are deleted after being merged into another one
+GRANT DELETE ON TABLE workplaces TO orca;
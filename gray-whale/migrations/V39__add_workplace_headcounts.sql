@@ -0,0 +1,15 @@
+CREATE TABLE workplace_headcounts
+    ( id UUID PRIMARY KEY DEFAULT UUID_GENERATE_V4()
+    , workplace_id UUID NOT NULL REFERENCES workplaces(id) ON DELETE CASCADE
+    , headcount INTEGER NOT NULL CHECK (headcount > 0)
+    , estimated_on DATE NOT NULL DEFAULT CURRENT_DATE
+    , note TEXT
+    , created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+
+CREATE INDEX workplace_headcounts_workplace_id ON workplace_headcounts(workplace_id, estimated_on);
+
+COMMENT ON TABLE workplace_headcounts IS 'Estimates of number of people working in workplace entered by reps, used to compute organizing density';
+COMMENT ON COLUMN workplace_headcounts.estimated_on IS 'Estimate is valid from this day until the next one';
+
+GRANT SELECT, INSERT ON TABLE workplace_headcounts TO orca;
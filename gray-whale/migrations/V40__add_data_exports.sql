@@ -0,0 +1,23 @@
+CREATE TABLE data_exports
+    ( id UUID PRIMARY KEY DEFAULT UUID_GENERATE_V4()
+    , exported_by UUID NOT NULL
+    , exported_by_email TEXT NOT NULL
+    , resource TEXT NOT NULL
+    , selection TEXT NOT NULL
+    , columns TEXT[] NOT NULL
+    , format TEXT NOT NULL
+    , row_count INTEGER NOT NULL
+    , created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+
+CREATE INDEX data_exports_created_at ON data_exports(created_at);
+CREATE INDEX data_exports_exported_by ON data_exports(exported_by);
+
+COMMENT ON TABLE data_exports IS 'Audit trail of exports of personal data to spreadsheets';
+COMMENT ON COLUMN data_exports.exported_by IS 'OIDC subject of user who downloaded the export';
+COMMENT ON COLUMN data_exports.resource IS 'members or applications';
+COMMENT ON COLUMN data_exports.selection IS 'Which list was exported, for instance current or workplace:<id>';
+COMMENT ON COLUMN data_exports.format IS 'csv or xlsx';
+
+-- Audit trail is append only
+GRANT SELECT, INSERT ON TABLE data_exports TO orca;
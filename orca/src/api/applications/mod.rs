@@ -3,19 +3,21 @@ use log::error;
 use rocket::serde::json::Json;
 use rocket::{Route, State, delete, get, patch, post, routes};
 use serde::{Deserialize, Serialize};
+use validator::Validate;
 
 use crate::config::Config;
 
 use crate::api::Response;
 use crate::api::files::FileInfo;
-use crate::api::members;
-use crate::data::{Id, Member, MemberNumber, RegistrationRequest};
+use crate::api::{members, validate_non_empty};
+use crate::data::{self, Company, Id, Member, MemberNumber, RegistrationRequest};
 use crate::db::{self, DbPool};
 use crate::processing::{Command, QueueSender};
 use crate::server::IpAddress;
 use crate::server::oid::{JwtToken, Provider, Role};
+use crate::validation::Validated;
 
-use crate::listmonk::subscribe_to_listmonk;
+use crate::listmonk::{self, lists_for_application};
 
 use super::{ApiError, SuccessResponse};
 
@@ -169,6 +171,21 @@ async fn list_processing(
     Ok(Json(summaries))
 }
 
+#[get("/needs-info")]
+async fn list_needs_info(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+) -> Response<Json<Vec<ProcessingSummary>>> {
+    oid_provider.require_role(&token, Role::ListApplications)?;
+
+    let summaries = query::list_needs_info_summaries()
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    Ok(Json(summaries))
+}
+
 #[derive(Debug, Serialize, sqlx::FromRow)]
 pub struct InvalidSummary {
     id: Id<RegistrationRequest>,
@@ -210,9 +227,14 @@ pub struct Detail {
     note: Option<String>,
     pub(crate) city: Option<String>,
     address: Option<String>,
-    postal_code: Option<String>,
+    pub(crate) postal_code: Option<String>,
     occupation: Option<String>,
     company_name: Option<String>,
+    /// Canonical company suggested for `company_name`
+    #[sqlx(default)]
+    company_id: Option<Id<Company>>,
+    #[sqlx(default)]
+    company: Option<String>,
     verification_sent_at: Option<DateTime<Utc>>,
     confirmed_at: Option<DateTime<Utc>>,
     registration_ip: Option<IpAddress>,
@@ -224,6 +246,9 @@ pub struct Detail {
     accepted_at: Option<DateTime<Utc>>,
     created_at: DateTime<Utc>,
     pub(crate) language: Option<String>,
+    /// Status of Listmonk subscription of accepted member
+    #[sqlx(default)]
+    listmonk_subscription: Option<String>,
 }
 
 #[get("/<id>")]
@@ -255,6 +280,7 @@ pub struct ApplicationStatusData {
     invalidated_at: Option<DateTime<Utc>>,
     accepted_at: Option<DateTime<Utc>>,
     member_id: Option<Id<Member>>,
+    info_requested_at: Option<DateTime<Utc>>,
 }
 
 impl ApplicationStatusData {
@@ -278,6 +304,9 @@ impl ApplicationStatusData {
         }
 
         if self.confirmed_at.is_some() {
+            if self.info_requested_at.is_some() {
+                return ApplicationStatus::NeedsInfo;
+            }
             return ApplicationStatus::InProcessing;
         }
 
@@ -285,16 +314,22 @@ impl ApplicationStatusData {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ApplicationStatus {
     WaitingForConfirmation,
     InProcessing,
+    NeedsInfo,
     Rejected,
     Accepted,
     Invalid,
 }
 
 impl ApplicationStatus {
+    #[expect(
+        clippy::unnecessary_trailing_comma,
+        reason = "trailing comma predates the lint"
+    )]
     fn assert_status(self, status: Self) -> Result<(), ApiError> {
         if self == status {
             return Ok(());
@@ -312,15 +347,23 @@ impl ApplicationStatus {
         self.assert_status(Self::InProcessing)
     }
 
+    pub fn assert_needs_info(self) -> Result<(), ApiError> {
+        self.assert_status(Self::NeedsInfo)
+    }
+
     pub fn assert_waiting_or_in_processing_or(self) -> Result<(), ApiError> {
-        if self.assert_in_proceesing().is_ok() || self.assert_waiting_for_confirmation().is_ok() {
+        if self.assert_in_proceesing().is_ok()
+            || self.assert_waiting_for_confirmation().is_ok()
+            || self.assert_needs_info().is_ok()
+        {
             return Ok(());
         }
 
         let message = format!(
-            "Application status must be `{:?}` or `{:?} but is `{:?}`.",
+            "Application status must be `{:?}`, `{:?}` or `{:?}` but is `{:?}`.",
             ApplicationStatus::WaitingForConfirmation,
             ApplicationStatus::InProcessing,
+            ApplicationStatus::NeedsInfo,
             self
         );
 
@@ -505,21 +548,30 @@ async fn accept(
         .execute(&mut *tx)
         .await?;
 
+    // Subscribing to Listmonk is done by processing queue
+    // so Listmonk outage doesn't prevent accepting new members
+    let subscribe = listmonk::is_enabled(config);
+    if subscribe {
+        listmonk::query::create_subscription_request(member_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
     // Since we return just member_id from the insert query
     // let's just do an extra query for application detail
     let detail: Detail = query::get_application(id).fetch_one(&mut *tx).await?;
 
-    // if config file doesn't contain listmonk_host value, let's skip listmonk subscribe step.
-    // DEV environment will be probably missing listmonk_host, unless devs setup their own testing listmonk instance.
-    // We don't want to call production listmonk during development
-    if config
-        .listmonk_host
-        .as_deref()
-        .is_some_and(|h| !h.is_empty())
+    tx.commit().await?;
+
+    // Member is accepted already. Pending subscription request
+    // is picked up again on next start when it can't be enqueued now
+    if subscribe
+        && let Err(err) = queue
+            .inner()
+            .send(Command::SubscribeToListmonk(member_id))
+            .await
     {
-        subscribe_to_listmonk(&detail, member_number, config, tx, member_id).await?;
-    } else {
-        tx.commit().await?;
+        error!("Failed to enqueue SubscribeToListmonk for member {member_id}: {err}");
     }
 
     queue
@@ -535,6 +587,15 @@ async fn accept(
         .send(Command::SendNotificationToTreasurer)
         .await?;
 
+    // Member is accepted already, welcome email is not worth failing the request
+    if let Err(err) = queue
+        .inner()
+        .send(Command::SendWelcomeEmail(member_id))
+        .await
+    {
+        error!("Failed to enqueue SendWelcomeEmail for member {member_id}: {err}");
+    }
+
     Ok(Json(detail))
 }
 
@@ -634,6 +695,97 @@ async fn hard_delete(
     Ok(SuccessResponse::Accepted)
 }
 
+#[derive(Debug, Serialize)]
+pub struct ListmonkPreview {
+    lists: Vec<u32>,
+}
+
+#[get("/<id>/listmonk-lists")]
+async fn preview_listmonk_lists(
+    config: &State<Config>,
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<RegistrationRequest>,
+) -> Response<Json<ListmonkPreview>> {
+    oid_provider.require_role(&token, Role::ViewApplication)?;
+
+    let detail = query::get_application(id)
+        .fetch_one(db_pool.inner())
+        .await?;
+
+    Ok(Json(ListmonkPreview {
+        lists: lists_for_application(&detail, &config.listmonk_routing),
+    }))
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct InfoRequest {
+    id: Id<data::InfoRequest>,
+    question: String,
+    answer: Option<String>,
+    created_at: DateTime<Utc>,
+    answered_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[serde(crate = "rocket::serde")]
+pub struct NewInfoRequest {
+    #[validate(custom(function = "validate_non_empty"))]
+    question: String,
+}
+
+#[post("/<id>/request-info", format = "json", data = "<new_info_request>")]
+async fn request_info(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    queue: &State<QueueSender>,
+    id: Id<RegistrationRequest>,
+    new_info_request: Validated<Json<NewInfoRequest>>,
+) -> Response<Json<InfoRequest>> {
+    oid_provider.require_role(&token, Role::ResolveApplications)?;
+
+    let mut tx = db_pool.inner().begin().await?;
+
+    // only applications reviewers are working on can be put on hold
+    query::get_application_status_data(id)
+        .fetch_one(&mut *tx)
+        .await?
+        .to_status()
+        .assert_in_proceesing()?;
+
+    let info_request = query::create_info_request(id, &new_info_request.into_inner())
+        .fetch_one(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    // Question is delivered to applicant by email
+    queue
+        .inner()
+        .send(Command::SendInfoRequest(info_request.id))
+        .await?;
+
+    Ok(Json(info_request))
+}
+
+#[get("/<id>/info-requests")]
+async fn list_info_requests(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<RegistrationRequest>,
+) -> Response<Json<Vec<InfoRequest>>> {
+    oid_provider.require_role(&token, Role::ViewApplication)?;
+
+    let info_requests = query::list_info_requests(id)
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    Ok(Json(info_requests))
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct Note {
@@ -663,6 +815,7 @@ pub fn routes() -> Vec<Route> {
         list,
         list_unverified,
         list_processing,
+        list_needs_info,
         list_accepted,
         list_invalid,
         resend_email,
@@ -677,5 +830,70 @@ pub fn routes() -> Vec<Route> {
         accept,
         hard_delete,
         update_note,
+        request_info,
+        list_info_requests,
+        preview_listmonk_lists,
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn confirmed(info_requested: bool) -> ApplicationStatusData {
+        let now = Utc::now();
+        ApplicationStatusData {
+            id: Id::from(Uuid::nil()),
+            created_at: now,
+            confirmed_at: Some(now),
+            rejected_at: None,
+            invalidated_at: None,
+            accepted_at: None,
+            member_id: None,
+            info_requested_at: info_requested.then_some(now),
+        }
+    }
+
+    #[test]
+    fn open_info_request_puts_application_on_hold() {
+        assert_eq!(
+            confirmed(false).to_status(),
+            ApplicationStatus::InProcessing
+        );
+        assert_eq!(confirmed(true).to_status(), ApplicationStatus::NeedsInfo);
+    }
+
+    #[test]
+    fn rejection_wins_over_open_info_request() {
+        let data = ApplicationStatusData {
+            rejected_at: Some(Utc::now()),
+            ..confirmed(true)
+        };
+
+        assert_eq!(data.to_status(), ApplicationStatus::Rejected);
+    }
+
+    #[test]
+    fn applicant_can_correct_application_on_hold() {
+        ApplicationStatus::NeedsInfo
+            .assert_waiting_or_in_processing_or()
+            .unwrap();
+        assert!(ApplicationStatus::NeedsInfo.assert_in_proceesing().is_err());
+        assert!(
+            ApplicationStatus::Rejected
+                .assert_waiting_or_in_processing_or()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn question_must_not_be_blank() {
+        let question = |question: &str| NewInfoRequest {
+            question: question.to_string(),
+        };
+
+        question("Where do you work?").validate().unwrap();
+        assert!(question("  ").validate().is_err());
+    }
+}
@@ -4,8 +4,8 @@ use crate::{
 };
 
 use super::{
-    AcceptedSummary, ApplicationStatusData, Detail, FileInfo, InvalidSummary, Note,
-    ProcessingSummary, RejectedSummary, Summary, UnverifiedSummary,
+    AcceptedSummary, ApplicationStatusData, Detail, FileInfo, InfoRequest, InvalidSummary,
+    NewInfoRequest, Note, ProcessingSummary, RejectedSummary, Summary, UnverifiedSummary,
 };
 use crate::data::{Id, RegistrationRequest};
 
@@ -68,6 +68,26 @@ ORDER BY confirmed_at DESC
     )
 }
 
+pub fn list_needs_info_summaries<'a>() -> QueryAs<'a, ProcessingSummary> {
+    sqlx::query_as(
+        "
+SELECT id
+, email
+, first_name
+, last_name
+, phone_number
+, note
+, city
+, company_name
+, registration_local
+, created_at
+, confirmed_at
+FROM registration_requests_needs_info
+ORDER BY confirmed_at DESC
+",
+    )
+}
+
 pub fn list_accepted_summaries<'a>() -> QueryAs<'a, AcceptedSummary> {
     sqlx::query_as(
         "
@@ -145,6 +165,8 @@ SELECT rr.id
 , rr.postal_code
 , rr.occupation
 , rr.company_name
+, rr.company_id
+, c.name AS company
 , rr.verification_sent_at
 , rr.confirmed_at
 , rr.registration_ip
@@ -156,8 +178,11 @@ SELECT rr.id
 , rr.created_at
 , rr.registration_local AS language
 , m.created_at AS accepted_at
+, lr.status AS listmonk_subscription
 FROM registration_requests AS rr
 LEFT JOIN members AS m ON rr.id = m.registration_request_id
+LEFT JOIN listmonk_subscription_requests AS lr ON m.id = lr.member_id
+LEFT JOIN companies AS c ON c.id = rr.company_id
 WHERE rr.id = $1
 ",
     )
@@ -353,6 +378,8 @@ RETURNING id
     .bind(id)
 }
 
+/// Application is locked until the end of transaction
+/// so that concurrent changes of its status are serialized
 pub fn get_application_status_data<'a>(
     id: Id<RegistrationRequest>,
 ) -> QueryAs<'a, ApplicationStatusData> {
@@ -365,9 +392,15 @@ SELECT rr.id
 , rr.invalidated_at
 , m.created_at AS accepted_at
 , m.id AS member_id
+, ( SELECT MAX(ri.created_at)
+    FROM registration_info_requests AS ri
+    WHERE ri.registration_request_id = rr.id
+    AND ri.answered_at IS NULL
+  ) AS info_requested_at
 FROM registration_requests AS rr
 LEFT JOIN members AS m ON rr.id = m.registration_request_id
 WHERE rr.id = $1
+FOR UPDATE OF rr
 ",
     )
     .bind(id)
@@ -442,9 +475,10 @@ pub fn attach_occupation<'a>(
 INSERT INTO occupations
 ( member_id
 , company_name
+, company_id
 , position
 )
-SELECT $2, rr.company_name, rr.occupation
+SELECT $2, rr.company_name, rr.company_id, rr.occupation
 FROM registration_requests as rr
 WHERE rr.id = $1
 ",
@@ -517,3 +551,41 @@ RETURNING id
     .bind(id)
     .bind(new_note.note.as_deref())
 }
+
+pub fn create_info_request(
+    id: Id<RegistrationRequest>,
+    info_request: &NewInfoRequest,
+) -> QueryAs<'_, InfoRequest> {
+    sqlx::query_as(
+        "
+INSERT INTO registration_info_requests
+( registration_request_id
+, question
+)
+VALUES ( $1, $2 )
+RETURNING id
+, question
+, answer
+, created_at
+, answered_at
+",
+    )
+    .bind(id)
+    .bind(&info_request.question)
+}
+
+pub fn list_info_requests<'a>(id: Id<RegistrationRequest>) -> QueryAs<'a, InfoRequest> {
+    sqlx::query_as(
+        "
+SELECT id
+, question
+, answer
+, created_at
+, answered_at
+FROM registration_info_requests
+WHERE registration_request_id = $1
+ORDER BY created_at DESC
+",
+    )
+    .bind(id)
+}
@@ -0,0 +1,435 @@
+//! Registry of canonical companies
+//!
+//! Company names filled in by applicants and members are free text.
+//! Each company has aliases which are compared after normalization
+//! (see `company_normalize` in database) so that "Google s.r.o."
+//! and "google" are recognized as the same employer. Occupations and applications
+//! are linked to company automatically by database trigger when their company name is set.
+use chrono::{DateTime, Utc};
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{Route, State, delete, get, patch, post, routes};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use validator::Validate;
+
+pub mod query;
+
+use super::{ApiError, SuccessResponse};
+use crate::api::Response;
+use crate::data::{Company, Id};
+use crate::db::{self, DbPool};
+use crate::server::oid::{JwtToken, Provider, Role};
+use crate::validation::Validated;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct CompanySummary {
+    id: Id<Company>,
+    name: String,
+    registration_number: Option<String>,
+    created_at: DateTime<Utc>,
+    /// Current members currently employed by the company
+    member_count: i64,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Alias {
+    id: Id<Alias>,
+    name: String,
+    normalized_name: String,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompanyDetail {
+    #[serde(flatten)]
+    company: CompanySummary,
+    aliases: Vec<Alias>,
+}
+
+async fn load_detail(db_pool: &DbPool, id: Id<Company>) -> Response<Json<CompanyDetail>> {
+    let company = query::detail(id)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or(Status::NotFound)?;
+    let aliases = query::list_aliases(id).fetch_all(db_pool).await?;
+
+    Ok(Json(CompanyDetail { company, aliases }))
+}
+
+/// Add alias and link all occupations and applications with matching company name
+async fn add_alias(connection: &mut PgConnection, id: Id<Company>, name: &str) -> Response<Alias> {
+    let alias = query::add_alias(id, name)
+        .fetch_optional(&mut *connection)
+        .await?
+        .ok_or_else(|| {
+            ApiError::data_conflict(&format!("Name {name} is an alias of another company"))
+        })?;
+
+    query::link_occupations(id, name)
+        .execute(&mut *connection)
+        .await?;
+    query::link_applications(id, name)
+        .execute(&mut *connection)
+        .await?;
+
+    Ok(alias)
+}
+
+/// Both name and registration number are unique
+fn duplicate_company() -> ApiError {
+    ApiError::data_conflict("Company with the same name or registration number already exists")
+}
+
+fn check_name(name: &str, message: &str) -> Result<(), ApiError> {
+    if name.trim().is_empty() {
+        return Err(ApiError::bad_request(message));
+    }
+    Ok(())
+}
+
+/// `q` filters companies by their aliases
+#[get("/?<q>")]
+async fn list_all(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    q: Option<&str>,
+) -> Response<Json<Vec<CompanySummary>>> {
+    oid_provider.require_role(&token, Role::ListMembers)?;
+
+    let summaries = query::list_summaries(q.filter(|q| !q.trim().is_empty()))
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    Ok(Json(summaries))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[serde(crate = "rocket::serde")]
+pub struct NewCompany {
+    #[validate(length(min = 1))]
+    name: String,
+    registration_number: Option<String>,
+    /// Other names of company, canonical name is always an alias
+    #[serde(default)]
+    aliases: Vec<String>,
+}
+
+#[post("/", format = "json", data = "<company>")]
+async fn create_company(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    company: Validated<Json<NewCompany>>,
+) -> Response<Json<CompanyDetail>> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    let company = company.into_inner().into_inner();
+    check_name(&company.name, "Company name can't be empty")?;
+    for alias in &company.aliases {
+        check_name(alias, "Alias can't be empty")?;
+    }
+
+    let mut tx = db_pool.begin().await?;
+
+    let result = query::create_company(&company).fetch_one(&mut *tx).await;
+    if db::fail_duplicated(&result) {
+        return Err(duplicate_company());
+    }
+    let (id,) = result?;
+    for name in std::iter::once(&company.name).chain(&company.aliases) {
+        add_alias(&mut tx, id, name).await?;
+    }
+
+    tx.commit().await?;
+
+    load_detail(db_pool, id).await
+}
+
+/// Unlinked company names ordered from the most common
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct UnmatchedName {
+    normalized_name: String,
+    /// The most common spelling
+    name: String,
+    spellings: Vec<String>,
+    occupations: i64,
+    applications: i64,
+}
+
+#[get("/unmatched")]
+async fn list_unmatched(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+) -> Response<Json<Vec<UnmatchedName>>> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    let names = query::list_unmatched().fetch_all(db_pool.inner()).await?;
+
+    Ok(Json(names))
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Suggestion {
+    id: Id<Company>,
+    name: String,
+    /// Trigram similarity of the best matching alias between 0 and 1
+    similarity: f32,
+}
+
+/// Companies which company name filled in by member likely refers to
+#[get("/suggestions?<name>")]
+async fn suggestions(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    name: &str,
+) -> Response<Json<Vec<Suggestion>>> {
+    oid_provider.require_any_role(&token, &[Role::ListMembers, Role::ViewApplication])?;
+
+    let suggestions = query::suggest(name).fetch_all(db_pool.inner()).await?;
+
+    Ok(Json(suggestions))
+}
+
+#[get("/<id>")]
+async fn detail(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Company>,
+) -> Response<Json<CompanyDetail>> {
+    oid_provider.require_role(&token, Role::ListMembers)?;
+
+    load_detail(db_pool, id).await
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct UpdateCompany {
+    name: Option<String>,
+    registration_number: Option<String>,
+}
+
+/// New name becomes an alias, previous name is kept as an alias too.
+/// Occupations and applications are only linked again when the name changes.
+#[patch("/<id>", format = "json", data = "<company>")]
+async fn update_company(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Company>,
+    company: Json<UpdateCompany>,
+) -> Response<Json<CompanyDetail>> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    if let Some(name) = &company.name {
+        check_name(name, "Company name can't be empty")?;
+    }
+
+    let mut tx = db_pool.begin().await?;
+
+    let current = query::detail(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(Status::NotFound)?;
+
+    let result = query::update_company(id, &company)
+        .fetch_optional(&mut *tx)
+        .await;
+    if db::fail_duplicated(&result) {
+        return Err(duplicate_company());
+    }
+    let (name,) = result?.ok_or(Status::NotFound)?;
+    if name != current.name {
+        add_alias(&mut tx, id, &name).await?;
+    }
+
+    tx.commit().await?;
+
+    load_detail(db_pool, id).await
+}
+
+/// Linked occupations and applications become unmatched
+#[delete("/<id>")]
+async fn delete_company(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Company>,
+) -> Response<SuccessResponse> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    let result = query::delete_company(id).execute(db_pool.inner()).await?;
+    if result.rows_affected() == 0 {
+        return Err(Status::NotFound.into());
+    }
+
+    Ok(SuccessResponse::NoContent)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct NewAlias {
+    name: String,
+}
+
+/// Normalize company name to this company. All occupations and applications
+/// with the same name are linked to the company.
+#[post("/<id>/aliases", format = "json", data = "<alias>")]
+async fn create_alias(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Company>,
+    alias: Json<NewAlias>,
+) -> Response<Json<CompanyDetail>> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    check_name(&alias.name, "Alias can't be empty")?;
+
+    query::detail(id)
+        .fetch_optional(db_pool.inner())
+        .await?
+        .ok_or(Status::NotFound)?;
+
+    let mut tx = db_pool.begin().await?;
+    add_alias(&mut tx, id, &alias.name).await?;
+    tx.commit().await?;
+
+    load_detail(db_pool, id).await
+}
+
+/// Already linked occupations and applications stay linked
+#[delete("/<id>/aliases/<alias_id>")]
+async fn delete_alias(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Company>,
+    alias_id: Id<Alias>,
+) -> Response<SuccessResponse> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    let result = query::delete_alias(id, alias_id)
+        .execute(db_pool.inner())
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(Status::NotFound.into());
+    }
+
+    Ok(SuccessResponse::NoContent)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Merge {
+    company_ids: Vec<Id<Company>>,
+}
+
+impl Merge {
+    fn check(&self, id: Id<Company>) -> Result<(), ApiError> {
+        if self.company_ids.contains(&id) {
+            return Err(ApiError::bad_request("Company can't be merged into itself"));
+        }
+        Ok(())
+    }
+}
+
+/// Merge duplicate companies into company `id`. Their aliases,
+/// occupations and applications are moved and duplicates are deleted.
+#[post("/<id>/merge", format = "json", data = "<merge>")]
+async fn merge(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Company>,
+    merge: Json<Merge>,
+) -> Response<Json<CompanyDetail>> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    merge.check(id)?;
+
+    query::detail(id)
+        .fetch_optional(db_pool.inner())
+        .await?
+        .ok_or(Status::NotFound)?;
+
+    let mut tx = db_pool.begin().await?;
+
+    query::merge_aliases(id, &merge.company_ids)
+        .execute(&mut *tx)
+        .await?;
+    query::merge_occupations(id, &merge.company_ids)
+        .execute(&mut *tx)
+        .await?;
+    query::merge_applications(id, &merge.company_ids)
+        .execute(&mut *tx)
+        .await?;
+    query::delete_companies(&merge.company_ids)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    load_detail(db_pool, id).await
+}
+
+#[expect(clippy::redundant_type_annotations, reason = "rocket macro expansion")]
+pub fn routes() -> Vec<Route> {
+    routes![
+        list_all,
+        create_company,
+        list_unmatched,
+        suggestions,
+        detail,
+        update_company,
+        delete_company,
+        create_alias,
+        delete_alias,
+        merge,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blank_names_are_rejected() {
+        check_name("Google s.r.o.", "empty").unwrap();
+        assert!(check_name("", "empty").is_err());
+        assert!(check_name("  ", "empty").is_err());
+    }
+
+    #[test]
+    fn company_is_not_merged_into_itself() {
+        let id = Id::from(uuid::Uuid::new_v4());
+        let other = Id::from(uuid::Uuid::new_v4());
+
+        Merge {
+            company_ids: vec![other],
+        }
+        .check(id)
+        .unwrap();
+        assert!(
+            Merge {
+                company_ids: vec![other, id],
+            }
+            .check(id)
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn new_company_needs_name() {
+        let company: NewCompany = rocket::serde::json::from_str(r#"{"name": "Google"}"#).unwrap();
+        assert!(company.aliases.is_empty());
+        company.validate().unwrap();
+
+        let company: NewCompany = rocket::serde::json::from_str(r#"{"name": ""}"#).unwrap();
+        assert!(company.validate().is_err());
+    }
+}
@@ -0,0 +1,257 @@
+use super::{Alias, CompanySummary, NewCompany, Suggestion, UnmatchedName, UpdateCompany};
+use crate::data::{Company, Id};
+use crate::db::{Query, QueryAs};
+
+/// Company is counted for current members who currently work for it
+pub fn list_summaries(search: Option<&str>) -> QueryAs<'_, CompanySummary> {
+    sqlx::query_as(
+        "
+SELECT c.id
+    , c.name
+    , c.registration_number
+    , c.created_at
+    , (SELECT COUNT(DISTINCT o.member_id)
+        FROM occupations o
+        INNER JOIN membership_periods p ON p.member_id = o.member_id AND p.left_at IS NULL
+        WHERE o.company_id = c.id
+        AND o.is_current
+    ) AS member_count
+FROM companies c
+WHERE $1::TEXT IS NULL
+    OR EXISTS (
+        SELECT 1 FROM company_aliases a
+        WHERE a.company_id = c.id
+        AND a.normalized_name LIKE '%' || company_normalize($1) || '%'
+    )
+ORDER BY c.name
+",
+    )
+    .bind(search)
+}
+
+pub fn detail<'a>(id: Id<Company>) -> QueryAs<'a, CompanySummary> {
+    sqlx::query_as(
+        "
+SELECT c.id
+    , c.name
+    , c.registration_number
+    , c.created_at
+    , (SELECT COUNT(DISTINCT o.member_id)
+        FROM occupations o
+        INNER JOIN membership_periods p ON p.member_id = o.member_id AND p.left_at IS NULL
+        WHERE o.company_id = c.id
+        AND o.is_current
+    ) AS member_count
+FROM companies c
+WHERE c.id = $1
+",
+    )
+    .bind(id)
+}
+
+pub fn create_company(company: &NewCompany) -> QueryAs<'_, (Id<Company>,)> {
+    sqlx::query_as(
+        "
+INSERT INTO companies
+    ( name
+    , registration_number
+    )
+VALUES ($1, $2)
+RETURNING id
+",
+    )
+    .bind(&company.name)
+    .bind(&company.registration_number)
+}
+
+pub fn update_company(id: Id<Company>, company: &UpdateCompany) -> QueryAs<'_, (String,)> {
+    sqlx::query_as(
+        "
+UPDATE companies
+SET name = COALESCE($2, name)
+    , registration_number = COALESCE($3, registration_number)
+WHERE id = $1
+RETURNING name
+",
+    )
+    .bind(id)
+    .bind(&company.name)
+    .bind(&company.registration_number)
+}
+
+pub fn delete_company<'a>(id: Id<Company>) -> Query<'a> {
+    sqlx::query(
+        "
+DELETE FROM companies
+WHERE id = $1
+",
+    )
+    .bind(id)
+}
+
+pub fn list_aliases<'a>(id: Id<Company>) -> QueryAs<'a, Alias> {
+    sqlx::query_as(
+        "
+SELECT id
+    , name
+    , normalized_name
+    , created_at
+FROM company_aliases
+WHERE company_id = $1
+ORDER BY name
+",
+    )
+    .bind(id)
+}
+
+/// Nothing is added when company already has alias with the same normalized name
+pub fn add_alias(id: Id<Company>, name: &str) -> QueryAs<'_, Alias> {
+    sqlx::query_as(
+        "
+INSERT INTO company_aliases
+    ( company_id
+    , name
+    )
+VALUES ($1, $2)
+ON CONFLICT (normalized_name) DO UPDATE
+SET company_id = company_aliases.company_id
+WHERE company_aliases.company_id = $1
+RETURNING id
+    , name
+    , normalized_name
+    , created_at
+",
+    )
+    .bind(id)
+    .bind(name)
+}
+
+pub fn delete_alias<'a>(id: Id<Company>, alias_id: Id<Alias>) -> Query<'a> {
+    sqlx::query(
+        "
+DELETE FROM company_aliases
+WHERE id = $2
+    AND company_id = $1
+",
+    )
+    .bind(id)
+    .bind(alias_id)
+}
+
+/// Assign company to all occupations and applications whose company name matches `name` exactly.
+/// Links made before (automatically or by hand) are overwritten.
+pub fn link_occupations(id: Id<Company>, name: &str) -> Query<'_> {
+    sqlx::query(
+        "
+UPDATE occupations
+SET company_id = $1
+WHERE company_normalize(company_name) = company_normalize($2)
+",
+    )
+    .bind(id)
+    .bind(name)
+}
+
+pub fn link_applications(id: Id<Company>, name: &str) -> Query<'_> {
+    sqlx::query(
+        "
+UPDATE registration_requests
+SET company_id = $1
+WHERE company_normalize(company_name) = company_normalize($2)
+",
+    )
+    .bind(id)
+    .bind(name)
+}
+
+/// Move aliases, occupations and applications of `merged` companies to company `id`
+pub fn merge_aliases(id: Id<Company>, merged: &[Id<Company>]) -> Query<'_> {
+    sqlx::query(
+        "
+UPDATE company_aliases
+SET company_id = $1
+WHERE company_id = ANY($2)
+",
+    )
+    .bind(id)
+    .bind(merged)
+}
+
+pub fn merge_occupations(id: Id<Company>, merged: &[Id<Company>]) -> Query<'_> {
+    sqlx::query(
+        "
+UPDATE occupations
+SET company_id = $1
+WHERE company_id = ANY($2)
+",
+    )
+    .bind(id)
+    .bind(merged)
+}
+
+pub fn merge_applications(id: Id<Company>, merged: &[Id<Company>]) -> Query<'_> {
+    sqlx::query(
+        "
+UPDATE registration_requests
+SET company_id = $1
+WHERE company_id = ANY($2)
+",
+    )
+    .bind(id)
+    .bind(merged)
+}
+
+pub fn delete_companies(ids: &[Id<Company>]) -> Query<'_> {
+    sqlx::query(
+        "
+DELETE FROM companies
+WHERE id = ANY($1)
+",
+    )
+    .bind(ids)
+}
+
+/// Free text company names not linked to any company grouped by normalized name
+pub fn list_unmatched<'a>() -> QueryAs<'a, UnmatchedName> {
+    sqlx::query_as(
+        "
+WITH names AS (
+    SELECT company_name, 1 AS occupation, 0 AS application
+    FROM occupations
+    WHERE company_id IS NULL
+    UNION ALL
+    SELECT company_name, 0, 1
+    FROM registration_requests
+    WHERE company_id IS NULL
+)
+SELECT company_normalize(company_name) AS normalized_name
+    , mode() WITHIN GROUP (ORDER BY company_name) AS name
+    , array_agg(DISTINCT company_name) AS spellings
+    , SUM(occupation) AS occupations
+    , SUM(application) AS applications
+FROM names
+WHERE company_normalize(company_name) <> ''
+GROUP BY company_normalize(company_name)
+ORDER BY COUNT(*) DESC, normalized_name
+",
+    )
+}
+
+/// Companies with similar aliases ordered by best similarity
+pub fn suggest(name: &str) -> QueryAs<'_, Suggestion> {
+    sqlx::query_as(
+        "
+SELECT c.id
+    , c.name
+    , MAX(similarity(a.normalized_name, company_normalize($1))) AS similarity
+FROM company_aliases a
+INNER JOIN companies c ON c.id = a.company_id
+WHERE a.normalized_name % company_normalize($1)
+GROUP BY c.id
+    , c.name
+ORDER BY similarity DESC
+LIMIT 5
+",
+    )
+    .bind(name)
+}
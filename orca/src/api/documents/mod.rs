@@ -0,0 +1,410 @@
+//! Printable documents of members
+//!
+//! Documents are printed with xelatex the same way as registration forms.
+//! Every value coming from the database is escaped with `TexEscape`.
+use std::process::ExitStatus;
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use log::{error, info};
+use phf::phf_map;
+use rocket::http::{ContentType, Status};
+use rocket::request::FromParam;
+use rocket::response::Responder;
+use rocket::{Route, State, get, routes};
+use thiserror::Error;
+use tokio::{fs, io};
+use uuid::Uuid;
+
+mod query;
+
+use super::workplaces::scope::WorkplaceScope;
+use super::{ApiError, Response};
+use crate::config::Config;
+use crate::data::{Id, Member, MemberNumber};
+use crate::db::DbPool;
+use crate::dues::format_amount;
+use crate::dues::status::DUES_CURRENCY;
+use crate::media::{self, TexEscape};
+use crate::server::oid::{JwtToken, Provider, Role};
+use crate::spayd;
+
+#[derive(Debug, Error)]
+pub(crate) enum Error {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Tex command failed: {0}")]
+    Tex(ExitStatus),
+    #[error("QR code error: {0}")]
+    Qr(#[from] spayd::Error),
+}
+
+impl From<Error> for ApiError {
+    fn from(err: Error) -> Self {
+        error!("Failed to print document: {err}");
+        Status::InternalServerError.into()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// Card with member number and QR code linking to member in admin
+    Card,
+    /// Letter confirming membership, for employers and such
+    Membership,
+    /// Confirmation of dues paid during a calendar year, for tax deduction
+    Dues,
+}
+
+impl Kind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Card => "card",
+            Self::Membership => "membership-confirmation",
+            Self::Dues => "dues-confirmation",
+        }
+    }
+
+    /// Name and content of TeX file
+    fn template(self) -> (&'static str, &'static str) {
+        match self {
+            Self::Card => ("card.tex", include_str!("../../../latex/card.tex")),
+            Self::Membership => (
+                "membership.tex",
+                include_str!("../../../latex/membership.tex"),
+            ),
+            Self::Dues => ("dues.tex", include_str!("../../../latex/dues.tex")),
+        }
+    }
+}
+
+impl<'a> FromParam<'a> for Kind {
+    type Error = &'a str;
+
+    fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+        match param {
+            "card" => Ok(Self::Card),
+            "membership-confirmation" => Ok(Self::Membership),
+            "dues-confirmation" => Ok(Self::Dues),
+            _ => Err(param),
+        }
+    }
+}
+
+const DEFAULT_LOCALIZATION: &str = include_str!("../../../latex/documents.en.tex");
+static LOCALIZATIONS: phf::Map<&'static str, &'static str> = phf_map!(
+    "en" => DEFAULT_LOCALIZATION,
+    "cs" => include_str!("../../../latex/documents.cs.tex"),
+);
+
+/// Member the document is printed for
+#[derive(Debug, sqlx::FromRow)]
+pub struct Holder {
+    id: Id<Member>,
+    member_number: MemberNumber,
+    first_name: Option<String>,
+    last_name: Option<String>,
+    date_of_birth: Option<NaiveDate>,
+    address: Option<String>,
+    city: Option<String>,
+    postal_code: Option<String>,
+    language: Option<String>,
+    member_since: Option<DateTime<Utc>>,
+    left_at: Option<DateTime<Utc>>,
+}
+
+/// Dues paid by member during the year
+#[derive(Debug, Clone, Copy)]
+struct PaidDues {
+    year: i32,
+    amount: i64,
+}
+
+fn print_tex_data(holder: &Holder, today: NaiveDate, dues: Option<PaidDues>) -> String {
+    format!(
+        "\
+\\def\\Name{{{}}}
+\\def\\Surname{{{}}}
+\\def\\MemberNumber{{{}}}
+\\def\\DateOfBirth{{{}}}
+\\def\\Address{{{}}}
+\\def\\City{{{}}}
+\\def\\Zipcode{{{}}}
+\\def\\MemberFrom{{{}}}
+\\def\\MemberUntil{{{}}}
+\\def\\IssuedOn{{{}}}
+\\def\\Year{{{}}}
+\\def\\Amount{{{}}}
+\\def\\Currency{{{}}}
+",
+        holder.first_name.as_deref().escape_tex(),
+        holder.last_name.as_deref().escape_tex(),
+        // Numbers, dates and amounts don't contain any dangerous chars
+        holder.member_number,
+        holder.date_of_birth.escape_tex(),
+        holder.address.as_deref().escape_tex(),
+        holder.city.as_deref().escape_tex(),
+        holder.postal_code.as_deref().escape_tex(),
+        holder.member_since.map(|at| at.date_naive()).escape_tex(),
+        holder.left_at.map(|at| at.date_naive()).escape_tex(),
+        today.escape_tex(),
+        dues.map(|dues| dues.year.to_string()).escape_tex(),
+        dues.map(|dues| format_amount(dues.amount)).escape_tex(),
+        DUES_CURRENCY,
+    )
+}
+
+/// Members scanning the card are taken to member detail in admin
+fn card_qr_payload(config: &Config, holder: &Holder) -> String {
+    match &config.admin_host {
+        Some(admin_host) => format!("{admin_host}/members/{}", holder.id),
+        None => holder.member_number.to_string(),
+    }
+}
+
+/// Print the document in a directory which is removed afterwards
+async fn print_pdf(
+    config: &Config,
+    kind: Kind,
+    tex_data: &str,
+    language: Option<&str>,
+    qr_png: Option<Vec<u8>>,
+) -> Result<Vec<u8>, Error> {
+    let dir = format!("data/documents/{}", Uuid::new_v4());
+    fs::create_dir_all(&dir).await?;
+
+    let pdf = print_pdf_in(config, &dir, kind, tex_data, language, qr_png).await;
+    fs::remove_dir_all(&dir).await?;
+
+    pdf
+}
+
+async fn print_pdf_in(
+    config: &Config,
+    dir: &str,
+    kind: Kind,
+    tex_data: &str,
+    language: Option<&str>,
+    qr_png: Option<Vec<u8>>,
+) -> Result<Vec<u8>, Error> {
+    info!("Printing {} pdf at {dir}", kind.as_str());
+
+    let (tex_name, tex) = kind.template();
+    let localization = language
+        .and_then(|language| LOCALIZATIONS.get(language))
+        .unwrap_or(&DEFAULT_LOCALIZATION);
+
+    fs::write(format!("{dir}/data.tex"), tex_data).await?;
+    fs::write(format!("{dir}/lang.tex"), localization).await?;
+    fs::write(
+        format!("{dir}/preamble.tex"),
+        include_str!("../../../latex/preamble.tex"),
+    )
+    .await?;
+    fs::write(format!("{dir}/{tex_name}"), tex).await?;
+    fs::write(
+        format!("{dir}/logo.png"),
+        include_bytes!("../../../latex/logo.png"),
+    )
+    .await?;
+    if let Some(png) = qr_png {
+        fs::write(format!("{dir}/qr.png"), png).await?;
+    }
+
+    let status = media::xelatex(&config.tex_exe, dir, tex_name).await?;
+    if !status.success() {
+        return Err(Error::Tex(status));
+    }
+
+    let pdf_name = tex_name.replace(".tex", ".pdf");
+    Ok(fs::read(format!("{dir}/{pdf_name}")).await?)
+}
+
+/// PDF downloaded as attachment
+#[derive(Debug)]
+pub struct Pdf {
+    data: Vec<u8>,
+    /// Without extension
+    name: String,
+}
+
+impl<'r> Responder<'r, 'static> for Pdf {
+    fn respond_to(self, request: &'r rocket::Request<'_>) -> rocket::response::Result<'static> {
+        let disposition = format!("attachment; filename=\"{}.pdf\"", self.name);
+
+        rocket::response::Response::build_from(self.data.respond_to(request)?)
+            .header(ContentType::PDF)
+            .raw_header("Content-Disposition", disposition)
+            .ok()
+    }
+}
+
+/// The oldest year dues can be confirmed for
+const MIN_DUES_YEAR: i32 = 1900;
+
+/// Year of dues confirmation, by default the previous one.
+/// Dues prepaid for the next year can be confirmed too.
+fn dues_year(year: Option<i32>, today: NaiveDate) -> Result<i32, ApiError> {
+    let year = year.unwrap_or(today.year() - 1);
+    if !(MIN_DUES_YEAR..=today.year() + 1).contains(&year) {
+        return Err(ApiError::bad_request(&format!(
+            "Year has to be between {MIN_DUES_YEAR} and {}",
+            today.year() + 1
+        )));
+    }
+
+    Ok(year)
+}
+
+/// Print document of given kind for member.
+/// Dues are confirmed for the given `year`, by default for the previous one.
+/// Year is validated for every kind of document, it's shared by all endpoints.
+pub(crate) async fn print(
+    config: &Config,
+    db_pool: &DbPool,
+    member_id: Id<Member>,
+    kind: Kind,
+    year: Option<i32>,
+) -> Result<Pdf, ApiError> {
+    let today = Utc::now().date_naive();
+    let year = dues_year(year, today)?;
+
+    let holder = query::get_holder(member_id)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or(Status::NotFound)?;
+
+    let mut qr_png = None;
+    let mut dues = None;
+    match kind {
+        Kind::Card => {
+            if holder.left_at.is_some() {
+                return Err(ApiError::data_conflict("Member has left"));
+            }
+            qr_png = Some(spayd::qr_png(&card_qr_payload(config, &holder)).map_err(Error::Qr)?);
+        }
+        Kind::Membership => {}
+        Kind::Dues => {
+            let (amount,) = query::paid_in_year(member_id, DUES_CURRENCY, year)
+                .fetch_one(db_pool)
+                .await?;
+            if amount == 0 {
+                return Err(ApiError::data_missing("payments in the year"));
+            }
+            dues = Some(PaidDues { year, amount });
+        }
+    }
+
+    let tex_data = print_tex_data(&holder, today, dues);
+    let data = print_pdf(config, kind, &tex_data, holder.language.as_deref(), qr_png).await?;
+
+    Ok(Pdf {
+        data,
+        name: format!("{}-{}", kind.as_str(), holder.member_number),
+    })
+}
+
+/// Dues confirmation requires treasurer who handles dues of all members like `/members/<id>/dues`.
+/// Other documents only need access to member detail limited to workplaces
+/// of representatives the same way as the detail itself.
+#[get("/<id>/documents/<kind>?<year>")]
+async fn member_document(
+    config: &State<Config>,
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Member>,
+    kind: Kind,
+    year: Option<i32>,
+) -> Response<Pdf> {
+    match kind {
+        Kind::Dues => {
+            oid_provider.require_role(&token, Role::Treasurer)?;
+        }
+        Kind::Card | Kind::Membership => {
+            oid_provider.require_role(&token, Role::ViewMember)?;
+            WorkplaceScope::of(db_pool, oid_provider, &token)
+                .await?
+                .require_member(db_pool, id)
+                .await?;
+        }
+    }
+
+    print(config, db_pool, id, kind, year).await
+}
+
+/// Routes mounted under `/members`
+#[expect(clippy::redundant_type_annotations, reason = "rocket macro expansion")]
+pub fn member_routes() -> Vec<Route> {
+    routes![member_document]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn holder() -> Holder {
+        Holder {
+            id: Id::from(Uuid::nil()),
+            member_number: rocket::serde::json::from_str("42").unwrap(),
+            first_name: Some("Jana".to_string()),
+            last_name: Some("Nováková".to_string()),
+            date_of_birth: None,
+            address: Some("Náměstí 1 & 2".to_string()),
+            city: Some("Brno".to_string()),
+            postal_code: Some("60200".to_string()),
+            language: Some("cs".to_string()),
+            member_since: DateTime::from_timestamp(1_700_000_000, 0),
+            left_at: None,
+        }
+    }
+
+    #[test]
+    fn tex_data_is_escaped() {
+        let mut holder = holder();
+        holder.last_name = Some("\\input{/etc/passwd}".to_string());
+        let today = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+
+        let data = print_tex_data(&holder, today, None);
+
+        assert!(data.contains("\\def\\Surname{\\textbackslash{}input\\{/etc/passwd\\}}\n"));
+        assert!(data.contains("\\def\\Address{Náměstí 1 \\& 2}\n"));
+        assert!(data.contains("\\def\\MemberFrom{2023-11-14}\n"));
+        assert!(data.contains("\\def\\MemberUntil{---}\n"));
+        assert!(data.contains("\\def\\Amount{---}\n"));
+    }
+
+    #[test]
+    fn tex_data_includes_paid_dues() {
+        let today = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+        let dues = PaidDues {
+            year: 2024,
+            amount: 180_000,
+        };
+
+        let data = print_tex_data(&holder(), today, Some(dues));
+
+        assert!(data.contains("\\def\\MemberNumber{42}\n"));
+        assert!(data.contains("\\def\\Year{2024}\n"));
+        assert!(data.contains("\\def\\Amount{1800.00}\n"));
+        assert!(data.contains("\\def\\IssuedOn{2025-01-15}\n"));
+    }
+
+    #[test]
+    fn dues_are_confirmed_for_previous_year_by_default() {
+        let today = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+
+        assert_eq!(dues_year(None, today).unwrap(), 2024);
+        assert_eq!(dues_year(Some(2026), today).unwrap(), 2026);
+        assert_eq!(dues_year(Some(1900), today).unwrap(), 1900);
+    }
+
+    #[test]
+    fn dues_year_is_bounded() {
+        let today = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+
+        dues_year(Some(2027), today).unwrap_err();
+        dues_year(Some(1899), today).unwrap_err();
+        dues_year(Some(i32::MAX), today).unwrap_err();
+        dues_year(Some(i32::MIN), today).unwrap_err();
+    }
+}
@@ -0,0 +1,43 @@
+use super::Holder;
+use crate::data::{Id, Member};
+use crate::db::QueryAs;
+
+/// `member_since` is the start of the latest membership period
+pub fn get_holder<'a>(id: Id<Member>) -> QueryAs<'a, Holder> {
+    sqlx::query_as(
+        "
+SELECT m.id
+    , m.member_number
+    , m.first_name
+    , m.last_name
+    , m.date_of_birth
+    , m.address
+    , m.city
+    , m.postal_code
+    , m.language
+    , (SELECT MAX(p.joined_at) FROM membership_periods p WHERE p.member_id = m.id) AS member_since
+    , m.left_at
+FROM members m
+WHERE m.id = $1
+",
+    )
+    .bind(id)
+}
+
+/// Total of payments in `currency` made during the calendar `year`
+pub fn paid_in_year(id: Id<Member>, currency: &str, year: i32) -> QueryAs<'_, (i64,)> {
+    sqlx::query_as(
+        "
+SELECT COALESCE(SUM(p.amount), 0)::BIGINT
+FROM payments p
+INNER JOIN members m ON m.member_number = p.member_number
+WHERE m.id = $1
+    AND p.currency = $2
+    AND p.paid_on >= MAKE_DATE($3, 1, 1)
+    AND p.paid_on < MAKE_DATE($3 + 1, 1, 1)
+",
+    )
+    .bind(id)
+    .bind(currency)
+    .bind(year)
+}
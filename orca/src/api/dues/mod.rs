@@ -0,0 +1,592 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use log::error;
+use rocket::data::{Data, ToByteUnit};
+use rocket::http::{ContentType, Status};
+use rocket::serde::json::Json;
+use rocket::{Route, State, delete, get, post, put, routes};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use validator::{Validate, ValidationError};
+
+pub mod query;
+
+use crate::api::{ApiError, Response, SuccessResponse, validate_non_empty};
+use crate::bank::{self, Match};
+use crate::config::Config;
+use crate::data::{self, Id, Member, MemberNumber};
+use crate::db::DbPool;
+use crate::dues::status::{
+    DUES_CURRENCY, DuesStatus, FeeSchedule, current_statuses, fetch_schedules, member_status,
+    payment_request,
+};
+use crate::processing::{Command, QueueSender};
+use crate::server::oid::{JwtToken, Provider, Role};
+use crate::validation::Validated;
+
+#[get("/schedules")]
+async fn list_schedules(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+) -> Response<Json<Vec<FeeSchedule>>> {
+    oid_provider.require_role(&token, Role::Treasurer)?;
+
+    Ok(Json(fetch_schedules(db_pool.inner()).await?))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[serde(crate = "rocket::serde")]
+pub struct NewFeeBand {
+    #[validate(range(min = 0))]
+    income_from: i64,
+    #[validate(range(min = 0))]
+    amount: i64,
+}
+
+fn validate_fee_kind(schedule: &NewFeeSchedule) -> Result<(), ValidationError> {
+    if schedule.flat_amount.is_some() == schedule.bands.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationError::new("flat_amount_or_bands"))
+    }
+}
+
+fn validate_dues_currency(currency: &str) -> Result<(), ValidationError> {
+    if currency == DUES_CURRENCY {
+        Ok(())
+    } else {
+        Err(ValidationError::new("dues_currency"))
+    }
+}
+
+/// Schedule has either `flat_amount` or income `bands`
+#[derive(Debug, Deserialize, Validate)]
+#[serde(crate = "rocket::serde")]
+#[validate(schema(function = "validate_fee_kind"))]
+pub struct NewFeeSchedule {
+    #[validate(required)]
+    #[validate(custom(function = "validate_non_empty"))]
+    name: Option<String>,
+    #[validate(required)]
+    valid_from: Option<NaiveDate>,
+    /// Dues are only collected in `DUES_CURRENCY`
+    #[validate(custom(function = "validate_dues_currency"))]
+    currency: Option<String>,
+    #[validate(range(min = 0))]
+    flat_amount: Option<i64>,
+    #[serde(default)]
+    #[validate(nested)]
+    bands: Vec<NewFeeBand>,
+}
+
+#[post("/schedules", format = "json", data = "<new_schedule>")]
+async fn create_schedule(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    new_schedule: Validated<Json<NewFeeSchedule>>,
+) -> Response<Json<FeeSchedule>> {
+    oid_provider.require_role(&token, Role::Treasurer)?;
+
+    let new_schedule = new_schedule.into_inner().into_inner();
+
+    let mut tx = db_pool.inner().begin().await?;
+
+    let mut schedule = query::create_schedule(&new_schedule)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    for band in &new_schedule.bands {
+        let band = query::create_band(schedule.id, band)
+            .fetch_one(&mut *tx)
+            .await?;
+        schedule.bands.push(band);
+    }
+
+    tx.commit().await?;
+
+    Ok(Json(schedule))
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Payment {
+    id: Id<data::Payment>,
+    member_number: MemberNumber,
+    amount: i64,
+    currency: String,
+    paid_on: NaiveDate,
+    source: String,
+    reference: Option<String>,
+    note: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+#[get("/<id>/payments")]
+async fn list_payments(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Member>,
+) -> Response<Json<Vec<Payment>>> {
+    oid_provider.require_role(&token, Role::Treasurer)?;
+
+    let payments = query::list_member_payments(id)
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    Ok(Json(payments))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[serde(crate = "rocket::serde")]
+pub struct NewPayment {
+    #[validate(required)]
+    #[validate(range(min = 1))]
+    amount: Option<i64>,
+    #[validate(required)]
+    paid_on: Option<NaiveDate>,
+    #[validate(length(equal = 3))]
+    currency: Option<String>,
+    reference: Option<String>,
+    note: Option<String>,
+}
+
+#[post("/<id>/payments", format = "json", data = "<new_payment>")]
+async fn create_payment(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Member>,
+    new_payment: Validated<Json<NewPayment>>,
+) -> Response<Json<Payment>> {
+    oid_provider.require_role(&token, Role::Treasurer)?;
+
+    let payment = query::create_manual_payment(id, &new_payment.into_inner().into_inner())
+        .fetch_one(db_pool.inner())
+        .await?;
+
+    Ok(Json(payment))
+}
+
+#[get("/<id>/dues")]
+async fn member_dues(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Member>,
+) -> Response<Json<DuesStatus>> {
+    oid_provider.require_role(&token, Role::Treasurer)?;
+
+    Ok(Json(member_status(db_pool.inner(), id).await?))
+}
+
+/// QR code for payment of dues. Amount (in minor units) defaults to arrears of member
+/// or monthly fee when member doesn't owe anything.
+#[get("/<id>/payment-qr?<amount>")]
+async fn payment_qr(
+    config: &State<Config>,
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Member>,
+    amount: Option<i64>,
+) -> Response<(ContentType, Vec<u8>)> {
+    oid_provider.require_role(&token, Role::Treasurer)?;
+
+    let status = member_status(db_pool.inner(), id).await?;
+    let amount = amount.unwrap_or(if status.arrears > 0 {
+        status.arrears
+    } else {
+        status.monthly_fee
+    });
+
+    let png = payment_request(config, status.member_number, amount)
+        .ok_or_else(|| ApiError::config_missing("payment_account"))?
+        .qr_png()
+        .map_err(|err| {
+            error!("Failed to generate payment QR code: {err}");
+            Status::InternalServerError
+        })?;
+
+    Ok((ContentType::PNG, png))
+}
+
+/// Send next reminder of unpaid dues to member right away
+/// regardless of pause and time since the previous reminder.
+/// Fails with conflict when member doesn't owe anything.
+#[post("/<id>/dues/reminders")]
+async fn send_reminder(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    queue: &State<QueueSender>,
+    id: Id<Member>,
+) -> Response<SuccessResponse> {
+    oid_provider.require_role(&token, Role::Treasurer)?;
+
+    // Fails with 404 for unknown member
+    let status = member_status(db_pool.inner(), id).await?;
+    if status.arrears <= 0 {
+        return Err(ApiError::data_conflict(&format!(
+            "Member {id} doesn't owe any dues"
+        )));
+    }
+
+    queue.inner().send(Command::SendDuesReminder(id)).await?;
+
+    Ok(SuccessResponse::Accepted)
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct DuesReminder {
+    id: Id<data::DuesReminder>,
+    level: String,
+    arrears: i64,
+    automatic: bool,
+    sent_at: DateTime<Utc>,
+}
+
+#[get("/<id>/dues/reminders")]
+async fn list_reminders(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Member>,
+) -> Response<Json<Vec<DuesReminder>>> {
+    oid_provider.require_any_role(&token, &[Role::Treasurer, Role::ManageMembers])?;
+
+    let reminders = query::list_member_reminders(id)
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    Ok(Json(reminders))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ReminderPause {
+    /// Last day of the pause, reminders are paused until resumed when missing
+    until: Option<NaiveDate>,
+    reason: Option<String>,
+}
+
+/// Stop sending automatic reminders to member, for example in case of hardship
+#[put("/<id>/dues/reminders/pause", format = "json", data = "<pause>")]
+async fn pause_reminders(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Member>,
+    pause: Json<ReminderPause>,
+) -> Response<Json<DuesStatus>> {
+    oid_provider.require_any_role(&token, &[Role::Treasurer, Role::ManageMembers])?;
+
+    let (id,) = query::pause_reminders(id, &pause)
+        .fetch_one(db_pool.inner())
+        .await?;
+
+    Ok(Json(member_status(db_pool.inner(), id).await?))
+}
+
+#[delete("/<id>/dues/reminders/pause")]
+async fn resume_reminders(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Member>,
+) -> Response<Json<DuesStatus>> {
+    oid_provider.require_any_role(&token, &[Role::Treasurer, Role::ManageMembers])?;
+
+    let (id,) = query::resume_reminders(id)
+        .fetch_one(db_pool.inner())
+        .await?;
+
+    Ok(Json(member_status(db_pool.inner(), id).await?))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[serde(crate = "rocket::serde")]
+pub struct DeclaredIncome {
+    /// `null` clears declared income
+    #[validate(range(min = 0))]
+    declared_income: Option<i64>,
+}
+
+#[put("/<id>/declared-income", format = "json", data = "<income>")]
+async fn update_declared_income(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Member>,
+    income: Validated<Json<DeclaredIncome>>,
+) -> Response<Json<DuesStatus>> {
+    oid_provider.require_role(&token, Role::Treasurer)?;
+
+    let (id,) = query::update_declared_income(id, income.into_inner().declared_income)
+        .fetch_one(db_pool.inner())
+        .await?;
+
+    Ok(Json(member_status(db_pool.inner(), id).await?))
+}
+
+/// Current members who didn't pay all their dues
+/// optionally only those who owe at least `min_months` monthly fees
+#[get("/arrears?<min_months>")]
+async fn list_arrears(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    min_months: Option<i64>,
+) -> Response<Json<Vec<DuesStatus>>> {
+    oid_provider.require_role(&token, Role::Treasurer)?;
+
+    let arrears = current_statuses(db_pool.inner())
+        .await?
+        .into_iter()
+        .filter(|status| status.arrears > 0)
+        .filter(|status| status.months_in_arrears >= min_months.unwrap_or(0))
+        .collect();
+
+    Ok(Json(arrears))
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct BankTransaction {
+    id: Id<data::BankTransaction>,
+    bank_reference: String,
+    booked_on: NaiveDate,
+    amount: i64,
+    currency: String,
+    variable_symbol: Option<String>,
+    counterparty_account: Option<String>,
+    counterparty_name: Option<String>,
+    message: Option<String>,
+    status: String,
+    imported_at: DateTime<Utc>,
+    resolved_at: Option<DateTime<Utc>>,
+    #[sqlx(default)]
+    member_id: Option<Id<Member>>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportSummary {
+    transactions: usize,
+    matched: usize,
+    unmatched: usize,
+    ambiguous: usize,
+    /// Already imported by previous upload
+    duplicates: usize,
+    /// Outgoing transactions are not imported
+    outgoing: usize,
+}
+
+/// Import bank statement. Format is detected from content when not given.
+/// Importing the same statement again is harmless.
+#[post("/bank-statements?<format>", data = "<statement>")]
+async fn import_bank_statement(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    format: Option<bank::Format>,
+    statement: Data<'_>,
+) -> Response<Json<ImportSummary>> {
+    oid_provider.require_role(&token, Role::Treasurer)?;
+
+    let statement = statement
+        .open(10.mebibytes())
+        .into_bytes()
+        .await
+        .map_err(|_err| Status::BadRequest)?;
+    if !statement.is_complete() {
+        return Err(Status::PayloadTooLarge.into());
+    }
+
+    let transactions =
+        bank::parse(format, &statement).map_err(|err| ApiError::bad_request(&err.to_string()))?;
+
+    let members: HashMap<i32, (Id<Member>, bool)> = query::list_member_numbers()
+        .fetch_all(db_pool.inner())
+        .await?
+        .into_iter()
+        .map(|(id, number, current)| (number, (id, current)))
+        .collect();
+
+    let mut summary = ImportSummary {
+        transactions: transactions.len(),
+        ..ImportSummary::default()
+    };
+
+    let mut tx = db_pool.inner().begin().await?;
+
+    for transaction in &transactions {
+        if transaction.amount <= 0 {
+            summary.outgoing += 1;
+            continue;
+        }
+
+        let matched = bank::match_member(transaction, DUES_CURRENCY, |number| {
+            members.get(&number).copied()
+        });
+        let status = match matched {
+            Match::Member(_) => "matched",
+            Match::Unmatched => "unmatched",
+            Match::Ambiguous => "ambiguous",
+        };
+
+        let Some(imported) = query::create_bank_transaction(transaction, status)
+            .fetch_optional(&mut *tx)
+            .await?
+        else {
+            summary.duplicates += 1;
+            continue;
+        };
+
+        match matched {
+            Match::Member(member_id) => {
+                query::create_bank_payment(imported.id, member_id)
+                    .execute(&mut *tx)
+                    .await?;
+                summary.matched += 1;
+            }
+            Match::Unmatched => summary.unmatched += 1,
+            Match::Ambiguous => summary.ambiguous += 1,
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok(Json(summary))
+}
+
+/// Transactions with given `status`, by default those waiting for review
+/// (`unmatched` and `ambiguous`)
+#[get("/bank-transactions?<status>")]
+async fn list_bank_transactions(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    status: Option<&str>,
+) -> Response<Json<Vec<BankTransaction>>> {
+    oid_provider.require_role(&token, Role::Treasurer)?;
+
+    let transactions = match status {
+        Some(status) => query::list_bank_transactions(status),
+        None => query::list_bank_transactions_for_review(),
+    }
+    .fetch_all(db_pool.inner())
+    .await?;
+
+    Ok(Json(transactions))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Assignment {
+    member_id: Id<Member>,
+}
+
+/// Error for transaction which couldn't be resolved, missing one is reported as not found
+async fn unresolvable_transaction(
+    connection: &mut PgConnection,
+    id: Id<data::BankTransaction>,
+) -> ApiError {
+    match query::bank_transaction_exists(id)
+        .fetch_one(connection)
+        .await
+    {
+        Ok((true,)) => ApiError::data_conflict("Transaction was already resolved"),
+        Ok((false,)) => Status::NotFound.into(),
+        Err(err) => err.into(),
+    }
+}
+
+/// Record payment from transaction which wasn't matched automatically
+#[post(
+    "/bank-transactions/<id>/assign",
+    format = "json",
+    data = "<assignment>"
+)]
+async fn assign_bank_transaction(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<data::BankTransaction>,
+    assignment: Json<Assignment>,
+) -> Response<Json<BankTransaction>> {
+    oid_provider.require_role(&token, Role::Treasurer)?;
+
+    let mut tx = db_pool.inner().begin().await?;
+
+    let Some(transaction) = query::resolve_bank_transaction(id, "resolved")
+        .fetch_optional(&mut *tx)
+        .await?
+    else {
+        return Err(unresolvable_transaction(&mut tx, id).await);
+    };
+
+    let recorded = query::create_bank_payment(transaction.id, assignment.member_id)
+        .execute(&mut *tx)
+        .await?;
+    if recorded.rows_affected() == 0 {
+        return Err(Status::NotFound.into());
+    }
+
+    tx.commit().await?;
+
+    Ok(Json(BankTransaction {
+        member_id: Some(assignment.member_id),
+        ..transaction
+    }))
+}
+
+/// Mark transaction as not being a membership payment
+#[post("/bank-transactions/<id>/ignore")]
+async fn ignore_bank_transaction(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<data::BankTransaction>,
+) -> Response<Json<BankTransaction>> {
+    oid_provider.require_role(&token, Role::Treasurer)?;
+
+    let mut connection = db_pool.acquire().await?;
+
+    match query::resolve_bank_transaction(id, "ignored")
+        .fetch_optional(&mut *connection)
+        .await?
+    {
+        Some(transaction) => Ok(Json(transaction)),
+        None => Err(unresolvable_transaction(&mut connection, id).await),
+    }
+}
+
+#[expect(clippy::redundant_type_annotations, reason = "rocket macro expansion")]
+pub fn routes() -> Vec<Route> {
+    routes![
+        list_schedules,
+        create_schedule,
+        import_bank_statement,
+        list_bank_transactions,
+        assign_bank_transaction,
+        ignore_bank_transaction,
+    ]
+}
+
+/// Routes mounted under `/members`
+#[expect(clippy::redundant_type_annotations, reason = "rocket macro expansion")]
+pub fn member_routes() -> Vec<Route> {
+    routes![
+        list_arrears,
+        list_payments,
+        create_payment,
+        member_dues,
+        update_declared_income,
+        payment_qr,
+        send_reminder,
+        list_reminders,
+        pause_reminders,
+        resume_reminders,
+    ]
+}
@@ -0,0 +1,334 @@
+use super::{
+    BankTransaction, DuesReminder, NewFeeBand, NewFeeSchedule, NewPayment, Payment, ReminderPause,
+};
+use crate::bank::Transaction;
+use crate::data::{self, Id, Member};
+use crate::db::{Query, QueryAs};
+use crate::dues::status::{DUES_CURRENCY, FeeBand, FeeSchedule};
+
+pub fn create_schedule(schedule: &NewFeeSchedule) -> QueryAs<'_, FeeSchedule> {
+    sqlx::query_as(
+        "
+INSERT INTO fee_schedules
+    ( name
+    , valid_from
+    , currency
+    , flat_amount
+    ) VALUES ($1, $2, $3, $4)
+RETURNING id
+    , name
+    , valid_from
+    , currency
+    , flat_amount
+    , created_at
+",
+    )
+    .bind(&schedule.name)
+    .bind(schedule.valid_from)
+    .bind(schedule.currency.as_deref().unwrap_or(DUES_CURRENCY))
+    .bind(schedule.flat_amount)
+}
+
+pub fn create_band(
+    fee_schedule_id: Id<data::FeeSchedule>,
+    band: &NewFeeBand,
+) -> QueryAs<'_, FeeBand> {
+    sqlx::query_as(
+        "
+INSERT INTO fee_schedule_bands
+    ( fee_schedule_id
+    , income_from
+    , amount
+    ) VALUES ($1, $2, $3)
+RETURNING fee_schedule_id
+    , income_from
+    , amount
+",
+    )
+    .bind(fee_schedule_id)
+    .bind(band.income_from)
+    .bind(band.amount)
+}
+
+pub fn list_member_payments<'a>(id: Id<Member>) -> QueryAs<'a, Payment> {
+    sqlx::query_as(
+        "
+SELECT p.id
+    , p.member_number
+    , p.amount
+    , p.currency
+    , p.paid_on
+    , p.source
+    , p.reference
+    , p.note
+    , p.created_at
+FROM payments p
+JOIN members m ON m.member_number = p.member_number
+WHERE m.id = $1
+ORDER BY p.paid_on DESC
+",
+    )
+    .bind(id)
+}
+
+pub fn create_manual_payment(id: Id<Member>, payment: &NewPayment) -> QueryAs<'_, Payment> {
+    sqlx::query_as(
+        "
+INSERT INTO payments
+    ( member_number
+    , amount
+    , currency
+    , paid_on
+    , source
+    , reference
+    , note
+    ) SELECT m.member_number, $2, $3, $4, 'manual', $5, $6
+    FROM members m
+    WHERE m.id = $1
+RETURNING id
+    , member_number
+    , amount
+    , currency
+    , paid_on
+    , source
+    , reference
+    , note
+    , created_at
+",
+    )
+    .bind(id)
+    .bind(payment.amount)
+    .bind(payment.currency.as_deref().unwrap_or(DUES_CURRENCY))
+    .bind(payment.paid_on)
+    .bind(&payment.reference)
+    .bind(&payment.note)
+}
+
+pub fn update_declared_income<'a>(
+    id: Id<Member>,
+    declared_income: Option<i64>,
+) -> QueryAs<'a, (Id<Member>,)> {
+    sqlx::query_as(
+        "
+UPDATE members AS m
+SET declared_income = $2
+WHERE m.id = $1
+RETURNING m.id
+",
+    )
+    .bind(id)
+    .bind(declared_income)
+}
+
+/// Member number of every member together with flag whether member is current
+pub fn list_member_numbers<'a>() -> QueryAs<'a, (Id<Member>, i32, bool)> {
+    sqlx::query_as(
+        "
+SELECT id
+    , member_number
+    , left_at IS NULL
+FROM members
+",
+    )
+}
+
+/// Returns `None` when transaction was already imported
+pub fn create_bank_transaction<'a>(
+    transaction: &'a Transaction,
+    status: &'a str,
+) -> QueryAs<'a, BankTransaction> {
+    sqlx::query_as(
+        "
+INSERT INTO bank_transactions
+    ( bank_reference
+    , booked_on
+    , amount
+    , currency
+    , variable_symbol
+    , counterparty_account
+    , counterparty_name
+    , message
+    , status
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+ON CONFLICT (bank_reference) DO NOTHING
+RETURNING id
+    , bank_reference
+    , booked_on
+    , amount
+    , currency
+    , variable_symbol
+    , counterparty_account
+    , counterparty_name
+    , message
+    , status
+    , imported_at
+    , resolved_at
+",
+    )
+    .bind(&transaction.id)
+    .bind(transaction.booked_on)
+    .bind(transaction.amount)
+    .bind(&transaction.currency)
+    .bind(&transaction.variable_symbol)
+    .bind(&transaction.counterparty_account)
+    .bind(&transaction.counterparty_name)
+    .bind(&transaction.message)
+    .bind(status)
+}
+
+pub fn create_bank_payment<'a>(id: Id<data::BankTransaction>, member_id: Id<Member>) -> Query<'a> {
+    sqlx::query(
+        "
+INSERT INTO payments
+    ( member_number
+    , amount
+    , currency
+    , paid_on
+    , source
+    , reference
+    , note
+    , bank_transaction_id
+    ) SELECT m.member_number, t.amount, t.currency, t.booked_on, 'bank', t.bank_reference, t.message, t.id
+    FROM bank_transactions t, members m
+    WHERE t.id = $1 AND m.id = $2
+",
+    )
+    .bind(id)
+    .bind(member_id)
+}
+
+pub fn list_bank_transactions(status: &str) -> QueryAs<'_, BankTransaction> {
+    sqlx::query_as(
+        "
+SELECT t.id
+    , t.bank_reference
+    , t.booked_on
+    , t.amount
+    , t.currency
+    , t.variable_symbol
+    , t.counterparty_account
+    , t.counterparty_name
+    , t.message
+    , t.status
+    , t.imported_at
+    , t.resolved_at
+    , m.id AS member_id
+FROM bank_transactions t
+LEFT JOIN payments p ON p.bank_transaction_id = t.id
+LEFT JOIN members m ON m.member_number = p.member_number
+WHERE t.status = $1
+ORDER BY t.booked_on DESC
+",
+    )
+    .bind(status)
+}
+
+pub fn list_bank_transactions_for_review<'a>() -> QueryAs<'a, BankTransaction> {
+    sqlx::query_as(
+        "
+SELECT t.id
+    , t.bank_reference
+    , t.booked_on
+    , t.amount
+    , t.currency
+    , t.variable_symbol
+    , t.counterparty_account
+    , t.counterparty_name
+    , t.message
+    , t.status
+    , t.imported_at
+    , t.resolved_at
+FROM bank_transactions t
+WHERE t.status IN ('unmatched', 'ambiguous')
+ORDER BY t.booked_on
+",
+    )
+}
+
+pub fn bank_transaction_exists<'a>(id: Id<data::BankTransaction>) -> QueryAs<'a, (bool,)> {
+    sqlx::query_as(
+        "
+SELECT EXISTS (
+    SELECT 1 FROM bank_transactions
+    WHERE id = $1
+)
+",
+    )
+    .bind(id)
+}
+
+/// Returns `None` when transaction doesn't wait for review
+pub fn resolve_bank_transaction(
+    id: Id<data::BankTransaction>,
+    status: &str,
+) -> QueryAs<'_, BankTransaction> {
+    sqlx::query_as(
+        "
+UPDATE bank_transactions
+SET status = $2
+    , resolved_at = NOW()
+WHERE id = $1
+    AND status IN ('unmatched', 'ambiguous')
+RETURNING id
+    , bank_reference
+    , booked_on
+    , amount
+    , currency
+    , variable_symbol
+    , counterparty_account
+    , counterparty_name
+    , message
+    , status
+    , imported_at
+    , resolved_at
+",
+    )
+    .bind(id)
+    .bind(status)
+}
+
+pub fn pause_reminders(id: Id<Member>, pause: &ReminderPause) -> QueryAs<'_, (Id<Member>,)> {
+    sqlx::query_as(
+        "
+UPDATE members AS m
+SET dues_reminders_paused_at = NOW()
+    , dues_reminders_paused_until = $2
+    , dues_reminders_pause_reason = $3
+WHERE m.id = $1
+RETURNING m.id
+",
+    )
+    .bind(id)
+    .bind(pause.until)
+    .bind(&pause.reason)
+}
+
+pub fn resume_reminders<'a>(id: Id<Member>) -> QueryAs<'a, (Id<Member>,)> {
+    sqlx::query_as(
+        "
+UPDATE members AS m
+SET dues_reminders_paused_at = NULL
+    , dues_reminders_paused_until = NULL
+    , dues_reminders_pause_reason = NULL
+WHERE m.id = $1
+RETURNING m.id
+",
+    )
+    .bind(id)
+}
+
+pub fn list_member_reminders<'a>(id: Id<Member>) -> QueryAs<'a, DuesReminder> {
+    sqlx::query_as(
+        "
+SELECT id
+    , level
+    , arrears
+    , automatic
+    , sent_at
+FROM dues_reminders
+WHERE member_id = $1
+ORDER BY sent_at DESC
+",
+    )
+    .bind(id)
+}
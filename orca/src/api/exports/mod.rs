@@ -0,0 +1,473 @@
+//! Spreadsheet exports of members and applications
+//!
+//! Exports contain personal data so every download is recorded in audit trail
+//! together with user who made it. Column names in header are the same
+//! as values of `columns` parameter.
+use chrono::{DateTime, NaiveDate, Utc};
+use rocket::serde::json::Json;
+use rocket::{FromFormField, Route, State, get, routes};
+use serde::Serialize;
+use uuid::Uuid;
+
+pub mod query;
+pub mod table;
+
+use super::workplaces::scope::WorkplaceScope;
+use crate::api::Response;
+use crate::data::{Id, MemberNumber, Workplace};
+use crate::db::DbPool;
+use crate::server::oid::{JwtToken, Provider, Role};
+use table::{Export, Format, Row};
+
+fn text(value: Option<&String>) -> String {
+    value.cloned().unwrap_or_default()
+}
+
+fn date(value: Option<NaiveDate>) -> String {
+    value.map(|date| date.to_string()).unwrap_or_default()
+}
+
+fn timestamp(value: Option<DateTime<Utc>>) -> String {
+    date(value.map(|time| time.date_naive()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromFormField)]
+pub enum MemberList {
+    All,
+    Current,
+    New,
+    Past,
+}
+
+impl MemberList {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::All => "all",
+            Self::Current => "current",
+            Self::New => "new",
+            Self::Past => "past",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromFormField)]
+pub enum MemberColumn {
+    #[field(value = "member_number")]
+    MemberNumber,
+    #[field(value = "first_name")]
+    FirstName,
+    #[field(value = "last_name")]
+    LastName,
+    #[field(value = "email")]
+    Email,
+    #[field(value = "phone_number")]
+    PhoneNumber,
+    #[field(value = "date_of_birth")]
+    DateOfBirth,
+    #[field(value = "address")]
+    Address,
+    #[field(value = "city")]
+    City,
+    #[field(value = "postal_code")]
+    PostalCode,
+    #[field(value = "language")]
+    Language,
+    #[field(value = "companies")]
+    Companies,
+    #[field(value = "workplaces")]
+    Workplaces,
+    #[field(value = "joined_at")]
+    JoinedAt,
+    #[field(value = "left_at")]
+    LeftAt,
+    #[field(value = "note")]
+    Note,
+}
+
+/// Columns exported when none are selected
+const DEFAULT_MEMBER_COLUMNS: [MemberColumn; 8] = [
+    MemberColumn::MemberNumber,
+    MemberColumn::FirstName,
+    MemberColumn::LastName,
+    MemberColumn::Email,
+    MemberColumn::PhoneNumber,
+    MemberColumn::City,
+    MemberColumn::Companies,
+    MemberColumn::Workplaces,
+];
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct MemberRow {
+    member_number: MemberNumber,
+    first_name: Option<String>,
+    last_name: Option<String>,
+    email: Option<String>,
+    phone_number: Option<String>,
+    date_of_birth: Option<NaiveDate>,
+    address: Option<String>,
+    city: Option<String>,
+    postal_code: Option<String>,
+    language: Option<String>,
+    companies: Vec<String>,
+    workplaces: Vec<String>,
+    joined_at: Option<DateTime<Utc>>,
+    left_at: Option<DateTime<Utc>>,
+    note: Option<String>,
+}
+
+impl Row for MemberRow {
+    type Column = MemberColumn;
+
+    fn header(column: MemberColumn) -> &'static str {
+        match column {
+            MemberColumn::MemberNumber => "member_number",
+            MemberColumn::FirstName => "first_name",
+            MemberColumn::LastName => "last_name",
+            MemberColumn::Email => "email",
+            MemberColumn::PhoneNumber => "phone_number",
+            MemberColumn::DateOfBirth => "date_of_birth",
+            MemberColumn::Address => "address",
+            MemberColumn::City => "city",
+            MemberColumn::PostalCode => "postal_code",
+            MemberColumn::Language => "language",
+            MemberColumn::Companies => "companies",
+            MemberColumn::Workplaces => "workplaces",
+            MemberColumn::JoinedAt => "joined_at",
+            MemberColumn::LeftAt => "left_at",
+            MemberColumn::Note => "note",
+        }
+    }
+
+    fn cell(&self, column: MemberColumn) -> String {
+        match column {
+            MemberColumn::MemberNumber => self.member_number.to_string(),
+            MemberColumn::FirstName => text(self.first_name.as_ref()),
+            MemberColumn::LastName => text(self.last_name.as_ref()),
+            MemberColumn::Email => text(self.email.as_ref()),
+            MemberColumn::PhoneNumber => text(self.phone_number.as_ref()),
+            MemberColumn::DateOfBirth => date(self.date_of_birth),
+            MemberColumn::Address => text(self.address.as_ref()),
+            MemberColumn::City => text(self.city.as_ref()),
+            MemberColumn::PostalCode => text(self.postal_code.as_ref()),
+            MemberColumn::Language => text(self.language.as_ref()),
+            MemberColumn::Companies => self.companies.join("; "),
+            MemberColumn::Workplaces => self.workplaces.join("; "),
+            MemberColumn::JoinedAt => timestamp(self.joined_at),
+            MemberColumn::LeftAt => timestamp(self.left_at),
+            MemberColumn::Note => text(self.note.as_ref()),
+        }
+    }
+}
+
+/// Record of export in audit trail
+#[derive(Debug)]
+struct Audit<'a> {
+    resource: &'static str,
+    selection: &'a str,
+    columns: Vec<&'static str>,
+    format: Format,
+    row_count: usize,
+}
+
+impl<'a> Audit<'a> {
+    fn new<R: Row>(
+        resource: &'static str,
+        selection: &'a str,
+        columns: &[R::Column],
+        format: Format,
+        rows: &[R],
+    ) -> Self {
+        Self {
+            resource,
+            selection,
+            columns: columns.iter().map(|column| R::header(*column)).collect(),
+            format,
+            row_count: rows.len(),
+        }
+    }
+
+    async fn record(
+        &self,
+        db_pool: &DbPool,
+        oid_provider: &Provider,
+        token: &JwtToken<'_>,
+    ) -> Response<()> {
+        let claims = oid_provider.decode_jwt(token)?.claims;
+
+        query::record_export(
+            claims.sub,
+            &claims.email,
+            self.resource,
+            self.selection,
+            &self.columns,
+            self.format.as_str(),
+            i32::try_from(self.row_count).unwrap_or(i32::MAX),
+        )
+        .execute(db_pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Export of member list, `current` members by default.
+/// Members of single workplace can be exported by its representatives.
+#[get("/members?<list>&<workplace_id>&<columns>&<format>")]
+async fn export_members(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    list: Option<MemberList>,
+    workplace_id: Option<Id<Workplace>>,
+    columns: Vec<MemberColumn>,
+    format: Option<Format>,
+) -> Response<Export> {
+    if let Some(workplace_id) = workplace_id {
+        oid_provider.require_role(&token, Role::ListWorkplaces)?;
+        oid_provider.require_role(&token, Role::ViewMember)?;
+        WorkplaceScope::of(db_pool, oid_provider, &token)
+            .await?
+            .require_workplace(workplace_id)?;
+    } else {
+        oid_provider.require_role(&token, Role::ListMembers)?;
+    }
+
+    let list = list.unwrap_or(MemberList::Current);
+    let columns = if columns.is_empty() {
+        DEFAULT_MEMBER_COLUMNS.to_vec()
+    } else {
+        columns
+    };
+    let format = format.unwrap_or(Format::Csv);
+
+    let rows = query::list_members(list.as_str(), workplace_id)
+        .fetch_all(db_pool.inner())
+        .await?;
+    let data = table::render(&rows, &columns, format)?;
+
+    let selection = match workplace_id {
+        Some(workplace_id) => format!("{}:workplace:{workplace_id}", list.as_str()),
+        None => list.as_str().to_string(),
+    };
+    Audit::new("members", &selection, &columns, format, &rows)
+        .record(db_pool, oid_provider, &token)
+        .await?;
+
+    Ok(Export {
+        data,
+        format,
+        name: format!("members-{}-{}", list.as_str(), Utc::now().date_naive()),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromFormField)]
+pub enum ApplicationStatus {
+    #[field(value = "unverified")]
+    Unverified,
+    #[field(value = "processing")]
+    Processing,
+    #[field(value = "needs_info")]
+    NeedsInfo,
+    #[field(value = "accepted")]
+    Accepted,
+    #[field(value = "rejected")]
+    Rejected,
+    #[field(value = "invalid")]
+    Invalid,
+}
+
+impl ApplicationStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Unverified => "unverified",
+            Self::Processing => "processing",
+            Self::NeedsInfo => "needs_info",
+            Self::Accepted => "accepted",
+            Self::Rejected => "rejected",
+            Self::Invalid => "invalid",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromFormField)]
+pub enum ApplicationColumn {
+    #[field(value = "email")]
+    Email,
+    #[field(value = "first_name")]
+    FirstName,
+    #[field(value = "last_name")]
+    LastName,
+    #[field(value = "phone_number")]
+    PhoneNumber,
+    #[field(value = "date_of_birth")]
+    DateOfBirth,
+    #[field(value = "address")]
+    Address,
+    #[field(value = "city")]
+    City,
+    #[field(value = "postal_code")]
+    PostalCode,
+    #[field(value = "company_name")]
+    CompanyName,
+    #[field(value = "occupation")]
+    Occupation,
+    #[field(value = "language")]
+    Language,
+    #[field(value = "source")]
+    Source,
+    #[field(value = "status")]
+    Status,
+    #[field(value = "created_at")]
+    CreatedAt,
+    #[field(value = "confirmed_at")]
+    ConfirmedAt,
+    #[field(value = "note")]
+    Note,
+}
+
+/// Columns exported when none are selected
+const DEFAULT_APPLICATION_COLUMNS: [ApplicationColumn; 8] = [
+    ApplicationColumn::FirstName,
+    ApplicationColumn::LastName,
+    ApplicationColumn::Email,
+    ApplicationColumn::PhoneNumber,
+    ApplicationColumn::City,
+    ApplicationColumn::CompanyName,
+    ApplicationColumn::Status,
+    ApplicationColumn::CreatedAt,
+];
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct ApplicationRow {
+    email: Option<String>,
+    first_name: Option<String>,
+    last_name: Option<String>,
+    phone_number: Option<String>,
+    date_of_birth: Option<NaiveDate>,
+    address: Option<String>,
+    city: Option<String>,
+    postal_code: Option<String>,
+    company_name: Option<String>,
+    occupation: Option<String>,
+    language: Option<String>,
+    source: Option<String>,
+    status: Option<String>,
+    created_at: DateTime<Utc>,
+    confirmed_at: Option<DateTime<Utc>>,
+    note: Option<String>,
+}
+
+impl Row for ApplicationRow {
+    type Column = ApplicationColumn;
+
+    fn header(column: ApplicationColumn) -> &'static str {
+        match column {
+            ApplicationColumn::Email => "email",
+            ApplicationColumn::FirstName => "first_name",
+            ApplicationColumn::LastName => "last_name",
+            ApplicationColumn::PhoneNumber => "phone_number",
+            ApplicationColumn::DateOfBirth => "date_of_birth",
+            ApplicationColumn::Address => "address",
+            ApplicationColumn::City => "city",
+            ApplicationColumn::PostalCode => "postal_code",
+            ApplicationColumn::CompanyName => "company_name",
+            ApplicationColumn::Occupation => "occupation",
+            ApplicationColumn::Language => "language",
+            ApplicationColumn::Source => "source",
+            ApplicationColumn::Status => "status",
+            ApplicationColumn::CreatedAt => "created_at",
+            ApplicationColumn::ConfirmedAt => "confirmed_at",
+            ApplicationColumn::Note => "note",
+        }
+    }
+
+    fn cell(&self, column: ApplicationColumn) -> String {
+        match column {
+            ApplicationColumn::Email => text(self.email.as_ref()),
+            ApplicationColumn::FirstName => text(self.first_name.as_ref()),
+            ApplicationColumn::LastName => text(self.last_name.as_ref()),
+            ApplicationColumn::PhoneNumber => text(self.phone_number.as_ref()),
+            ApplicationColumn::DateOfBirth => date(self.date_of_birth),
+            ApplicationColumn::Address => text(self.address.as_ref()),
+            ApplicationColumn::City => text(self.city.as_ref()),
+            ApplicationColumn::PostalCode => text(self.postal_code.as_ref()),
+            ApplicationColumn::CompanyName => text(self.company_name.as_ref()),
+            ApplicationColumn::Occupation => text(self.occupation.as_ref()),
+            ApplicationColumn::Language => text(self.language.as_ref()),
+            ApplicationColumn::Source => text(self.source.as_ref()),
+            ApplicationColumn::Status => text(self.status.as_ref()),
+            ApplicationColumn::CreatedAt => timestamp(Some(self.created_at)),
+            ApplicationColumn::ConfirmedAt => timestamp(self.confirmed_at),
+            ApplicationColumn::Note => text(self.note.as_ref()),
+        }
+    }
+}
+
+/// Export of applications, all of them unless `status` is given
+#[get("/applications?<status>&<columns>&<format>")]
+async fn export_applications(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    status: Option<ApplicationStatus>,
+    columns: Vec<ApplicationColumn>,
+    format: Option<Format>,
+) -> Response<Export> {
+    oid_provider.require_role(&token, Role::ListApplications)?;
+
+    let columns = if columns.is_empty() {
+        DEFAULT_APPLICATION_COLUMNS.to_vec()
+    } else {
+        columns
+    };
+    let format = format.unwrap_or(Format::Csv);
+    let selection = status.map_or("all", ApplicationStatus::as_str);
+
+    let rows = query::list_applications(status.map(ApplicationStatus::as_str))
+        .fetch_all(db_pool.inner())
+        .await?;
+    let data = table::render(&rows, &columns, format)?;
+
+    Audit::new("applications", selection, &columns, format, &rows)
+        .record(db_pool, oid_provider, &token)
+        .await?;
+
+    Ok(Export {
+        data,
+        format,
+        name: format!("applications-{selection}-{}", Utc::now().date_naive()),
+    })
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct DataExport {
+    id: Id<DataExport>,
+    /// OIDC subject of user who made the export
+    exported_by: Uuid,
+    exported_by_email: String,
+    resource: String,
+    selection: String,
+    columns: Vec<String>,
+    format: String,
+    row_count: i32,
+    created_at: DateTime<Utc>,
+}
+
+/// Audit trail of exports, the latest first
+#[get("/")]
+async fn list_exports(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+) -> Response<Json<Vec<DataExport>>> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    let exports = query::list_exports().fetch_all(db_pool.inner()).await?;
+
+    Ok(Json(exports))
+}
+
+#[expect(clippy::redundant_type_annotations, reason = "rocket macro expansion")]
+pub fn routes() -> Vec<Route> {
+    routes![export_members, export_applications, list_exports]
+}
@@ -0,0 +1,147 @@
+use uuid::Uuid;
+
+use super::{ApplicationRow, DataExport, MemberRow};
+use crate::data::{Id, Workplace};
+use crate::db::{Query, QueryAs};
+
+/// `list` is one of `all`, `current`, `new` or `past`.
+/// Only current employers are included in companies.
+pub fn list_members(list: &str, workplace_id: Option<Id<Workplace>>) -> QueryAs<'_, MemberRow> {
+    sqlx::query_as(
+        "
+SELECT m.member_number
+    , m.first_name
+    , m.last_name
+    , m.email
+    , m.phone_number
+    , m.date_of_birth
+    , m.address
+    , m.city
+    , m.postal_code
+    , m.language
+    , ARRAY(
+        SELECT o.company_name
+        FROM occupations o
+        WHERE o.member_id = m.id
+        AND o.is_current
+        AND o.company_name IS NOT NULL
+        ORDER BY o.started_on DESC NULLS LAST
+    ) AS companies
+    , ARRAY(
+        SELECT w.name
+        FROM members_workplaces mw
+        INNER JOIN workplaces w ON w.id = mw.workplace_id
+        WHERE mw.member_id = m.id
+        ORDER BY w.name
+    ) AS workplaces
+    , (SELECT MAX(p.joined_at) FROM membership_periods p WHERE p.member_id = m.id) AS joined_at
+    , m.left_at
+    , m.note
+FROM members m
+WHERE CASE $1
+    WHEN 'current' THEN EXISTS (SELECT 1 FROM members_current v WHERE v.id = m.id)
+    WHEN 'new' THEN EXISTS (SELECT 1 FROM members_new v WHERE v.id = m.id)
+    WHEN 'past' THEN EXISTS (SELECT 1 FROM members_past v WHERE v.id = m.id)
+    ELSE TRUE
+END
+AND ($2::UUID IS NULL OR EXISTS (
+    SELECT 1 FROM members_workplaces mw
+    WHERE mw.member_id = m.id
+    AND mw.workplace_id = $2
+))
+ORDER BY m.member_number
+",
+    )
+    .bind(list)
+    .bind(workplace_id)
+}
+
+/// All applications unless `status` is given
+pub fn list_applications(status: Option<&str>) -> QueryAs<'_, ApplicationRow> {
+    sqlx::query_as(
+        "
+SELECT a.*
+FROM (
+    SELECT rr.email
+        , rr.first_name
+        , rr.last_name
+        , rr.phone_number
+        , rr.date_of_birth
+        , rr.address
+        , rr.city
+        , rr.postal_code
+        , rr.company_name
+        , rr.occupation
+        , rr.registration_local AS language
+        , rr.registration_source AS source
+        , CASE
+            WHEN EXISTS (SELECT 1 FROM registration_requests_accepted v WHERE v.id = rr.id) THEN 'accepted'
+            WHEN EXISTS (SELECT 1 FROM registration_requests_rejected v WHERE v.id = rr.id) THEN 'rejected'
+            WHEN EXISTS (SELECT 1 FROM registration_requests_invalid v WHERE v.id = rr.id) THEN 'invalid'
+            WHEN EXISTS (SELECT 1 FROM registration_requests_needs_info v WHERE v.id = rr.id) THEN 'needs_info'
+            WHEN EXISTS (SELECT 1 FROM registration_requests_processing v WHERE v.id = rr.id) THEN 'processing'
+            WHEN EXISTS (SELECT 1 FROM registration_requests_unverified v WHERE v.id = rr.id) THEN 'unverified'
+        END AS status
+        , rr.created_at
+        , rr.confirmed_at
+        , rr.note
+    FROM registration_requests rr
+) a
+WHERE $1::TEXT IS NULL
+    OR a.status = $1
+ORDER BY a.created_at DESC
+",
+    )
+    .bind(status)
+}
+
+pub fn record_export<'a>(
+    exported_by: Uuid,
+    exported_by_email: &'a str,
+    resource: &'a str,
+    selection: &'a str,
+    columns: &'a [&'a str],
+    format: &'a str,
+    row_count: i32,
+) -> Query<'a> {
+    sqlx::query(
+        "
+INSERT INTO data_exports
+    ( exported_by
+    , exported_by_email
+    , resource
+    , selection
+    , columns
+    , format
+    , row_count
+    )
+VALUES
+    ( $1, $2, $3, $4, $5, $6, $7 )
+",
+    )
+    .bind(exported_by)
+    .bind(exported_by_email)
+    .bind(resource)
+    .bind(selection)
+    .bind(columns)
+    .bind(format)
+    .bind(row_count)
+}
+
+pub fn list_exports<'a>() -> QueryAs<'a, DataExport> {
+    sqlx::query_as(
+        "
+SELECT id
+    , exported_by
+    , exported_by_email
+    , resource
+    , selection
+    , columns
+    , format
+    , row_count
+    , created_at
+FROM data_exports
+ORDER BY created_at DESC
+",
+    )
+}
@@ -0,0 +1,207 @@
+//! Rendering of exported rows to spreadsheet files
+use log::error;
+use rocket::FromFormField;
+use rocket::http::{ContentType, Status};
+use rocket::response::Responder;
+use rust_xlsxwriter::{Format as CellFormat, Workbook, XlsxError};
+use thiserror::Error;
+
+use crate::api::ApiError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromFormField)]
+pub enum Format {
+    Csv,
+    Xlsx,
+}
+
+impl Format {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Xlsx => "xlsx",
+        }
+    }
+
+    fn content_type(self) -> ContentType {
+        match self {
+            Self::Csv => ContentType::CSV,
+            Self::Xlsx => ContentType::new(
+                "application",
+                "vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("XLSX error: {0}")]
+    Xlsx(#[from] XlsxError),
+    #[error("Table is too large")]
+    TooLarge,
+}
+
+impl From<Error> for ApiError {
+    fn from(err: Error) -> Self {
+        error!("Failed to render export: {err}");
+        Status::InternalServerError.into()
+    }
+}
+
+/// Row which can be exported with selectable columns
+pub trait Row {
+    type Column: Copy;
+
+    fn header(column: Self::Column) -> &'static str;
+
+    /// Empty string for missing values
+    fn cell(&self, column: Self::Column) -> String;
+}
+
+/// Characters which make spreadsheets evaluate cell as formula
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+/// Values which would be evaluated as formula are prefixed with `'`
+fn neutralize_formula(value: String) -> String {
+    if value.starts_with(FORMULA_PREFIXES) {
+        format!("'{value}")
+    } else {
+        value
+    }
+}
+
+/// CSV starts with byte order mark so that Excel recognizes it as UTF-8
+fn render_csv<R: Row>(rows: &[R], columns: &[R::Column]) -> Result<Vec<u8>, Error> {
+    let mut writer = csv::Writer::from_writer("\u{feff}".as_bytes().to_vec());
+
+    writer.write_record(columns.iter().map(|column| R::header(*column)))?;
+    for row in rows {
+        writer.write_record(
+            columns
+                .iter()
+                .map(|column| neutralize_formula(row.cell(*column))),
+        )?;
+    }
+
+    writer
+        .into_inner()
+        .map_err(|err| Error::Csv(err.into_error().into()))
+}
+
+fn render_xlsx<R: Row>(rows: &[R], columns: &[R::Column]) -> Result<Vec<u8>, Error> {
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    let bold = CellFormat::new().set_bold();
+
+    for (col, column) in columns.iter().enumerate() {
+        let col = u16::try_from(col).map_err(|_err| Error::TooLarge)?;
+        worksheet.write_string_with_format(0, col, R::header(*column), &bold)?;
+    }
+    for (row_index, row) in rows.iter().enumerate() {
+        let row_index = u32::try_from(row_index + 1).map_err(|_err| Error::TooLarge)?;
+        for (col, column) in columns.iter().enumerate() {
+            let col = u16::try_from(col).map_err(|_err| Error::TooLarge)?;
+            worksheet.write_string(row_index, col, row.cell(*column))?;
+        }
+    }
+    worksheet.set_freeze_panes(1, 0)?;
+
+    Ok(workbook.save_to_buffer()?)
+}
+
+pub fn render<R: Row>(rows: &[R], columns: &[R::Column], format: Format) -> Result<Vec<u8>, Error> {
+    match format {
+        Format::Csv => render_csv(rows, columns),
+        Format::Xlsx => render_xlsx(rows, columns),
+    }
+}
+
+/// Spreadsheet downloaded as attachment
+#[derive(Debug)]
+pub struct Export {
+    pub data: Vec<u8>,
+    pub format: Format,
+    /// Without extension
+    pub name: String,
+}
+
+impl<'r> Responder<'r, 'static> for Export {
+    fn respond_to(self, request: &'r rocket::Request<'_>) -> rocket::response::Result<'static> {
+        let disposition = format!(
+            "attachment; filename=\"{}.{}\"",
+            self.name,
+            self.format.as_str()
+        );
+
+        rocket::response::Response::build_from(self.data.respond_to(request)?)
+            .header(self.format.content_type())
+            .raw_header("Content-Disposition", disposition)
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy)]
+    enum Column {
+        Name,
+        City,
+    }
+
+    struct Person(&'static str, Option<&'static str>);
+
+    impl Row for Person {
+        type Column = Column;
+
+        fn header(column: Column) -> &'static str {
+            match column {
+                Column::Name => "Name",
+                Column::City => "City",
+            }
+        }
+
+        fn cell(&self, column: Column) -> String {
+            match column {
+                Column::Name => self.0.to_string(),
+                Column::City => self.1.unwrap_or_default().to_string(),
+            }
+        }
+    }
+
+    fn csv(columns: &[Column]) -> String {
+        let rows = [Person("Jan Novák", Some("Brno")), Person("Doe, Jane", None)];
+        String::from_utf8(render(&rows, columns, Format::Csv).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn csv_has_header_and_selected_columns_in_order() {
+        assert_eq!(
+            csv(&[Column::City, Column::Name]),
+            "\u{feff}City,Name\nBrno,Jan Novák\n,\"Doe, Jane\"\n"
+        );
+    }
+
+    #[test]
+    fn csv_neutralizes_formulas() {
+        let rows = [
+            Person("=HYPERLINK(\"http://example.com\")", Some("+420 123")),
+            Person("@SUM(A1)", Some("-1")),
+        ];
+        let data = render(&rows, &[Column::Name, Column::City], Format::Csv).unwrap();
+
+        assert_eq!(
+            String::from_utf8(data).unwrap(),
+            "\u{feff}Name,City\n\"'=HYPERLINK(\"\"http://example.com\"\")\",'+420 123\n'@SUM(A1),'-1\n"
+        );
+    }
+
+    #[test]
+    fn xlsx_is_zip_archive() {
+        let data = render(&[Person("Jan", None)], &[Column::Name], Format::Xlsx).unwrap();
+        assert!(data.starts_with(b"PK"));
+    }
+}
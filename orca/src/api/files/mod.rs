@@ -5,7 +5,7 @@ use rocket::serde::Serialize;
 use rocket::{Route, State, get, routes};
 
 use crate::api::Response;
-use crate::data::Id;
+use crate::data::{Id, Member};
 use crate::db::DbPool;
 use crate::server::oid::{JwtToken, Provider, Role};
 
@@ -63,6 +63,22 @@ WHERE id = $1
     )
     .bind(id)
 }
+
+/// File only if it belongs to the member
+pub fn read_member_file<'a>(member_id: Id<Member>, id: Id<File>) -> QueryAs<'a, File> {
+    sqlx::query_as(
+        "
+SELECT f.data, f.file_type
+FROM files AS f
+INNER JOIN members_files AS mf ON mf.file_id = f.id
+WHERE mf.member_id = $1
+    AND f.id = $2
+",
+    )
+    .bind(member_id)
+    .bind(id)
+}
+
 #[expect(clippy::redundant_type_annotations, reason = "rocket macro expansion")]
 pub fn routes() -> Vec<Route> {
     routes![get]
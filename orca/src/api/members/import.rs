@@ -0,0 +1,396 @@
+//! Import of members from CSV
+//!
+//! Columns are named after fields of new member (the same names are used by member export),
+//! unknown columns are ignored. Missing language defaults to English as it isn't part
+//! of default member export. Every row is validated with the same rules as a member
+//! created through the API. Rows with errors or duplicates are skipped, all other rows
+//! are created in a single transaction.
+use std::collections::{HashMap, HashSet};
+
+use csv::{ReaderBuilder, StringRecord, Trim};
+use log::info;
+use serde::Serialize;
+use thiserror::Error;
+use validator::{Validate, ValidationErrors};
+
+use super::{NewMember, query};
+use crate::api::ApiError;
+use crate::data::{Id, Member, MemberNumber};
+use crate::db::DbPool;
+
+#[derive(Debug, Error)]
+pub(crate) enum Error {
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("SQL Error: {0}")]
+    Sql(#[from] sqlx::Error),
+}
+
+impl From<Error> for ApiError {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Csv(err) => ApiError::bad_request(&err.to_string()),
+            Error::Sql(err) => err.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ImportedRow {
+    /// Line in the file, header is on line 1
+    line: u64,
+    /// Explicit member number, or the allocated one once the row is imported
+    member_number: Option<MemberNumber>,
+    email: Option<String>,
+    /// Validation errors and duplicates, row isn't imported when there are any
+    errors: Vec<String>,
+    /// Created member, `None` in dry run
+    member_id: Option<Id<Member>>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ImportReport {
+    dry_run: bool,
+    valid: usize,
+    invalid: usize,
+    imported: usize,
+    rows: Vec<ImportedRow>,
+}
+
+struct Parsed {
+    row: ImportedRow,
+    /// `None` when the row can't be imported
+    member: Option<NewMember>,
+}
+
+fn column<'r>(headers: &StringRecord, record: &'r StringRecord, name: &str) -> Option<&'r str> {
+    let index = headers.iter().position(|header| header == name)?;
+    record.get(index).filter(|value| !value.is_empty())
+}
+
+fn describe(err: &csv::Error, headers: &StringRecord) -> String {
+    if let csv::ErrorKind::Deserialize { err, .. } = err.kind() {
+        let field = err
+            .field()
+            .and_then(|index| headers.get(usize::try_from(index).ok()?));
+        match field {
+            Some(field) => format!("{field}: {}", err.kind()),
+            None => err.kind().to_string(),
+        }
+    } else {
+        err.to_string()
+    }
+}
+
+fn validation_errors(errors: &ValidationErrors) -> Vec<String> {
+    let mut messages: Vec<String> = errors
+        .field_errors()
+        .into_iter()
+        .flat_map(|(field, errors)| {
+            errors.iter().map(move |error| {
+                let message = error.message.as_ref().unwrap_or(&error.code);
+                format!("{field}: {message}")
+            })
+        })
+        .collect();
+    messages.sort();
+    messages
+}
+
+/// Language of members imported without one
+const DEFAULT_LANGUAGE: &str = "en";
+
+/// Member export prefixes values which spreadsheets would take for formulas with `'`
+fn strip_formula_escape(value: &str) -> &str {
+    match value.strip_prefix('\'') {
+        Some(rest) if rest.starts_with(['=', '+', '-', '@', '\t', '\r']) => rest,
+        _ => value,
+    }
+}
+
+fn parse(text: &str) -> Result<Vec<Parsed>, csv::Error> {
+    // Exported files start with byte order mark
+    let text = text.trim_start_matches('\u{feff}');
+    let mut reader = ReaderBuilder::new()
+        .trim(Trim::All)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let mut headers = reader.headers()?.clone();
+    let language_index = if let Some(index) = headers.iter().position(|header| header == "language")
+    {
+        index
+    } else {
+        headers.push_field("language");
+        headers.len() - 1
+    };
+
+    let mut parsed = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        if record.iter().all(str::is_empty) {
+            continue;
+        }
+        let line = record.position().map_or(0, csv::Position::line);
+        let mut fields: Vec<&str> = record.iter().map(strip_formula_escape).collect();
+        if fields.len() <= language_index {
+            fields.resize(language_index + 1, "");
+        }
+        if fields[language_index].is_empty() {
+            fields[language_index] = DEFAULT_LANGUAGE;
+        }
+        let record = StringRecord::from(fields);
+
+        let (row, member) = match record.deserialize::<NewMember>(Some(&headers)) {
+            Ok(member) => {
+                let errors = member
+                    .validate()
+                    .err()
+                    .map(|errors| validation_errors(&errors))
+                    .unwrap_or_default();
+                let row = ImportedRow {
+                    line,
+                    member_number: member.member_number,
+                    email: member.email.clone(),
+                    errors,
+                    member_id: None,
+                };
+                (row, Some(member))
+            }
+            Err(err) => {
+                let row = ImportedRow {
+                    line,
+                    member_number: None,
+                    email: column(&headers, &record, "email").map(ToString::to_string),
+                    errors: vec![describe(&err, &headers)],
+                    member_id: None,
+                };
+                (row, None)
+            }
+        };
+        parsed.push(Parsed { row, member });
+    }
+
+    Ok(parsed)
+}
+
+/// Report rows repeating email or member number of an existing member or of an earlier row.
+/// Emails are compared case insensitively, `existing` emails must be lowercase.
+fn mark_duplicates(rows: &mut [Parsed], existing: &[(MemberNumber, String)]) {
+    let existing_emails: HashMap<&str, MemberNumber> = existing
+        .iter()
+        .map(|(number, email)| (email.as_str(), *number))
+        .collect();
+    let existing_numbers: HashSet<MemberNumber> =
+        existing.iter().map(|(number, _)| *number).collect();
+
+    let mut emails: HashMap<String, u64> = HashMap::new();
+    let mut numbers: HashMap<MemberNumber, u64> = HashMap::new();
+
+    for Parsed { row, member } in rows.iter_mut() {
+        if member.is_none() {
+            continue;
+        }
+
+        if let Some(email) = &row.email {
+            let email = email.to_lowercase();
+            if let Some(number) = existing_emails.get(email.as_str()) {
+                row.errors
+                    .push(format!("email: already used by member {number}"));
+            } else if let Some(line) = emails.get(&email) {
+                row.errors.push(format!("email: same as on line {line}"));
+            } else {
+                emails.insert(email, row.line);
+            }
+        }
+
+        if let Some(number) = row.member_number {
+            if existing_numbers.contains(&number) {
+                row.errors
+                    .push(format!("member_number: {number} already exists"));
+            } else if let Some(line) = numbers.get(&number) {
+                row.errors
+                    .push(format!("member_number: same as on line {line}"));
+            } else {
+                numbers.insert(number, row.line);
+            }
+        }
+    }
+}
+
+/// Validate members in CSV `text` and unless `dry_run` is set create the valid ones.
+/// Rows without member number get numbers following both existing and imported ones.
+pub(crate) async fn import(
+    db_pool: &DbPool,
+    text: &str,
+    dry_run: bool,
+) -> Result<ImportReport, Error> {
+    let mut rows = parse(text)?;
+    let existing = query::list_member_identifiers().fetch_all(db_pool).await?;
+    mark_duplicates(&mut rows, &existing);
+
+    let valid = rows
+        .iter()
+        .filter(|parsed| parsed.row.errors.is_empty())
+        .count();
+    let mut report = ImportReport {
+        dry_run,
+        valid,
+        invalid: rows.len() - valid,
+        imported: 0,
+        rows: Vec::new(),
+    };
+
+    if dry_run {
+        report.rows = rows.into_iter().map(|parsed| parsed.row).collect();
+        return Ok(report);
+    }
+
+    let mut tx = db_pool.begin().await?;
+
+    // Explicit numbers are reserved so that allocated ones don't collide with later rows
+    let (next_number,) = query::get_next_member_number().fetch_one(&mut *tx).await?;
+    let mut next_number = Some(next_number);
+    if let Some(highest) = rows
+        .iter()
+        .filter(|parsed| parsed.row.errors.is_empty())
+        .filter_map(|parsed| parsed.row.member_number)
+        .max()
+    {
+        // Explicit numbers are validated to have a following one
+        next_number = next_number.max(highest.next());
+    }
+
+    for Parsed { mut row, member } in rows {
+        if let Some(member) = member.filter(|_| row.errors.is_empty()) {
+            let member_number = match (member.member_number, next_number) {
+                (Some(number), _) => number,
+                (None, Some(number)) => {
+                    next_number = number.next();
+                    number
+                }
+                (None, None) => {
+                    row.errors
+                        .push("member_number: no number left to allocate".to_string());
+                    report.valid -= 1;
+                    report.invalid += 1;
+                    report.rows.push(row);
+                    continue;
+                }
+            };
+            let summary = query::create_member(member_number, &member)
+                .fetch_one(&mut *tx)
+                .await?;
+            row.member_number = Some(summary.member_number);
+            row.member_id = Some(summary.id);
+            report.imported += 1;
+        }
+        report.rows.push(row);
+    }
+
+    tx.commit().await?;
+
+    info!("Imported {} member(s) from CSV", report.imported);
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "member_number,first_name,last_name,email,language\n";
+
+    fn parse_rows(rows: &str) -> Vec<Parsed> {
+        parse(&format!("{HEADER}{rows}")).unwrap()
+    }
+
+    fn number(value: i32) -> MemberNumber {
+        rocket::serde::json::from_str(&value.to_string()).unwrap()
+    }
+
+    #[test]
+    fn valid_rows_are_parsed() {
+        let rows = parse(&format!(
+            "\u{feff}{HEADER}42, Jana ,Nováková,jana@example.com,cs\n,Petr,,petr@example.com,en\n\n"
+        ))
+        .unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().all(|parsed| parsed.row.errors.is_empty()));
+        let jana = rows[0].member.as_ref().unwrap();
+        assert_eq!(jana.member_number, Some(number(42)));
+        assert_eq!(jana.first_name.as_deref(), Some("Jana"));
+        let petr = rows[1].member.as_ref().unwrap();
+        assert_eq!(petr.member_number, None);
+        assert_eq!(petr.last_name, None);
+        assert_eq!(rows[1].row.line, 3);
+    }
+
+    #[test]
+    fn formula_escapes_of_export_are_removed() {
+        let rows = parse_rows("1,'-,'@home,jana@example.com,cs\n");
+        let jana = rows[0].member.as_ref().unwrap();
+
+        assert_eq!(jana.first_name.as_deref(), Some("-"));
+        assert_eq!(jana.last_name.as_deref(), Some("@home"));
+    }
+
+    #[test]
+    fn missing_language_defaults_to_english() {
+        let rows = parse("email,first_name\njana@example.com,Jana\npetr@example.com\n").unwrap();
+
+        assert!(rows.iter().all(|parsed| parsed.row.errors.is_empty()));
+        assert_eq!(rows[0].member.as_ref().unwrap().language, "en");
+        assert_eq!(rows[1].member.as_ref().unwrap().language, "en");
+    }
+
+    #[test]
+    fn invalid_rows_are_reported() {
+        let rows = parse_rows("x,Jana,,jana@example.com,cs\n1,Petr,,,en\n2,Karel,,karel,en\n");
+
+        assert!(rows[0].member.is_none());
+        assert_eq!(rows[0].row.email.as_deref(), Some("jana@example.com"));
+        assert!(rows[0].row.errors[0].starts_with("member_number: "));
+        assert_eq!(rows[1].row.errors, vec!["email: required"]);
+        assert_eq!(rows[2].row.errors, vec!["email: email"]);
+    }
+
+    #[test]
+    fn highest_member_number_is_rejected() {
+        let rows = parse_rows(&format!(
+            "{},Jana,,jana@example.com,cs\n{},Petr,,petr@example.com,en\n",
+            i32::MAX,
+            i32::MAX - 1
+        ));
+
+        assert_eq!(rows[0].row.errors, vec!["member_number: too_high"]);
+        assert!(rows[1].row.errors.is_empty());
+        assert_eq!(number(i32::MAX - 1).next(), Some(number(i32::MAX)));
+        assert_eq!(number(i32::MAX).next(), None);
+    }
+
+    #[test]
+    fn duplicates_are_reported() {
+        let mut rows = parse_rows(
+            "1,Jana,,jana@example.com,cs\n\
+             2,Petr,,petr@example.com,en\n\
+             2,Karel,,KAREL@example.com,en\n\
+             ,Eva,,Petr@Example.com,cs\n",
+        );
+        mark_duplicates(&mut rows, &[(number(1), "karel@example.com".to_string())]);
+
+        let errors: Vec<&Vec<String>> = rows.iter().map(|parsed| &parsed.row.errors).collect();
+        assert_eq!(
+            errors,
+            [
+                &vec!["member_number: 1 already exists".to_string()],
+                &vec![],
+                &vec![
+                    "email: already used by member 1".to_string(),
+                    "member_number: same as on line 3".to_string()
+                ],
+                &vec!["email: same as on line 3".to_string()],
+            ]
+        );
+    }
+}
@@ -1,25 +1,35 @@
 use std::collections::HashMap;
 
-use chrono::{DateTime, NaiveDate, Utc};
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
 use handlebars::Handlebars;
+use log::{error, info};
+use rocket::data::{Data, ToByteUnit};
+use rocket::http::Status;
+use rocket::response::Redirect;
 use rocket::serde::json::Json;
-use rocket::{Route, State, delete, get, patch, post, put, routes};
+use rocket::{FromFormField, Route, State, delete, get, patch, post, put, routes};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
+pub mod import;
 pub mod query;
 
-use super::ApiError;
 use super::SuccessResponse;
+use super::workplaces::scope::WorkplaceScope;
+use super::{ApiError, Patch, validate_non_empty};
 use crate::api::Response;
 use crate::api::files::FileInfo;
-use crate::data::{Id, Member, MemberNumber, RegistrationRequest, Workplace};
+use crate::config::Config;
+use crate::data::{self, Company, Id, Member, MemberNumber, RegistrationRequest, Workplace};
 use crate::db::DbPool;
+use crate::generate;
+use crate::listmonk::{self, Connection, sync::Mismatch};
 use crate::processing::{Command, QueueSender};
 use crate::server::oid::{JwtToken, Provider, RealmManagementRole, Role, User};
 use crate::validation::Validated;
 
+/// Company names only include current employers unless history is requested
 #[derive(Debug, Serialize, sqlx::FromRow)]
 pub struct Summary {
     id: Id<Member>,
@@ -38,62 +48,167 @@ pub struct Summary {
     sub: Option<Uuid>,
 }
 
-#[get("/")]
+#[get("/?<history>")]
 async fn list_all(
     db_pool: &State<DbPool>,
     oid_provider: &State<Provider>,
     token: JwtToken<'_>,
+    history: Option<bool>,
 ) -> Response<Json<Vec<Summary>>> {
     oid_provider.require_role(&token, Role::ListMembers)?;
 
-    let summaries = query::list_summaries().fetch_all(db_pool.inner()).await?;
+    let summaries = query::list_summaries(history.unwrap_or(false))
+        .fetch_all(db_pool.inner())
+        .await?;
     Ok(Json(summaries))
 }
 
-#[get("/past")]
+#[get("/past?<history>")]
 async fn list_past(
     db_pool: &State<DbPool>,
     oid_provider: &State<Provider>,
     token: JwtToken<'_>,
+    history: Option<bool>,
 ) -> Response<Json<Vec<Summary>>> {
     oid_provider.require_role(&token, Role::ListMembers)?;
 
-    let summaries = query::list_past_summaries()
+    let summaries = query::list_past_summaries(history.unwrap_or(false))
         .fetch_all(db_pool.inner())
         .await?;
     Ok(Json(summaries))
 }
 
-#[get("/new")]
+#[get("/new?<history>")]
 async fn list_new(
     db_pool: &State<DbPool>,
     oid_provider: &State<Provider>,
     token: JwtToken<'_>,
+    history: Option<bool>,
 ) -> Response<Json<Vec<Summary>>> {
     oid_provider.require_role(&token, Role::ListMembers)?;
 
-    let summaries = query::list_new_summaries()
+    let summaries = query::list_new_summaries(history.unwrap_or(false))
         .fetch_all(db_pool.inner())
         .await?;
     Ok(Json(summaries))
 }
 
-#[get("/current")]
+#[get("/current?<history>")]
 async fn list_current(
     db_pool: &State<DbPool>,
     oid_provider: &State<Provider>,
     token: JwtToken<'_>,
+    history: Option<bool>,
 ) -> Response<Json<Vec<Summary>>> {
     oid_provider.require_role(&token, Role::ListMembers)?;
 
-    let summaries = query::list_current_summaries()
+    let summaries = query::list_current_summaries(history.unwrap_or(false))
         .fetch_all(db_pool.inner())
         .await?;
     Ok(Json(summaries))
 }
 
+#[derive(Debug, Clone, Copy, FromFormField)]
+pub enum MemberState {
+    Current,
+    New,
+    Past,
+}
+
+impl MemberState {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Current => "current",
+            Self::New => "new",
+            Self::Past => "past",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct SearchResult {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    summary: Summary,
+    /// How well member matches the search, between 0 and 1
+    score: f32,
+    /// Number of all matching members
+    #[serde(skip)]
+    total: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchPage {
+    members: Vec<SearchResult>,
+    total: i64,
+    page: u32,
+    per_page: u32,
+}
+
+const DEFAULT_PER_PAGE: u32 = 50;
+const MAX_PER_PAGE: u32 = 200;
+
+/// Page (starting at 1), its size and offset of its first item
+fn pagination(page: Option<u32>, per_page: Option<u32>) -> (u32, u32, i64) {
+    let page = page.unwrap_or(1).max(1);
+    let per_page = per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+    let offset = i64::from(page - 1) * i64::from(per_page);
+
+    (page, per_page, offset)
+}
+
+/// Search members by name, email, phone, member number, city or company.
+/// Matching ignores diacritics and tolerates typos. Best matches come first.
+#[get("/search?<q>&<state>&<page>&<per_page>")]
+async fn search(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    q: &str,
+    state: Option<MemberState>,
+    page: Option<u32>,
+    per_page: Option<u32>,
+) -> Response<Json<SearchPage>> {
+    oid_provider.require_role(&token, Role::ListMembers)?;
+
+    let q = q.trim();
+    if q.is_empty() {
+        return Err(ApiError::bad_request("Search query can't be empty"));
+    }
+
+    let (page, per_page, offset) = pagination(page, per_page);
+
+    let mut tx = db_pool.inner().begin().await?;
+
+    query::set_search_threshold().execute(&mut *tx).await?;
+    let state = state.map(MemberState::as_str);
+    let members = query::search(q, state, i64::from(per_page), offset)
+        .fetch_all(&mut *tx)
+        .await?;
+
+    // Page past the end doesn't carry the total, count it with the first match
+    let total = match members.first() {
+        Some(member) => member.total,
+        None if offset > 0 => query::search(q, state, 1, 0)
+            .fetch_optional(&mut *tx)
+            .await?
+            .map_or(0, |member| member.total),
+        None => 0,
+    };
+
+    tx.commit().await?;
+
+    Ok(Json(SearchPage {
+        members,
+        total,
+        page,
+        per_page,
+    }))
+}
+
 #[derive(Deserialize, Validate)]
 pub struct NewMember {
+    #[validate(custom(function = "validate_member_number"))]
     member_number: Option<MemberNumber>,
     first_name: Option<String>,
     last_name: Option<String>,
@@ -138,6 +253,32 @@ async fn create_member(
     Ok(Json(summary))
 }
 
+/// Create members from CSV file with the same columns as member export.
+/// With `dry_run` rows are only validated and checked for duplicates.
+#[post("/import?<dry_run>", data = "<file>")]
+async fn import_members(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    dry_run: Option<bool>,
+    file: Data<'_>,
+) -> Response<Json<import::ImportReport>> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    let file = file
+        .open(10.mebibytes())
+        .into_string()
+        .await
+        .map_err(|_err| Status::BadRequest)?;
+    if !file.is_complete() {
+        return Err(Status::PayloadTooLarge.into());
+    }
+
+    let report = import::import(db_pool, &file, dry_run.unwrap_or(false)).await?;
+
+    Ok(Json(report))
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct EmailInfo {
@@ -214,6 +355,10 @@ pub struct Detail {
     created_at: DateTime<Utc>,
     workplace_id: Option<Id<Workplace>>,
     sub: Option<Uuid>,
+    suspended_at: Option<DateTime<Utc>>,
+    suspension_reason: Option<String>,
+    /// Status of Listmonk subscription, `None` when member was never subscribed
+    listmonk_subscription: Option<String>,
 }
 
 #[get("/<id>")]
@@ -224,6 +369,10 @@ async fn detail(
     id: Id<Member>,
 ) -> Response<Json<Detail>> {
     oid_provider.require_any_role(&token, &[Role::ListMembers, Role::ViewMember])?;
+    WorkplaceScope::of(db_pool, oid_provider, &token)
+        .await?
+        .require_member(db_pool, id)
+        .await?;
 
     let detail = query::detail(id).fetch_one(db_pool.inner()).await?;
 
@@ -233,14 +382,20 @@ async fn detail(
 #[derive(Debug, sqlx::FromRow)]
 pub struct MemberStatusData {
     sub: Option<Uuid>,
+    email: Option<String>,
     left_at: Option<DateTime<Utc>>,
     onboarding_finished_at: Option<DateTime<Utc>>,
+    suspended_at: Option<DateTime<Utc>>,
 }
 
 impl MemberStatusData {
     pub(crate) fn sub(&self) -> Option<Uuid> {
         self.sub
     }
+
+    pub(crate) fn has_left(&self) -> bool {
+        self.left_at.is_some()
+    }
 }
 
 #[patch("/<id>/accept")]
@@ -291,7 +446,13 @@ async fn list_files(
 pub struct Occupation {
     id: Id<Occupation>,
     company_name: Option<String>,
+    /// Canonical company of `company_name`
+    company_id: Option<Id<Company>>,
     position: Option<String>,
+    started_on: Option<NaiveDate>,
+    ended_on: Option<NaiveDate>,
+    /// Member still works for the company
+    current: bool,
     created_at: DateTime<Utc>,
 }
 
@@ -303,6 +464,10 @@ async fn list_occupations(
     id: Id<Member>,
 ) -> Response<Json<Vec<Occupation>>> {
     oid_provider.require_any_role(&token, &[Role::ListMembers, Role::ViewMember])?;
+    WorkplaceScope::of(db_pool, oid_provider, &token)
+        .await?
+        .require_member(db_pool, id)
+        .await?;
 
     let occupations = query::list_occupations(id)
         .fetch_all(db_pool.inner())
@@ -311,6 +476,127 @@ async fn list_occupations(
     Ok(Json(occupations))
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct NewOccupation {
+    company_name: Option<String>,
+    /// Suggested automatically from company name when not set
+    company_id: Option<Id<Company>>,
+    position: Option<String>,
+    started_on: Option<NaiveDate>,
+    ended_on: Option<NaiveDate>,
+    /// Defaults to current unless employment has ended
+    current: Option<bool>,
+}
+
+impl NewOccupation {
+    fn is_current(&self) -> bool {
+        self.current.unwrap_or(self.ended_on.is_none())
+    }
+}
+
+/// Fields which are not present are left unchanged, `null` clears them.
+/// Changing `ended_on` without `current` updates it the same way as on create.
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct UpdateOccupation {
+    #[serde(default)]
+    company_name: Patch<String>,
+    #[serde(default)]
+    company_id: Patch<Id<Company>>,
+    #[serde(default)]
+    position: Patch<String>,
+    #[serde(default)]
+    started_on: Patch<NaiveDate>,
+    #[serde(default)]
+    ended_on: Patch<NaiveDate>,
+    current: Option<bool>,
+}
+
+fn check_occupation_dates(
+    started_on: Option<NaiveDate>,
+    ended_on: Option<NaiveDate>,
+) -> Result<(), ApiError> {
+    match (started_on, ended_on) {
+        (Some(started_on), Some(ended_on)) if ended_on < started_on => Err(ApiError::bad_request(
+            "Employment can't end before it started",
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[post("/<id>/occupations", format = "json", data = "<occupation>")]
+async fn create_occupation(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Member>,
+    occupation: Json<NewOccupation>,
+) -> Response<Json<Occupation>> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+    check_occupation_dates(occupation.started_on, occupation.ended_on)?;
+
+    query::get_status_data(id)
+        .fetch_optional(db_pool.inner())
+        .await?
+        .ok_or(Status::NotFound)?;
+
+    let occupation = query::create_occupation(id, &occupation)
+        .fetch_one(db_pool.inner())
+        .await?;
+
+    Ok(Json(occupation))
+}
+
+#[patch(
+    "/<id>/occupations/<occupation_id>",
+    format = "json",
+    data = "<occupation>"
+)]
+async fn update_occupation(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Member>,
+    occupation_id: Id<Occupation>,
+    occupation: Json<UpdateOccupation>,
+) -> Response<Json<Occupation>> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    let mut tx = db_pool.begin().await?;
+
+    let occupation = query::update_occupation(id, occupation_id, &occupation)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(Status::NotFound)?;
+    // Dates are checked after the update so that partial change is compared with stored value
+    check_occupation_dates(occupation.started_on, occupation.ended_on)?;
+
+    tx.commit().await?;
+
+    Ok(Json(occupation))
+}
+
+#[delete("/<id>/occupations/<occupation_id>")]
+async fn delete_occupation(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Member>,
+    occupation_id: Id<Occupation>,
+) -> Response<SuccessResponse> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    let result = query::delete_occupation(id, occupation_id)
+        .execute(db_pool.inner())
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(Status::NotFound.into());
+    }
+
+    Ok(SuccessResponse::NoContent)
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct Note {
@@ -334,13 +620,19 @@ async fn update_note(
     Ok(Json(detail))
 }
 
+/// Emails differing only in case belong to the same person
+fn is_same_email(current: Option<&str>, email: &str) -> bool {
+    current.is_some_and(|current| current.to_lowercase() == email.to_lowercase())
+}
+
+/// Board can still change email directly without confirmation,
+/// missing `email` keeps the current one
 #[derive(Debug, Deserialize, Validate)]
 #[serde(crate = "rocket::serde")]
 pub struct UpdateMember {
     first_name: Option<String>,
     last_name: Option<String>,
     date_of_birth: Option<NaiveDate>,
-    #[validate(required)]
     #[validate(email)]
     email: Option<String>,
     phone_number: Option<String>,
@@ -355,25 +647,87 @@ pub struct UpdateMember {
 async fn update_member(
     db_pool: &State<DbPool>,
     oid_provider: &State<Provider>,
+    queue: &State<QueueSender>,
     token: JwtToken<'_>,
     id: Id<Member>,
     data: Validated<Json<UpdateMember>>,
 ) -> Response<Json<Detail>> {
     oid_provider.require_role(&token, Role::ManageMembers)?;
 
-    let result = query::update_member(id, data.into_inner().into_inner())
-        .fetch_one(db_pool.inner())
+    let data = data.into_inner().into_inner();
+
+    let mut tx = db_pool.begin().await?;
+
+    let status = query::get_status_data(id).fetch_one(&mut *tx).await?;
+    let new_email = data
+        .email
+        .clone()
+        .filter(|email| !is_same_email(status.email.as_deref(), email));
+
+    if let Some(email) = &new_email {
+        let (used,) = query::is_email_used(email, id).fetch_one(&mut *tx).await?;
+        if used {
+            return Err(ApiError::data_conflict("Email is already used by a member"));
+        }
+        // Confirmation links sent before would overwrite the new email
+        query::cancel_pending_email_changes(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let result = query::update_member(id, data).fetch_one(&mut *tx).await?;
+
+    // Transaction is committed only after OIDC provider accepted the new email
+    if let (Some(email), Some(sub)) = (&new_email, status.sub) {
+        let service_token = oid_provider.service_token().await?;
+        oid_provider
+            .update_user_email(&JwtToken::new(&service_token), sub, email)
+            .await
+            .map_err(|err| ApiError::keycloak_push(&err.to_string()))?;
+    }
+
+    tx.commit().await?;
+
+    // Listmonk gets the new email with the rest of the changes
+    queue
+        .inner()
+        .send(Command::SyncListmonkSubscriber(id))
         .await?;
 
     Ok(Json(result))
 }
 
-#[delete("/<id>", format = "json")]
+/// Why member left the organization
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, FromFormField)]
+#[serde(rename_all = "lowercase")]
+pub enum LeftReason {
+    Resigned,
+    Expelled,
+    /// Membership lapsed for non-payment of dues
+    Lapsed,
+    Deceased,
+}
+
+impl LeftReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Resigned => "resigned",
+            Self::Expelled => "expelled",
+            Self::Lapsed => "lapsed",
+            Self::Deceased => "deceased",
+        }
+    }
+}
+
+/// Ends membership, by default because member resigned
+#[delete("/<id>?<reason>", format = "json")]
 async fn remove_member(
     db_pool: &State<DbPool>,
     oid_provider: &State<Provider>,
+    queue: &State<QueueSender>,
     token: JwtToken<'_>,
     id: Id<Member>,
+    reason: Option<LeftReason>,
 ) -> Response<Json<Detail>> {
     oid_provider.require_role(&token, Role::ManageMembers)?;
 
@@ -381,24 +735,185 @@ async fn remove_member(
 
     let status = query::get_status_data(id).fetch_one(&mut *tx).await?;
 
+    if status.left_at.is_some() {
+        return Err(ApiError::data_conflict(&format!(
+            "Id {id} is no longer a member of organization"
+        )));
+    }
+
+    // Mark in database, remove workplace associations and end positions
+    super::workplaces::query::remove_member_workplace_associations(id)
+        .execute(&mut *tx)
+        .await?;
+    let ended_positions = super::workplaces::query::end_member_positions(id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+    // Groups are synced the same way as when board ends the position,
+    // account has to exist until then
+    for (workplace_id,) in ended_positions {
+        super::workplaces::sync_keycloak_group(&mut tx, oid_provider, &token, workplace_id, id)
+            .await?;
+    }
+
     // Remove from keycloak if paired
     if let Some(uuid) = status.sub {
         oid_provider.inner().remove_user(&token, uuid).await?;
     }
 
+    query::close_membership_period(id, reason.unwrap_or(LeftReason::Resigned))
+        .execute(&mut *tx)
+        .await?;
+    let detail = query::remove_member(id).fetch_one(&mut *tx).await?;
+
+    tx.commit().await?;
+
+    // Blocklist in Listmonk so former members don't receive newsletters
+    queue
+        .inner()
+        .send(Command::SyncListmonkSubscriber(id))
+        .await?;
+
+    Ok(Json(detail))
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct MembershipPeriod {
+    id: Id<MembershipPeriod>,
+    joined_at: DateTime<Utc>,
+    left_at: Option<DateTime<Utc>>,
+    left_reason: Option<String>,
+    note: Option<String>,
+}
+
+impl MembershipPeriod {
+    /// Nobody re-joins after their death was recorded
+    fn is_final(&self) -> bool {
+        self.left_reason.as_deref() == Some(LeftReason::Deceased.as_str())
+    }
+}
+
+#[get("/<id>/periods")]
+async fn list_periods(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Member>,
+) -> Response<Json<Vec<MembershipPeriod>>> {
+    oid_provider.require_any_role(&token, &[Role::ListMembers, Role::ViewMember])?;
+    WorkplaceScope::of(db_pool, oid_provider, &token)
+        .await?
+        .require_member(db_pool, id)
+        .await?;
+
+    let periods = query::list_membership_periods(id)
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    Ok(Json(periods))
+}
+
+/// Re-join past member under the original member number.
+/// Starts new membership period and recreates OID account.
+#[post("/<id>/rejoin")]
+async fn rejoin(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    queue: &State<QueueSender>,
+    token: JwtToken<'_>,
+    id: Id<Member>,
+) -> Response<Json<Detail>> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    let mut tx = db_pool.begin().await?;
+
+    let status = query::get_status_data(id).fetch_one(&mut *tx).await?;
+    if status.left_at.is_none() {
+        return Err(ApiError::data_conflict(&format!(
+            "Id {id} is a member of organization already"
+        )));
+    }
+
+    let periods = query::list_membership_periods(id)
+        .fetch_all(&mut *tx)
+        .await?;
+    if periods.first().is_some_and(MembershipPeriod::is_final) {
+        return Err(ApiError::data_conflict("Deceased member can't re-join"));
+    }
+
+    query::open_membership_period(id).execute(&mut *tx).await?;
+    let detail = query::rejoin_member(id).fetch_one(&mut *tx).await?;
+
+    tx.commit().await?;
+
+    queue
+        .inner()
+        .send(Command::NewMemberCreated(
+            id,
+            Some(token.as_str().to_owned()),
+        ))
+        .await?;
+    // Subscriber was blocklisted when member left
+    queue
+        .inner()
+        .send(Command::ResubscribeListmonkSubscriber(id))
+        .await?;
+
+    Ok(Json(detail))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Suspension {
+    reason: Option<String>,
+}
+
+/// Suspend membership without ending it. Repeated suspension only updates the reason.
+#[put("/<id>/suspension", format = "json", data = "<suspension>")]
+async fn suspend(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Member>,
+    suspension: Json<Suspension>,
+) -> Response<Json<Detail>> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    let status = query::get_status_data(id)
+        .fetch_one(db_pool.inner())
+        .await?;
     if status.left_at.is_some() {
         return Err(ApiError::data_conflict(&format!(
             "Id {id} is no longer a member of organization"
         )));
     }
 
-    // Mark in database and remove workplace associations
-    super::workplaces::query::remove_member_workplace_associations(id)
-        .execute(&mut *tx)
+    let detail = query::suspend_member(id, &suspension)
+        .fetch_one(db_pool.inner())
         .await?;
-    let detail = query::remove_member(id).fetch_one(&mut *tx).await?;
 
-    tx.commit().await?;
+    Ok(Json(detail))
+}
+
+#[delete("/<id>/suspension")]
+async fn lift_suspension(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Member>,
+) -> Response<Json<Detail>> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    let status = query::get_status_data(id)
+        .fetch_one(db_pool.inner())
+        .await?;
+    if status.suspended_at.is_none() {
+        return Err(ApiError::data_conflict("Member is not suspended"));
+    }
+
+    let detail = query::lift_suspension(id)
+        .fetch_one(db_pool.inner())
+        .await?;
 
     Ok(Json(detail))
 }
@@ -498,25 +1013,694 @@ async fn pair_oid(
     Ok(Json(detail))
 }
 
-#[expect(clippy::redundant_type_annotations, reason = "rocket macro expansion")]
-pub fn routes() -> Vec<Route> {
-    routes![
-        list_all,
-        list_past,
-        list_new,
-        list_current,
-        create_member,
-        send_email,
-        list_files,
-        list_occupations,
-        detail,
-        accept,
-        update_note,
+/// Member's own view of their data, without internal notes of the board
+#[derive(Debug, Serialize)]
+pub struct Profile {
+    id: Id<Member>,
+    member_number: MemberNumber,
+    first_name: Option<String>,
+    last_name: Option<String>,
+    date_of_birth: Option<NaiveDate>,
+    email: Option<String>,
+    phone_number: Option<String>,
+    address: Option<String>,
+    city: Option<String>,
+    postal_code: Option<String>,
+    language: Option<String>,
+    left_at: Option<DateTime<Utc>>,
+    onboarding_finished_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+    workplace_id: Option<Id<Workplace>>,
+    suspended_at: Option<DateTime<Utc>>,
+    listmonk_subscription: Option<String>,
+}
+
+impl From<Detail> for Profile {
+    fn from(detail: Detail) -> Self {
+        Self {
+            id: detail.id,
+            member_number: detail.member_number,
+            first_name: detail.first_name,
+            last_name: detail.last_name,
+            date_of_birth: detail.date_of_birth,
+            email: detail.email,
+            phone_number: detail.phone_number,
+            address: detail.address,
+            city: detail.city,
+            postal_code: detail.postal_code,
+            language: detail.language,
+            left_at: detail.left_at,
+            onboarding_finished_at: detail.onboarding_finished_at,
+            created_at: detail.created_at,
+            workplace_id: detail.workplace_id,
+            suspended_at: detail.suspended_at,
+            listmonk_subscription: detail.listmonk_subscription,
+        }
+    }
+}
+
+fn validate_patch_non_empty(val: &Patch<String>) -> Result<(), ValidationError> {
+    val.value().map_or(Ok(()), |val| validate_non_empty(val))
+}
+
+/// Explicit number has to leave room for numbers allocated after it
+#[expect(
+    clippy::trivially_copy_pass_by_ref,
+    reason = "validator passes a reference"
+)]
+fn validate_member_number(val: &MemberNumber) -> Result<(), ValidationError> {
+    if val.next().is_none() {
+        return Err(ValidationError::new("too_high"));
+    }
+
+    Ok(())
+}
+
+/// Two letter lowercase language code like `cs`
+fn validate_language(val: &str) -> Result<(), ValidationError> {
+    if val.len() != 2 || !val.chars().all(|c| c.is_ascii_lowercase()) {
+        return Err(ValidationError::new("language"));
+    }
+
+    Ok(())
+}
+
+/// Contact details members can change themselves.
+/// Missing fields are left untouched, optional fields can be cleared with `null`.
+#[derive(Debug, Deserialize, Validate)]
+#[serde(crate = "rocket::serde")]
+pub struct ContactUpdate {
+    #[serde(default)]
+    #[validate(custom(function = "validate_patch_non_empty"))]
+    phone_number: Patch<String>,
+    #[serde(default)]
+    #[validate(custom(function = "validate_patch_non_empty"))]
+    address: Patch<String>,
+    #[serde(default)]
+    #[validate(custom(function = "validate_patch_non_empty"))]
+    city: Patch<String>,
+    #[serde(default)]
+    #[validate(custom(function = "validate_patch_non_empty"))]
+    postal_code: Patch<String>,
+    #[validate(custom(function = "validate_language"))]
+    language: Option<String>,
+}
+
+/// Change of personal data requested by member, `None` fields are not changed
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct NewProfileChange {
+    first_name: Option<String>,
+    last_name: Option<String>,
+    date_of_birth: Option<NaiveDate>,
+}
+
+impl NewProfileChange {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.first_name.is_none() && self.last_name.is_none() && self.date_of_birth.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, FromFormField)]
+pub enum ProfileChangeStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl ProfileChangeStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Approved => "approved",
+            Self::Rejected => "rejected",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ProfileChangeRequest {
+    id: Id<data::ProfileChangeRequest>,
+    member_id: Id<Member>,
+    first_name: Option<String>,
+    last_name: Option<String>,
+    date_of_birth: Option<NaiveDate>,
+    status: String,
+    created_at: DateTime<Utc>,
+    resolved_at: Option<DateTime<Utc>>,
+}
+
+/// Changes of personal data requested by members, pending by default
+#[get("/profile-change-requests?<status>")]
+async fn list_profile_change_requests(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    status: Option<ProfileChangeStatus>,
+) -> Response<Json<Vec<ProfileChangeRequest>>> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    let requests =
+        query::list_profile_change_requests(status.unwrap_or(ProfileChangeStatus::Pending))
+            .fetch_all(db_pool.inner())
+            .await?;
+
+    Ok(Json(requests))
+}
+
+#[post("/profile-change-requests/<id>/approve")]
+async fn approve_profile_change(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    queue: &State<QueueSender>,
+    token: JwtToken<'_>,
+    id: Id<data::ProfileChangeRequest>,
+) -> Response<Json<Detail>> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    let mut tx = db_pool.begin().await?;
+
+    let request = query::resolve_profile_change_request(id, ProfileChangeStatus::Approved)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| ApiError::data_conflict("Change request is not pending"))?;
+    let detail = query::apply_profile_change_request(id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    queue
+        .inner()
+        .send(Command::SyncListmonkSubscriber(request.member_id))
+        .await?;
+
+    Ok(Json(detail))
+}
+
+#[post("/profile-change-requests/<id>/reject")]
+async fn reject_profile_change(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<data::ProfileChangeRequest>,
+) -> Response<Json<ProfileChangeRequest>> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    let request = query::resolve_profile_change_request(id, ProfileChangeStatus::Rejected)
+        .fetch_optional(db_pool.inner())
+        .await?
+        .ok_or_else(|| ApiError::data_conflict("Change request is not pending"))?;
+
+    Ok(Json(request))
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct EmailChange {
+    id: Id<data::EmailChange>,
+    member_id: Id<Member>,
+    old_email: Option<String>,
+    new_email: String,
+    created_at: DateTime<Utc>,
+    confirmed_at: Option<DateTime<Utc>>,
+    cancelled_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[serde(crate = "rocket::serde")]
+pub struct NewEmailChange {
+    #[validate(email)]
+    email: String,
+}
+
+/// Email change waiting for confirmation
+#[derive(Debug, sqlx::FromRow)]
+pub struct PendingEmailChange {
+    id: Id<data::EmailChange>,
+    member_id: Id<Member>,
+    /// Member might have had no email before
+    old_email: Option<String>,
+    new_email: String,
+    created_at: DateTime<Utc>,
+    sub: Option<Uuid>,
+    language: Option<String>,
+}
+
+/// How long confirmation links of email changes are valid
+const EMAIL_CHANGE_VALIDITY: TimeDelta = TimeDelta::days(2);
+
+impl PendingEmailChange {
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.created_at + EMAIL_CHANGE_VALIDITY <= now
+    }
+}
+
+/// Start change of email by sending confirmation link to the new address.
+/// Previous unconfirmed changes of the member are cancelled.
+pub(crate) async fn request_email_change(
+    db_pool: &DbPool,
+    queue: &QueueSender,
+    id: Id<Member>,
+    change: &NewEmailChange,
+) -> Result<EmailChange, ApiError> {
+    let mut tx = db_pool.begin().await?;
+
+    let status = query::get_status_data(id).fetch_one(&mut *tx).await?;
+    if status.left_at.is_some() {
+        return Err(ApiError::data_conflict(&format!(
+            "Id {id} is no longer a member of organization"
+        )));
+    }
+
+    let (used,) = query::is_email_used(&change.email, id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if used {
+        return Err(ApiError::data_conflict("Email is already used by a member"));
+    }
+
+    query::cancel_pending_email_changes(id)
+        .execute(&mut *tx)
+        .await?;
+    let email_change = query::create_email_change(id, &change.email, generate::string(64))
+        .fetch_one(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    queue
+        .send(Command::SendEmailChangeConfirmation(email_change.id))
+        .await?;
+
+    Ok(email_change)
+}
+
+#[post("/<id>/email-changes", format = "json", data = "<change>")]
+async fn change_email(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    queue: &State<QueueSender>,
+    token: JwtToken<'_>,
+    id: Id<Member>,
+    change: Validated<Json<NewEmailChange>>,
+) -> Response<Json<EmailChange>> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    let email_change = request_email_change(db_pool, queue, id, &change.into_inner()).await?;
+
+    Ok(Json(email_change))
+}
+
+#[get("/<id>/email-changes")]
+async fn list_email_changes(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Member>,
+) -> Response<Json<Vec<EmailChange>>> {
+    oid_provider.require_any_role(&token, &[Role::ManageMembers, Role::ViewMember])?;
+    WorkplaceScope::of(db_pool, oid_provider, &token)
+        .await?
+        .require_member(db_pool, id)
+        .await?;
+
+    let changes = query::list_email_changes(id)
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    Ok(Json(changes))
+}
+
+/// Set email of member's OIDC user and Listmonk subscriber
+async fn push_email(
+    config: &Config,
+    oid_provider: &Provider,
+    db_pool: &DbPool,
+    change: &PendingEmailChange,
+    email: &str,
+) -> Result<(), ApiError> {
+    if let Some(sub) = change.sub {
+        let service_token = oid_provider.service_token().await?;
+        oid_provider
+            .update_user_email(&JwtToken::new(&service_token), sub, email)
+            .await
+            .map_err(|err| ApiError::keycloak_push(&err.to_string()))?;
+    }
+
+    if let Some(connection) = Connection::from_config(config)? {
+        listmonk::sync::set_member_email(
+            &connection,
+            &config.listmonk_routing,
+            db_pool,
+            change.member_id,
+            email,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Confirm email change using link from the email sent to the new address.
+///
+/// Email is changed in orca, OIDC provider and Listmonk all together.
+/// When any of them fails the others are reverted and the link can be used again.
+#[get("/email-changes/<code>/confirm")]
+async fn confirm_email_change(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    config: &State<Config>,
+    queue: &State<QueueSender>,
+    code: &str,
+) -> Response<Redirect> {
+    let mut tx = db_pool.begin().await?;
+
+    let Some(change) = query::get_pending_email_change(code)
+        .fetch_optional(&mut *tx)
+        .await?
+        .filter(|change| !change.is_expired(Utc::now()))
+    else {
+        // Link could have been clicked again
+        info!("Email change token not found, redirecting still...");
+        return Ok(Redirect::found(
+            config.email_change_redirect_for_local("default"),
+        ));
+    };
+
+    let confirmed = query::confirm_email_change(change.id)
+        .execute(&mut *tx)
+        .await?;
+    if confirmed.rows_affected() == 0 {
+        return Err(ApiError::data_conflict("Email change is no longer pending"));
+    }
+    query::set_member_email(change.member_id, &change.new_email)
+        .execute(&mut *tx)
+        .await?;
+
+    // Transaction is committed only after other systems accepted the change
+    let result = match push_email(config, oid_provider, db_pool, &change, &change.new_email).await {
+        Ok(()) => tx.commit().await.map_err(ApiError::from),
+        Err(err) => Err(err),
+    };
+
+    if let Err(err) = result {
+        // Member without previous email has nothing to be reverted to
+        if let Some(old_email) = &change.old_email
+            && let Err(revert_err) =
+                push_email(config, oid_provider, db_pool, &change, old_email).await
+        {
+            error!(
+                "Failed to revert email of member {} to {old_email}: {revert_err:?}",
+                change.member_id
+            );
+        }
+        return Err(err);
+    }
+
+    queue
+        .inner()
+        .send(Command::SendEmailChangedNotification(change.id))
+        .await?;
+
+    Ok(Redirect::found(config.email_change_redirect_for_local(
+        change.language.as_deref().unwrap_or("default"),
+    )))
+}
+
+#[get("/listmonk/reconciliation")]
+async fn listmonk_reconciliation(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    config: &State<Config>,
+    token: JwtToken<'_>,
+) -> Response<Json<Vec<Mismatch>>> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    let connection =
+        Connection::from_config(config)?.ok_or(ApiError::config_missing("listmonk_host"))?;
+
+    let report =
+        listmonk::sync::reconcile(&connection, &config.listmonk_routing, db_pool.inner()).await?;
+
+    Ok(Json(report))
+}
+
+/// (Re)try subscribing member to Listmonk
+/// for instance after all automatic attempts failed
+#[post("/<id>/listmonk/subscribe")]
+async fn subscribe_to_listmonk(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    config: &State<Config>,
+    queue: &State<QueueSender>,
+    token: JwtToken<'_>,
+    id: Id<Member>,
+) -> Response<SuccessResponse> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    if !listmonk::is_enabled(config) {
+        return Err(ApiError::config_missing("listmonk_host"));
+    }
+
+    let status = query::get_status_data(id)
+        .fetch_one(db_pool.inner())
+        .await?;
+    if status.left_at.is_some() {
+        return Err(ApiError::data_conflict(&format!(
+            "Id {id} is no longer a member of organization"
+        )));
+    }
+
+    listmonk::query::create_subscription_request(id)
+        .execute(db_pool.inner())
+        .await?;
+
+    queue.inner().send(Command::SubscribeToListmonk(id)).await?;
+
+    Ok(SuccessResponse::Accepted)
+}
+
+#[post("/listmonk/pull")]
+async fn listmonk_pull(
+    oid_provider: &State<Provider>,
+    queue: &State<QueueSender>,
+    token: JwtToken<'_>,
+) -> Response<SuccessResponse> {
+    oid_provider.require_role(&token, Role::ManageMembers)?;
+
+    queue.inner().send(Command::PullListmonkStatuses).await?;
+
+    Ok(SuccessResponse::Accepted)
+}
+
+#[expect(clippy::redundant_type_annotations, reason = "rocket macro expansion")]
+pub fn routes() -> Vec<Route> {
+    routes![
+        list_all,
+        search,
+        list_past,
+        list_new,
+        list_current,
+        create_member,
+        import_members,
+        send_email,
+        list_files,
+        list_occupations,
+        create_occupation,
+        update_occupation,
+        delete_occupation,
+        detail,
+        accept,
+        update_note,
         update_member,
         remove_member,
+        list_periods,
+        rejoin,
+        suspend,
+        lift_suspension,
         list_candidate_users,
         add_to_oid_group,
         pair_oid,
         create_oid_account,
+        subscribe_to_listmonk,
+        listmonk_reconciliation,
+        listmonk_pull,
+        list_profile_change_requests,
+        approve_profile_change,
+        reject_profile_change,
+        change_email,
+        list_email_changes,
+        confirm_email_change,
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_pages_start_at_one() {
+        assert_eq!(pagination(None, None), (1, DEFAULT_PER_PAGE, 0));
+        assert_eq!(pagination(Some(0), Some(20)), (1, 20, 0));
+        assert_eq!(pagination(Some(3), Some(20)), (3, 20, 40));
+    }
+
+    #[test]
+    fn search_page_size_is_limited() {
+        assert_eq!(pagination(Some(2), Some(0)), (2, 1, 1));
+        assert_eq!(
+            pagination(Some(2), Some(10_000)),
+            (2, MAX_PER_PAGE, i64::from(MAX_PER_PAGE))
+        );
+        assert_eq!(
+            pagination(Some(u32::MAX), Some(MAX_PER_PAGE)).2,
+            i64::from(u32::MAX - 1) * i64::from(MAX_PER_PAGE)
+        );
+    }
+
+    fn period(left_reason: Option<LeftReason>) -> MembershipPeriod {
+        MembershipPeriod {
+            id: Id::from(uuid::Uuid::nil()),
+            joined_at: Utc::now(),
+            left_at: left_reason.map(|_| Utc::now()),
+            left_reason: left_reason.map(|reason| reason.as_str().to_string()),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn only_death_prevents_rejoining() {
+        assert!(period(Some(LeftReason::Deceased)).is_final());
+        assert!(!period(Some(LeftReason::Lapsed)).is_final());
+        assert!(!period(Some(LeftReason::Expelled)).is_final());
+        assert!(!period(None).is_final());
+    }
+
+    #[test]
+    fn left_reasons_are_stored_as_serialized() {
+        for reason in [
+            LeftReason::Resigned,
+            LeftReason::Expelled,
+            LeftReason::Lapsed,
+            LeftReason::Deceased,
+        ] {
+            assert_eq!(
+                rocket::serde::json::to_string(&reason).unwrap(),
+                format!("\"{}\"", reason.as_str())
+            );
+        }
+    }
+
+    fn contact_update(json: &str) -> ContactUpdate {
+        rocket::serde::json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn contact_update_keeps_missing_fields() {
+        let update = contact_update(r#"{"city": "Brno", "address": null}"#);
+
+        assert_eq!(update.city, Patch::Set("Brno".to_string()));
+        assert_eq!(update.address, Patch::Clear);
+        assert_eq!(update.phone_number, Patch::Keep);
+        update.validate().unwrap();
+    }
+
+    #[test]
+    fn contact_update_rejects_blank_values_and_bad_language() {
+        assert!(
+            contact_update(r#"{"phone_number": " "}"#)
+                .validate()
+                .is_err()
+        );
+        assert!(contact_update(r#"{"language": "CZE"}"#).validate().is_err());
+        contact_update(r#"{"language": "cs"}"#).validate().unwrap();
+    }
+
+    #[test]
+    fn profile_change_needs_some_field() {
+        let change: NewProfileChange = rocket::serde::json::from_str("{}").unwrap();
+        assert!(change.is_empty());
+
+        let change: NewProfileChange =
+            rocket::serde::json::from_str(r#"{"last_name": "Novakova"}"#).unwrap();
+        assert!(!change.is_empty());
+    }
+
+    fn email_change(created_at: DateTime<Utc>) -> PendingEmailChange {
+        PendingEmailChange {
+            id: Id::from(uuid::Uuid::nil()),
+            member_id: Id::from(uuid::Uuid::nil()),
+            old_email: None,
+            new_email: "jana@example.com".to_string(),
+            created_at,
+            sub: None,
+            language: None,
+        }
+    }
+
+    #[test]
+    fn email_change_links_expire_after_two_days() {
+        let now = Utc::now();
+
+        assert!(!email_change(now).is_expired(now));
+        assert!(!email_change(now - TimeDelta::hours(47)).is_expired(now));
+        assert!(email_change(now - TimeDelta::days(2)).is_expired(now));
+    }
+
+    #[test]
+    fn board_update_keeps_email_unless_given() {
+        let update: UpdateMember =
+            rocket::serde::json::from_str(r#"{"first_name": "Jana", "language": "cs"}"#).unwrap();
+        assert_eq!(update.email, None);
+        update.validate().unwrap();
+
+        let update: UpdateMember =
+            rocket::serde::json::from_str(r#"{"email": "jana", "language": "cs"}"#).unwrap();
+        assert!(update.validate().is_err());
+    }
+
+    #[test]
+    fn occupation_is_current_until_it_ends() {
+        let occupation: NewOccupation =
+            rocket::serde::json::from_str(r#"{"company_name": "Google"}"#).unwrap();
+        assert!(occupation.is_current());
+
+        let occupation: NewOccupation =
+            rocket::serde::json::from_str(r#"{"ended_on": "2024-05-31"}"#).unwrap();
+        assert!(!occupation.is_current());
+
+        let occupation: NewOccupation =
+            rocket::serde::json::from_str(r#"{"ended_on": "2024-05-31", "current": true}"#)
+                .unwrap();
+        assert!(occupation.is_current());
+    }
+
+    #[test]
+    fn occupation_cannot_end_before_it_started() {
+        let date = |day| NaiveDate::from_ymd_opt(2024, 5, day).unwrap();
+
+        check_occupation_dates(Some(date(1)), Some(date(1))).unwrap();
+        check_occupation_dates(Some(date(2)), None).unwrap();
+        check_occupation_dates(None, Some(date(1))).unwrap();
+        assert!(check_occupation_dates(Some(date(2)), Some(date(1))).is_err());
+    }
+
+    #[test]
+    fn occupation_update_distinguishes_missing_and_null() {
+        let update: UpdateOccupation =
+            rocket::serde::json::from_str(r#"{"position": null, "ended_on": "2024-05-31"}"#)
+                .unwrap();
+
+        assert!(matches!(update.company_name, Patch::Keep));
+        assert!(matches!(update.position, Patch::Clear));
+        assert!(matches!(update.ended_on, Patch::Set(_)));
+        assert_eq!(update.current, None);
+    }
+
+    #[test]
+    fn email_case_is_not_a_change() {
+        assert!(is_same_email(Some("Jana@x.cz"), "jana@x.cz"));
+        assert!(!is_same_email(Some("jana@x.cz"), "petr@x.cz"));
+        assert!(!is_same_email(None, "jana@x.cz"));
+    }
+}
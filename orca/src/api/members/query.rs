@@ -1,11 +1,16 @@
 use uuid::Uuid;
 
-use super::{Detail, MemberStatusData, NewMember, Note, Occupation, Summary, UpdateMember};
+use super::{
+    ContactUpdate, Detail, EmailChange, LeftReason, MemberStatusData, MembershipPeriod, NewMember,
+    NewOccupation, NewProfileChange, Note, Occupation, PendingEmailChange, ProfileChangeRequest,
+    ProfileChangeStatus, SearchResult, Summary, Suspension, UpdateMember, UpdateOccupation,
+};
 use crate::api::files::FileInfo;
-use crate::data::{Id, Member, MemberNumber};
-use crate::db::QueryAs;
+use crate::data::{self, Id, Member, MemberNumber};
+use crate::db::{Query, QueryAs};
 
-pub fn list_summaries() -> QueryAs<'static, Summary> {
+/// Only current employers are included in `company_names` unless `history` is set
+pub fn list_summaries(history: bool) -> QueryAs<'static, Summary> {
     sqlx::query_as(
         "
 SELECT m.id
@@ -18,12 +23,12 @@ SELECT m.id
     , m.city
     , m.language
     , m.left_at
-    , array_agg(o.company_name ORDER BY o.created_at DESC) AS company_names
+    , array_agg(o.company_name ORDER BY o.is_current DESC, o.started_on DESC NULLS LAST, o.created_at DESC) AS company_names
     , m.created_at
     , ARRAY(SELECT mw.workplace_id FROM members_workplaces mw WHERE mw.member_id = m.id) AS workplace_ids
     , m.sub
 FROM members AS m
-LEFT JOIN occupations o ON o.member_id = m.id
+LEFT JOIN occupations o ON o.member_id = m.id AND (o.is_current OR $1)
 GROUP BY m.id
     , m.member_number
     , m.first_name
@@ -39,9 +44,11 @@ GROUP BY m.id
 ORDER BY m.member_number DESC
 ",
     )
+    .bind(history)
 }
 
-pub fn list_past_summaries() -> QueryAs<'static, Summary> {
+/// Only current employers are included in `company_names` unless `history` is set
+pub fn list_past_summaries(history: bool) -> QueryAs<'static, Summary> {
     sqlx::query_as(
         "
 SELECT m.id
@@ -54,12 +61,12 @@ SELECT m.id
     , m.city
     , m.language
     , m.left_at
-    , array_agg(o.company_name ORDER BY o.created_at DESC) AS company_names
+    , array_agg(o.company_name ORDER BY o.is_current DESC, o.started_on DESC NULLS LAST, o.created_at DESC) AS company_names
     , m.created_at
     , ARRAY(SELECT mw.workplace_id FROM members_workplaces mw WHERE mw.member_id = m.id) AS workplace_ids
     , m.sub
 FROM members_past AS m
-LEFT JOIN occupations o ON o.member_id = m.id
+LEFT JOIN occupations o ON o.member_id = m.id AND (o.is_current OR $1)
 GROUP BY m.id
     , m.member_number
     , m.first_name
@@ -75,9 +82,11 @@ GROUP BY m.id
 ORDER BY m.member_number DESC
 ",
     )
+    .bind(history)
 }
 
-pub fn list_new_summaries() -> QueryAs<'static, Summary> {
+/// Only current employers are included in `company_names` unless `history` is set
+pub fn list_new_summaries(history: bool) -> QueryAs<'static, Summary> {
     sqlx::query_as(
         "
 SELECT m.id
@@ -90,12 +99,12 @@ SELECT m.id
     , m.city
     , m.language
     , m.left_at
-    , array_agg(o.company_name ORDER BY o.created_at DESC) AS company_names
+    , array_agg(o.company_name ORDER BY o.is_current DESC, o.started_on DESC NULLS LAST, o.created_at DESC) AS company_names
     , m.created_at
     , ARRAY(SELECT mw.workplace_id FROM members_workplaces mw WHERE mw.member_id = m.id) AS workplace_ids
     , m.sub
 FROM members_new AS m
-LEFT JOIN occupations o ON o.member_id = m.id
+LEFT JOIN occupations o ON o.member_id = m.id AND (o.is_current OR $1)
 GROUP BY m.id
     , m.member_number
     , m.first_name
@@ -111,9 +120,11 @@ GROUP BY m.id
 ORDER BY m.member_number DESC
 ",
     )
+    .bind(history)
 }
 
-pub fn list_current_summaries() -> QueryAs<'static, Summary> {
+/// Only current employers are included in `company_names` unless `history` is set
+pub fn list_current_summaries(history: bool) -> QueryAs<'static, Summary> {
     sqlx::query_as(
         "
 SELECT m.id
@@ -126,12 +137,12 @@ SELECT m.id
     , m.city
     , m.language
     , m.left_at
-    , array_agg(o.company_name ORDER BY o.created_at DESC) AS company_names
+    , array_agg(o.company_name ORDER BY o.is_current DESC, o.started_on DESC NULLS LAST, o.created_at DESC) AS company_names
     , m.created_at
     , ARRAY(SELECT mw.workplace_id FROM members_workplaces mw WHERE mw.member_id = m.id) AS workplace_ids
     , m.sub
 FROM members_current AS m
-LEFT JOIN occupations o ON o.member_id = m.id
+LEFT JOIN occupations o ON o.member_id = m.id AND (o.is_current OR $1)
 GROUP BY m.id
     , m.member_number
     , m.first_name
@@ -147,6 +158,93 @@ GROUP BY m.id
 ORDER BY m.member_number DESC
 ",
     )
+    .bind(history)
+}
+
+/// Default threshold (0.6) of `<%` operator is too strict for typos in short names.
+/// Applies until the end of transaction.
+pub fn set_search_threshold<'a>() -> Query<'a> {
+    sqlx::query("SET LOCAL pg_trgm.word_similarity_threshold = 0.4")
+}
+
+/// Members matching `term` with optional `state` (current, new or past)
+/// ordered by relevance. Uses trigram indexes created in gray-whale migrations.
+pub fn search<'a>(
+    term: &'a str,
+    state: Option<&'a str>,
+    limit: i64,
+    offset: i64,
+) -> QueryAs<'a, SearchResult> {
+    sqlx::query_as(
+        "
+WITH term AS (
+    SELECT search_normalize($1) AS value
+        -- phone numbers are stored without spaces
+        , regexp_replace(search_normalize($1), '\\s', '', 'g') AS compact
+)
+SELECT m.id
+    , m.member_number
+    , m.first_name
+    , m.last_name
+    , m.email
+    , m.phone_number
+    , m.note
+    , m.city
+    , m.language
+    , m.left_at
+    , array_agg(o.company_name ORDER BY o.is_current DESC, o.started_on DESC NULLS LAST, o.created_at DESC) AS company_names
+    , m.created_at
+    , ARRAY(SELECT mw.workplace_id FROM members_workplaces mw WHERE mw.member_id = m.id) AS workplace_ids
+    , m.sub
+    , GREATEST(
+        word_similarity(t.value, member_search_text(m.first_name, m.last_name, m.email, m.phone_number, m.city)),
+        COALESCE(MAX(word_similarity(t.value, search_normalize(o.company_name))), 0),
+        CASE WHEN m.member_number::TEXT = $1 THEN 1 ELSE 0 END
+    )::REAL AS score
+    , COUNT(*) OVER () AS total
+FROM members AS m
+CROSS JOIN term AS t
+LEFT JOIN occupations o ON o.member_id = m.id AND o.is_current
+WHERE (
+    t.value <% member_search_text(m.first_name, m.last_name, m.email, m.phone_number, m.city)
+    -- substring match, strpos has no wildcards to escape unlike LIKE
+    OR strpos(member_search_text(m.first_name, m.last_name, m.email, m.phone_number, m.city), t.compact) > 0
+    OR m.member_number::TEXT = $1
+    OR EXISTS (
+        SELECT 1 FROM occupations so
+        WHERE so.member_id = m.id
+        AND t.value <% search_normalize(so.company_name)
+    )
+)
+AND (
+    $2::TEXT IS NULL
+    OR ($2 = 'current' AND EXISTS (SELECT 1 FROM members_current v WHERE v.id = m.id))
+    OR ($2 = 'new' AND EXISTS (SELECT 1 FROM members_new v WHERE v.id = m.id))
+    OR ($2 = 'past' AND EXISTS (SELECT 1 FROM members_past v WHERE v.id = m.id))
+)
+GROUP BY m.id
+    , m.member_number
+    , m.first_name
+    , m.last_name
+    , m.email
+    , m.phone_number
+    , m.note
+    , m.city
+    , m.language
+    , m.left_at
+    , m.created_at
+    , m.sub
+    , t.value
+    , t.compact
+ORDER BY score DESC
+    , m.member_number DESC
+LIMIT $3 OFFSET $4
+",
+    )
+    .bind(term)
+    .bind(state)
+    .bind(limit)
+    .bind(offset)
 }
 
 pub fn create_member(member_number: MemberNumber, new_member: &NewMember) -> QueryAs<'_, Summary> {
@@ -215,6 +313,9 @@ SELECT id
     , created_at
     , mw.workplace_id
     , sub
+    , suspended_at
+    , suspension_reason
+    , (SELECT lr.status FROM listmonk_subscription_requests lr WHERE lr.member_id = members.id) AS listmonk_subscription
 FROM members
 LEFT JOIN members_workplaces mw ON mw.member_id = members.id
 WHERE id = $1
@@ -238,6 +339,17 @@ SELECT COALESCE
     )
 }
 
+/// Emails are lowercased so that they can be compared case insensitively
+pub fn list_member_identifiers<'a>() -> QueryAs<'a, (MemberNumber, String)> {
+    sqlx::query_as(
+        "
+SELECT member_number
+    , LOWER(email)
+FROM members
+",
+    )
+}
+
 pub fn list_member_files<'a>(id: Id<Member>) -> QueryAs<'a, FileInfo> {
     sqlx::query_as(
         "
@@ -254,18 +366,110 @@ ORDER BY f.created_at DESC
     .bind(id)
 }
 
+/// Current employers first, then from the most recent
 pub fn list_occupations<'a>(id: Id<Member>) -> QueryAs<'a, Occupation> {
     sqlx::query_as(
         "
 SELECT id
 , company_name
+, company_id
 , position
+, started_on
+, ended_on
+, is_current AS current
 , created_at
 FROM occupations
 WHERE member_id = $1
-ORDER BY created_at DESC
+ORDER BY is_current DESC
+    , COALESCE(ended_on, started_on) DESC NULLS LAST
+    , created_at DESC
+",
+    )
+    .bind(id)
+}
+
+pub fn create_occupation(id: Id<Member>, occupation: &NewOccupation) -> QueryAs<'_, Occupation> {
+    sqlx::query_as(
+        "
+INSERT INTO occupations
+    ( member_id
+    , company_name
+    , company_id
+    , position
+    , started_on
+    , ended_on
+    , is_current
+    )
+VALUES ($1, $2, $3, $4, $5, $6, $7)
+RETURNING id
+    , company_name
+    , company_id
+    , position
+    , started_on
+    , ended_on
+    , is_current AS current
+    , created_at
+",
+    )
+    .bind(id)
+    .bind(&occupation.company_name)
+    .bind(occupation.company_id)
+    .bind(&occupation.position)
+    .bind(occupation.started_on)
+    .bind(occupation.ended_on)
+    .bind(occupation.is_current())
+}
+
+pub fn update_occupation(
+    member_id: Id<Member>,
+    id: Id<Occupation>,
+    occupation: &UpdateOccupation,
+) -> QueryAs<'_, Occupation> {
+    sqlx::query_as(
+        "
+UPDATE occupations
+SET company_name = CASE WHEN $9 THEN $3 ELSE company_name END
+    , company_id = CASE WHEN $10 THEN $4 ELSE company_id END
+    , position = CASE WHEN $11 THEN $5 ELSE position END
+    , started_on = CASE WHEN $12 THEN $6 ELSE started_on END
+    , ended_on = CASE WHEN $13 THEN $7 ELSE ended_on END
+    , is_current = COALESCE($8, CASE WHEN $13 THEN $7::DATE IS NULL ELSE is_current END)
+WHERE id = $2
+    AND member_id = $1
+RETURNING id
+    , company_name
+    , company_id
+    , position
+    , started_on
+    , ended_on
+    , is_current AS current
+    , created_at
+",
+    )
+    .bind(member_id)
+    .bind(id)
+    .bind(occupation.company_name.value())
+    .bind(occupation.company_id.value())
+    .bind(occupation.position.value())
+    .bind(occupation.started_on.value())
+    .bind(occupation.ended_on.value())
+    .bind(occupation.current)
+    .bind(occupation.company_name.is_change())
+    .bind(occupation.company_id.is_change())
+    .bind(occupation.position.is_change())
+    .bind(occupation.started_on.is_change())
+    .bind(occupation.ended_on.is_change())
+}
+
+pub fn delete_occupation<'a>(member_id: Id<Member>, id: Id<Occupation>) -> Query<'a> {
+    sqlx::query(
+        "
+DELETE FROM occupations
+WHERE id = $2
+    AND member_id = $1
 ",
     )
+    .bind(member_id)
     .bind(id)
 }
 
@@ -293,6 +497,9 @@ RETURNING members.id
 , created_at
 , (SELECT workplace_id FROM members_workplaces WHERE member_id = members.id) as workplace_id
 , sub
+, suspended_at
+, suspension_reason
+, (SELECT lr.status FROM listmonk_subscription_requests lr WHERE lr.member_id = members.id) AS listmonk_subscription
 ",
     )
     .bind(id)
@@ -302,7 +509,7 @@ RETURNING members.id
 pub fn get_status_data<'a>(id: Id<Member>) -> QueryAs<'a, MemberStatusData> {
     sqlx::query_as(
         "
-SELECT sub, left_at, onboarding_finished_at
+SELECT sub, email, left_at, onboarding_finished_at, suspended_at
 FROM members
 WHERE id = $1
 ",
@@ -334,6 +541,9 @@ RETURNING members.id
 , created_at
 , (SELECT workplace_id FROM members_workplaces WHERE member_id = members.id) as workplace_id
 , sub
+, suspended_at
+, suspension_reason
+, (SELECT lr.status FROM listmonk_subscription_requests lr WHERE lr.member_id = members.id) AS listmonk_subscription
 ",
     )
     .bind(id)
@@ -363,6 +573,9 @@ pub fn update_member_note(id: Id<Member>, new_note: &Note) -> QueryAs<'_, Detail
     , created_at
     , (SELECT workplace_id FROM members_workplaces WHERE member_id = members.id) as workplace_id
     , sub
+    , suspended_at
+    , suspension_reason
+    , (SELECT lr.status FROM listmonk_subscription_requests lr WHERE lr.member_id = members.id) AS listmonk_subscription
 ",
     )
     .bind(id)
@@ -376,13 +589,13 @@ UPDATE members
 SET first_name = $2
     , last_name = $3
     , date_of_birth = $4
-    , email = $5
-    , phone_number = $6
-    , note = $7
-    , address = $8
-    , city = $9
-    , postal_code = $10
-    , language = $11
+    , phone_number = $5
+    , note = $6
+    , address = $7
+    , city = $8
+    , postal_code = $9
+    , language = $10
+    , email = COALESCE($11, email)
 WHERE members.id = $1
 RETURNING members.id
 , member_number
@@ -402,19 +615,22 @@ RETURNING members.id
 , created_at
 , (SELECT workplace_id FROM members_workplaces WHERE member_id = members.id) as workplace_id
 , sub
+, suspended_at
+, suspension_reason
+, (SELECT lr.status FROM listmonk_subscription_requests lr WHERE lr.member_id = members.id) AS listmonk_subscription
 ",
     )
     .bind(id)
     .bind(updated_member.first_name)
     .bind(updated_member.last_name)
     .bind(updated_member.date_of_birth)
-    .bind(updated_member.email)
     .bind(updated_member.phone_number)
     .bind(updated_member.note)
     .bind(updated_member.address)
     .bind(updated_member.city)
     .bind(updated_member.postal_code)
     .bind(updated_member.language)
+    .bind(updated_member.email)
 }
 
 // This doesn't realy delete member from the database
@@ -425,6 +641,187 @@ pub fn remove_member<'a>(id: Id<Member>) -> QueryAs<'a, Detail> {
 UPDATE members
 SET left_at = NOW()
   , sub = NULL
+  , suspended_at = NULL
+  , suspension_reason = NULL
+WHERE members.id = $1
+RETURNING members.id
+, member_number
+, first_name
+, last_name
+, date_of_birth
+, email
+, phone_number
+, note
+, address
+, city
+, postal_code
+, language
+, registration_request_id as application_id
+, left_at
+, onboarding_finished_at
+, created_at
+, (SELECT workplace_id FROM members_workplaces WHERE member_id = members.id) as workplace_id
+, sub
+, suspended_at
+, suspension_reason
+, (SELECT lr.status FROM listmonk_subscription_requests lr WHERE lr.member_id = members.id) AS listmonk_subscription
+",
+    )
+    .bind(id)
+}
+
+/// Ends ongoing membership period at the same time as `remove_member`
+/// (`NOW()` is the start of transaction)
+pub fn close_membership_period<'a>(id: Id<Member>, reason: LeftReason) -> Query<'a> {
+    sqlx::query(
+        "
+UPDATE membership_periods
+SET left_at = NOW()
+    , left_reason = $2
+WHERE member_id = $1
+    AND left_at IS NULL
+",
+    )
+    .bind(id)
+    .bind(reason.as_str())
+}
+
+/// Reactivates past member keeping the member number
+pub fn rejoin_member<'a>(id: Id<Member>) -> QueryAs<'a, Detail> {
+    sqlx::query_as(
+        "
+UPDATE members
+SET left_at = NULL
+WHERE members.id = $1
+RETURNING members.id
+, member_number
+, first_name
+, last_name
+, date_of_birth
+, email
+, phone_number
+, note
+, address
+, city
+, postal_code
+, language
+, registration_request_id as application_id
+, left_at
+, onboarding_finished_at
+, created_at
+, (SELECT workplace_id FROM members_workplaces WHERE member_id = members.id) as workplace_id
+, sub
+, suspended_at
+, suspension_reason
+, (SELECT lr.status FROM listmonk_subscription_requests lr WHERE lr.member_id = members.id) AS listmonk_subscription
+",
+    )
+    .bind(id)
+}
+
+pub fn open_membership_period<'a>(id: Id<Member>) -> Query<'a> {
+    sqlx::query(
+        "
+INSERT INTO membership_periods (member_id)
+VALUES ($1)
+",
+    )
+    .bind(id)
+}
+
+pub fn list_membership_periods<'a>(id: Id<Member>) -> QueryAs<'a, MembershipPeriod> {
+    sqlx::query_as(
+        "
+SELECT id
+, joined_at
+, left_at
+, left_reason
+, note
+FROM membership_periods
+WHERE member_id = $1
+ORDER BY joined_at DESC
+",
+    )
+    .bind(id)
+}
+
+pub fn suspend_member(id: Id<Member>, suspension: &Suspension) -> QueryAs<'_, Detail> {
+    sqlx::query_as(
+        "
+UPDATE members
+SET suspended_at = COALESCE(suspended_at, NOW())
+    , suspension_reason = $2
+WHERE members.id = $1
+RETURNING members.id
+, member_number
+, first_name
+, last_name
+, date_of_birth
+, email
+, phone_number
+, note
+, address
+, city
+, postal_code
+, language
+, registration_request_id as application_id
+, left_at
+, onboarding_finished_at
+, created_at
+, (SELECT workplace_id FROM members_workplaces WHERE member_id = members.id) as workplace_id
+, sub
+, suspended_at
+, suspension_reason
+, (SELECT lr.status FROM listmonk_subscription_requests lr WHERE lr.member_id = members.id) AS listmonk_subscription
+",
+    )
+    .bind(id)
+    .bind(suspension.reason.as_deref())
+}
+
+pub fn lift_suspension<'a>(id: Id<Member>) -> QueryAs<'a, Detail> {
+    sqlx::query_as(
+        "
+UPDATE members
+SET suspended_at = NULL
+    , suspension_reason = NULL
+WHERE members.id = $1
+RETURNING members.id
+, member_number
+, first_name
+, last_name
+, date_of_birth
+, email
+, phone_number
+, note
+, address
+, city
+, postal_code
+, language
+, registration_request_id as application_id
+, left_at
+, onboarding_finished_at
+, created_at
+, (SELECT workplace_id FROM members_workplaces WHERE member_id = members.id) as workplace_id
+, sub
+, suspended_at
+, suspension_reason
+, (SELECT lr.status FROM listmonk_subscription_requests lr WHERE lr.member_id = members.id) AS listmonk_subscription
+",
+    )
+    .bind(id)
+}
+
+/// Contact details updated by member themselves
+pub fn update_contact(id: Id<Member>, contact: &ContactUpdate) -> QueryAs<'_, Detail> {
+    sqlx::query_as(
+        "
+UPDATE members
+SET phone_number = CASE WHEN $7 THEN $2 ELSE phone_number END
+    , address = CASE WHEN $8 THEN $3 ELSE address END
+    , city = CASE WHEN $9 THEN $4 ELSE city END
+    , postal_code = CASE WHEN $10 THEN $5 ELSE postal_code END
+    , language = COALESCE($6, language)
 WHERE members.id = $1
 RETURNING members.id
 , member_number
@@ -444,7 +841,279 @@ RETURNING members.id
 , created_at
 , (SELECT workplace_id FROM members_workplaces WHERE member_id = members.id) as workplace_id
 , sub
+, suspended_at
+, suspension_reason
+, (SELECT lr.status FROM listmonk_subscription_requests lr WHERE lr.member_id = members.id) AS listmonk_subscription
+",
+    )
+    .bind(id)
+    .bind(contact.phone_number.value())
+    .bind(contact.address.value())
+    .bind(contact.city.value())
+    .bind(contact.postal_code.value())
+    .bind(&contact.language)
+    .bind(contact.phone_number.is_change())
+    .bind(contact.address.is_change())
+    .bind(contact.city.is_change())
+    .bind(contact.postal_code.is_change())
+}
+
+pub fn create_profile_change_request(
+    member_id: Id<Member>,
+    change: &NewProfileChange,
+) -> QueryAs<'_, ProfileChangeRequest> {
+    sqlx::query_as(
+        "
+INSERT INTO profile_change_requests
+    ( member_id
+    , first_name
+    , last_name
+    , date_of_birth
+    )
+VALUES ($1, $2, $3, $4)
+RETURNING id
+, member_id
+, first_name
+, last_name
+, date_of_birth
+, status
+, created_at
+, resolved_at
+",
+    )
+    .bind(member_id)
+    .bind(&change.first_name)
+    .bind(&change.last_name)
+    .bind(change.date_of_birth)
+}
+
+pub fn list_member_profile_change_requests<'a>(
+    member_id: Id<Member>,
+) -> QueryAs<'a, ProfileChangeRequest> {
+    sqlx::query_as(
+        "
+SELECT id
+, member_id
+, first_name
+, last_name
+, date_of_birth
+, status
+, created_at
+, resolved_at
+FROM profile_change_requests
+WHERE member_id = $1
+ORDER BY created_at DESC
+",
+    )
+    .bind(member_id)
+}
+
+pub fn list_profile_change_requests<'a>(
+    status: ProfileChangeStatus,
+) -> QueryAs<'a, ProfileChangeRequest> {
+    sqlx::query_as(
+        "
+SELECT id
+, member_id
+, first_name
+, last_name
+, date_of_birth
+, status
+, created_at
+, resolved_at
+FROM profile_change_requests
+WHERE status = $1
+ORDER BY created_at
+",
+    )
+    .bind(status.as_str())
+}
+
+/// Returns nothing when request is not pending
+pub fn resolve_profile_change_request<'a>(
+    id: Id<data::ProfileChangeRequest>,
+    status: ProfileChangeStatus,
+) -> QueryAs<'a, ProfileChangeRequest> {
+    sqlx::query_as(
+        "
+UPDATE profile_change_requests
+SET status = $2
+    , resolved_at = NOW()
+WHERE id = $1
+    AND status = 'pending'
+RETURNING id
+, member_id
+, first_name
+, last_name
+, date_of_birth
+, status
+, created_at
+, resolved_at
+",
+    )
+    .bind(id)
+    .bind(status.as_str())
+}
+
+pub fn apply_profile_change_request<'a>(id: Id<data::ProfileChangeRequest>) -> QueryAs<'a, Detail> {
+    sqlx::query_as(
+        "
+UPDATE members AS m
+SET first_name = COALESCE(r.first_name, m.first_name)
+    , last_name = COALESCE(r.last_name, m.last_name)
+    , date_of_birth = COALESCE(r.date_of_birth, m.date_of_birth)
+FROM profile_change_requests AS r
+WHERE r.id = $1
+    AND m.id = r.member_id
+RETURNING m.id
+, m.member_number
+, m.first_name
+, m.last_name
+, m.date_of_birth
+, m.email
+, m.phone_number
+, m.note
+, m.address
+, m.city
+, m.postal_code
+, m.language
+, m.registration_request_id as application_id
+, m.left_at
+, m.onboarding_finished_at
+, m.created_at
+, (SELECT workplace_id FROM members_workplaces WHERE member_id = m.id) as workplace_id
+, m.sub
+, m.suspended_at
+, m.suspension_reason
+, (SELECT lr.status FROM listmonk_subscription_requests lr WHERE lr.member_id = m.id) AS listmonk_subscription
+",
+    )
+    .bind(id)
+}
+
+/// Email is used by current member other than the one with given id, case insensitively
+pub fn is_email_used(email: &str, member_id: Id<Member>) -> QueryAs<'_, (bool,)> {
+    sqlx::query_as(
+        "
+SELECT EXISTS (
+    SELECT 1 FROM members
+    WHERE lower(email) = lower($1)
+    AND left_at IS NULL
+    AND id <> $2
+)
+",
+    )
+    .bind(email)
+    .bind(member_id)
+}
+
+/// Only the latest requested change can be confirmed
+pub fn cancel_pending_email_changes<'a>(member_id: Id<Member>) -> Query<'a> {
+    sqlx::query(
+        "
+UPDATE email_changes
+SET cancelled_at = NOW()
+WHERE member_id = $1
+    AND confirmed_at IS NULL
+    AND cancelled_at IS NULL
+",
+    )
+    .bind(member_id)
+}
+
+pub fn create_email_change(
+    member_id: Id<Member>,
+    new_email: &str,
+    confirmation_token: String,
+) -> QueryAs<'_, EmailChange> {
+    sqlx::query_as(
+        "
+INSERT INTO email_changes
+    ( member_id
+    , old_email
+    , new_email
+    , confirmation_token
+    )
+SELECT id, email, $2, $3
+FROM members
+WHERE id = $1
+RETURNING id
+, member_id
+, old_email
+, new_email
+, created_at
+, confirmed_at
+, cancelled_at
+",
+    )
+    .bind(member_id)
+    .bind(new_email)
+    .bind(confirmation_token)
+}
+
+pub fn list_email_changes<'a>(member_id: Id<Member>) -> QueryAs<'a, EmailChange> {
+    sqlx::query_as(
+        "
+SELECT id
+, member_id
+, old_email
+, new_email
+, created_at
+, confirmed_at
+, cancelled_at
+FROM email_changes
+WHERE member_id = $1
+ORDER BY created_at DESC
+",
+    )
+    .bind(member_id)
+}
+
+/// Change waiting for confirmation of current member, see `PendingEmailChange::is_expired`.
+/// Locks the change so that it can't be confirmed twice at the same time
+pub fn get_pending_email_change(confirmation_token: &str) -> QueryAs<'_, PendingEmailChange> {
+    sqlx::query_as(
+        "
+SELECT ec.id
+    , ec.member_id
+    , m.email AS old_email
+    , ec.new_email
+    , ec.created_at
+    , m.sub
+    , m.language
+FROM email_changes AS ec
+INNER JOIN members AS m ON m.id = ec.member_id
+WHERE ec.confirmation_token = $1
+    AND ec.confirmed_at IS NULL
+    AND ec.cancelled_at IS NULL
+    AND m.left_at IS NULL
+FOR UPDATE OF ec
+",
+    )
+    .bind(confirmation_token)
+}
+
+pub fn confirm_email_change<'a>(id: Id<data::EmailChange>) -> Query<'a> {
+    sqlx::query(
+        "
+UPDATE email_changes
+SET confirmed_at = NOW()
+WHERE id = $1
+    AND confirmed_at IS NULL
+    AND cancelled_at IS NULL
+",
+    )
+    .bind(id)
+}
+
+pub fn set_member_email(id: Id<Member>, email: &str) -> Query<'_> {
+    sqlx::query(
+        "
+UPDATE members
+SET email = $2
+WHERE id = $1
 ",
     )
     .bind(id)
+    .bind(email)
 }
@@ -1,20 +1,24 @@
 use log::{error, warn};
 use rocket::response::{self, Responder};
-use rocket::serde::{Serialize, json::Json};
+use rocket::serde::{Deserialize, Deserializer, Serialize, Serializer, json::Json};
 use rocket::{Build, Request, Rocket, State, catchers, get, routes};
 use thiserror::Error;
 use tokio::task::JoinError;
 use validator::ValidationError;
 
 pub(crate) mod applications;
+mod companies;
+mod documents;
+pub(crate) mod dues;
 mod errors;
+mod exports;
 mod files;
 mod members;
 mod oidc;
 mod registration;
 mod session;
 mod stats;
-mod workplaces;
+pub(crate) mod workplaces;
 
 use crate::api::errors::validation_error;
 use crate::db::{self, DbPool};
@@ -72,8 +76,8 @@ impl<'r> Responder<'r, 'static> for SenderError {
 impl<'r> Responder<'r, 'static> for oid::Error {
     fn respond_to(self, _request: &'r Request<'_>) -> response::Result<'static> {
         use oid::Error::{
-            BadKey, BadToken, Disabled, Http, MissingOneOfRoles, MissingRealmRole, MissingRole,
-            Parsing, Proxy,
+            BadKey, BadToken, Disabled, Http, MissingClientSecret, MissingOneOfRoles,
+            MissingRealmRole, MissingRole, Parsing, Proxy,
         };
         use rocket::http::Status;
 
@@ -81,7 +85,7 @@ impl<'r> Responder<'r, 'static> for oid::Error {
 
         match self {
             Disabled => Err(Status::NotFound),
-            BadKey(_) | Parsing(_) => Err(Status::InternalServerError),
+            BadKey(_) | Parsing(_) | MissingClientSecret => Err(Status::InternalServerError),
             MissingRole(_) | MissingRealmRole(_) | MissingOneOfRoles(_) => Err(Status::Forbidden),
             BadToken(_) => Err(Status::Unauthorized),
             Http(_) => Err(Status::BadGateway),
@@ -148,6 +152,26 @@ impl ApiError {
         Self::Custom(custom_error)
     }
 
+    pub fn bad_request(description: &str) -> ApiError {
+        use rocket::http::Status;
+        use rocket::serde::json::json;
+
+        let custom_error = CustomError {
+            status: Status::BadRequest,
+            json: json!(
+            {
+                "error": {
+                    "code": 400,
+                    "reason": "Bad Request",
+                    "description": description
+                }
+            }
+            ),
+        };
+
+        Self::Custom(custom_error)
+    }
+
     pub fn invalid_group_id() -> ApiError {
         use rocket::http::Status;
         use rocket::serde::json::json;
@@ -373,11 +397,20 @@ pub fn build() -> Rocket<Build> {
         .mount("/stats", stats::routes())
         .register("/stats", errors::catchers())
         .mount("/members", members::routes())
+        .mount("/members", dues::member_routes())
+        .mount("/members", workplaces::member_routes())
+        .mount("/members", documents::member_routes())
         .register("/members", errors::catchers())
+        .mount("/dues", dues::routes())
+        .register("/dues", errors::catchers())
         .mount("/oidc", oidc::routes())
         .register("/oidc", errors::catchers())
+        .mount("/companies", companies::routes())
+        .register("/companies", errors::catchers())
         .mount("/workplaces", workplaces::routes())
         .register("/workplaces", errors::catchers())
+        .mount("/exports", exports::routes())
+        .register("/exports", errors::catchers())
         // Files use default catchers
         .mount("/files", files::routes())
 }
@@ -423,6 +456,50 @@ impl<'r> Responder<'r, 'static> for SuccessResponse {
     }
 }
 
+/// Field of partial update which can be left out, set or cleared with explicit `null`.
+/// Needs `#[serde(default)]` so that missing field keeps the stored value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Patch<T> {
+    #[default]
+    Keep,
+    Clear,
+    Set(T),
+}
+
+impl<T> Patch<T> {
+    /// Stored value should be replaced (possibly with `NULL`)
+    pub fn is_change(&self) -> bool {
+        !matches!(self, Self::Keep)
+    }
+
+    /// New value, `None` when cleared or kept
+    pub fn value(&self) -> Option<&T> {
+        match self {
+            Self::Set(value) => Some(value),
+            Self::Keep | Self::Clear => None,
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Patch<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Option::<T>::deserialize(deserializer)?.map_or(Self::Clear, Self::Set))
+    }
+}
+
+/// Serialized as the new value, needed to report validation errors
+impl<T: Serialize> Serialize for Patch<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.value().serialize(serializer)
+    }
+}
+
 pub fn validate_non_empty(val: &str) -> Result<(), ValidationError> {
     if val.trim().is_empty() {
         return Err(ValidationError::new("empty"));
@@ -430,3 +507,26 @@ pub fn validate_non_empty(val: &str) -> Result<(), ValidationError> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    #[serde(crate = "rocket::serde")]
+    struct Update {
+        #[serde(default)]
+        city: Patch<String>,
+    }
+
+    fn city(json: &str) -> Patch<String> {
+        rocket::serde::json::from_str::<Update>(json).unwrap().city
+    }
+
+    #[test]
+    fn patch_tells_missing_field_from_null() {
+        assert_eq!(city("{}"), Patch::Keep);
+        assert_eq!(city(r#"{"city": null}"#), Patch::Clear);
+        assert_eq!(city(r#"{"city": "Brno"}"#), Patch::Set("Brno".to_string()));
+    }
+}
@@ -2,14 +2,15 @@ use chrono::NaiveDate;
 use log::{info, warn};
 use rocket::http::Status;
 use rocket::response::Redirect;
-use rocket::serde::{Deserialize, json::Json};
-use rocket::{Route, State, get, post, routes};
+use rocket::serde::{Deserialize, Serialize, json::Json};
+use rocket::{Route, State, get, patch, post, routes};
 use validator::Validate;
 
 mod query;
-use super::{Response, SuccessResponse, validate_non_empty};
+use super::applications::{ApplicationStatus, ApplicationStatusData};
+use super::{Patch, Response, SuccessResponse, validate_non_empty};
 use crate::config::Config;
-use crate::data::{self, Id};
+use crate::data::{self, Id, MemberNumber};
 use crate::db::{self, DbPool};
 use crate::generate;
 use crate::media::RawBase64;
@@ -67,16 +68,21 @@ async fn api_join(
     // First lets create a request record so we don't loose
     // any people even if rest of the stuff goes wrong for one reason or another
     let reg_id: Id<data::RegistrationRequest>;
-    let mut confirmation_token;
     loop {
-        confirmation_token = generate::string(64);
-        let res =
-            query::create_join_request(ip_addr, user_agent, confirmation_token.clone(), &user)
-                .fetch_one(db_pool.inner())
-                .await;
+        let confirmation_token = generate::string(64);
+        let status_token = generate::string(64);
+        let res = query::create_join_request(
+            ip_addr,
+            user_agent,
+            confirmation_token,
+            status_token,
+            &user,
+        )
+        .fetch_one(db_pool.inner())
+        .await;
 
         if db::fail_duplicated(&res) {
-            // We won the loterry and generated confirmation token which already exists...
+            // We won the loterry and generated token which already exists...
             // lets just try a new one
             continue;
         }
@@ -156,7 +162,142 @@ async fn api_confirm(
     }
 }
 
+/// Data applicant filled in the form which they are allowed to see
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ApplicantData {
+    email: Option<String>,
+    first_name: Option<String>,
+    last_name: Option<String>,
+    date_of_birth: Option<NaiveDate>,
+    address: Option<String>,
+    city: Option<String>,
+    postal_code: Option<String>,
+    phone_number: Option<String>,
+    company_name: Option<String>,
+    occupation: Option<String>,
+    registration_local: String,
+    member_number: Option<MemberNumber>,
+    /// Question of reviewer waiting for the answer
+    pending_question: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApplicantStatus {
+    status: ApplicationStatus,
+    #[serde(flatten)]
+    data: ApplicantData,
+}
+
+#[get("/<token>/status")]
+/// Public status of application authenticated by status token
+/// which applicant receives within the confirmation email
+async fn api_status(db_pool: &State<DbPool>, token: &'_ str) -> Response<Json<ApplicantStatus>> {
+    let status = query::get_status_data_by_token(token)
+        .fetch_one(db_pool.inner())
+        .await?
+        .to_status();
+    let data = query::get_applicant_data(token)
+        .fetch_one(db_pool.inner())
+        .await?;
+
+    Ok(Json(ApplicantStatus { status, data }))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[serde(crate = "rocket::serde")]
+/// Corrections applicant can make to their own application.
+/// Email is not part of it on purpose since changing it
+/// would require another round of verification.
+/// Missing fields are left untouched, optional fields can be cleared with `null`.
+pub struct ApplicantCorrection<'r> {
+    #[validate(custom(function = "validate_non_empty"))]
+    first_name: Option<&'r str>,
+    #[validate(custom(function = "validate_non_empty"))]
+    last_name: Option<&'r str>,
+    date_of_birth: Option<NaiveDate>,
+    #[serde(default)]
+    address: Patch<String>,
+    #[validate(custom(function = "validate_non_empty"))]
+    city: Option<&'r str>,
+    #[serde(default)]
+    postal_code: Patch<String>,
+    #[validate(custom(function = "validate_non_empty"))]
+    phone_number: Option<&'r str>,
+    #[validate(custom(function = "validate_non_empty"))]
+    company_name: Option<&'r str>,
+    #[validate(custom(function = "validate_non_empty"))]
+    occupation: Option<&'r str>,
+}
+
+#[patch("/<token>", format = "json", data = "<correction>")]
+/// Allow applicant to fix typos in their data before the application is resolved
+async fn api_correct(
+    db_pool: &State<DbPool>,
+    token: &'_ str,
+    correction: Validated<Json<ApplicantCorrection<'_>>>,
+) -> Response<Json<ApplicantStatus>> {
+    let correction = correction.into_inner();
+
+    let mut tx = db_pool.inner().begin().await?;
+
+    let status = query::get_status_data_by_token(token)
+        .fetch_one(&mut *tx)
+        .await?
+        .to_status();
+    status.assert_waiting_or_in_processing_or()?;
+
+    let data = query::correct_applicant_data(token, &correction)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    info!("Applicant corrected data of their application.");
+    Ok(Json(ApplicantStatus { status, data }))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[serde(crate = "rocket::serde")]
+pub struct ApplicantAnswer<'r> {
+    #[validate(required)]
+    #[validate(custom(function = "validate_non_empty"))]
+    answer: Option<&'r str>,
+}
+
+#[post("/<token>/answer", format = "json", data = "<answer>")]
+/// Answer question of reviewer which returns application back to processing
+async fn api_answer(
+    db_pool: &State<DbPool>,
+    token: &'_ str,
+    answer: Validated<Json<ApplicantAnswer<'_>>>,
+) -> Response<Json<ApplicantStatus>> {
+    let answer = answer.into_inner();
+
+    let mut tx = db_pool.inner().begin().await?;
+
+    query::get_status_data_by_token(token)
+        .fetch_one(&mut *tx)
+        .await?
+        .to_status()
+        .assert_needs_info()?;
+
+    query::answer_info_requests(token, &answer)
+        .execute(&mut *tx)
+        .await?;
+
+    let status = query::get_status_data_by_token(token)
+        .fetch_one(&mut *tx)
+        .await?
+        .to_status();
+    let data = query::get_applicant_data(token).fetch_one(&mut *tx).await?;
+
+    tx.commit().await?;
+
+    info!("Applicant answered question about their application.");
+    Ok(Json(ApplicantStatus { status, data }))
+}
+
 #[expect(clippy::redundant_type_annotations, reason = "rocket macro expansion")]
 pub fn routes() -> Vec<Route> {
-    routes![api_join, api_confirm]
+    routes![api_join, api_confirm, api_status, api_correct, api_answer]
 }
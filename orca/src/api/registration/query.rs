@@ -1,4 +1,6 @@
-use super::RegistrationRequest;
+use super::{
+    ApplicantAnswer, ApplicantCorrection, ApplicantData, ApplicationStatusData, RegistrationRequest,
+};
 use crate::data::{self, Id};
 use crate::db::{Query, QueryAs};
 use crate::media::ImageData;
@@ -8,6 +10,7 @@ pub fn create_join_request<'r>(
     ip_addr: IpAddress,
     user_agent: UserAgent<'r>,
     confirmation_token: String,
+    status_token: String,
     user: &RegistrationRequest<'r>,
 ) -> QueryAs<'r, (Id<data::RegistrationRequest>,)> {
     sqlx::query_as(
@@ -28,7 +31,8 @@ INSERT INTO registration_requests
 , registration_user_agent
 , registration_source
 , confirmation_token
-) VALUES ( $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15 )
+, status_token
+) VALUES ( $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16 )
 RETURNING id
 ",
     )
@@ -47,6 +51,7 @@ RETURNING id
     .bind(user_agent)
     .bind("website_join_form")
     .bind(confirmation_token)
+    .bind(status_token)
 }
 
 pub fn confirm_email(code: &'_ str) -> QueryAs<'_, (Id<data::RegistrationRequest>, String)> {
@@ -88,3 +93,128 @@ INSERT INTO registration_requests_files
     .bind(image.to_vec())
     .bind(reg_id)
 }
+
+/// Application is locked until the end of transaction
+/// so that it can't be resolved while applicant changes it
+pub fn get_status_data_by_token(token: &'_ str) -> QueryAs<'_, ApplicationStatusData> {
+    sqlx::query_as(
+        "
+SELECT rr.id
+, rr.created_at
+, rr.confirmed_at
+, rr.rejected_at
+, rr.invalidated_at
+, m.created_at AS accepted_at
+, m.id AS member_id
+, ( SELECT MAX(ri.created_at)
+    FROM registration_info_requests AS ri
+    WHERE ri.registration_request_id = rr.id
+    AND ri.answered_at IS NULL
+  ) AS info_requested_at
+FROM registration_requests AS rr
+LEFT JOIN members AS m ON rr.id = m.registration_request_id
+WHERE rr.status_token = $1
+FOR UPDATE OF rr
+",
+    )
+    .bind(token)
+}
+
+pub fn get_applicant_data(token: &'_ str) -> QueryAs<'_, ApplicantData> {
+    sqlx::query_as(
+        "
+SELECT rr.email
+, rr.first_name
+, rr.last_name
+, rr.date_of_birth
+, rr.address
+, rr.city
+, rr.postal_code
+, rr.phone_number
+, rr.company_name
+, rr.occupation
+, rr.registration_local
+, m.member_number
+, ( SELECT ri.question
+    FROM registration_info_requests AS ri
+    WHERE ri.registration_request_id = rr.id
+    AND ri.answered_at IS NULL
+    ORDER BY ri.created_at DESC
+    LIMIT 1
+  ) AS pending_question
+FROM registration_requests AS rr
+LEFT JOIN members AS m ON rr.id = m.registration_request_id
+WHERE rr.status_token = $1
+",
+    )
+    .bind(token)
+}
+
+/// Fields which are not part of correction are left untouched.
+/// Address and postal code are cleared when they are explicitly `null`.
+pub fn correct_applicant_data<'r>(
+    token: &'r str,
+    correction: &'r ApplicantCorrection<'r>,
+) -> QueryAs<'r, ApplicantData> {
+    sqlx::query_as(
+        "
+UPDATE registration_requests
+SET   first_name = COALESCE($2, first_name)
+    , last_name = COALESCE($3, last_name)
+    , date_of_birth = COALESCE($4, date_of_birth)
+    , address = CASE WHEN $11 THEN $5 ELSE address END
+    , city = COALESCE($6, city)
+    , postal_code = CASE WHEN $12 THEN $7 ELSE postal_code END
+    , phone_number = COALESCE($8, phone_number)
+    , company_name = COALESCE($9, company_name)
+    , occupation = COALESCE($10, occupation)
+WHERE status_token = $1
+RETURNING email
+, first_name
+, last_name
+, date_of_birth
+, address
+, city
+, postal_code
+, phone_number
+, company_name
+, occupation
+, registration_local
+, NULL::INT AS member_number
+, ( SELECT ri.question
+    FROM registration_info_requests AS ri
+    WHERE ri.registration_request_id = registration_requests.id
+    AND ri.answered_at IS NULL
+    ORDER BY ri.created_at DESC
+    LIMIT 1
+  ) AS pending_question
+",
+    )
+    .bind(token)
+    .bind(correction.first_name)
+    .bind(correction.last_name)
+    .bind(correction.date_of_birth)
+    .bind(correction.address.value())
+    .bind(correction.city)
+    .bind(correction.postal_code.value())
+    .bind(correction.phone_number)
+    .bind(correction.company_name)
+    .bind(correction.occupation)
+    .bind(correction.address.is_change())
+    .bind(correction.postal_code.is_change())
+}
+
+/// Answers all pending questions so the application returns back to processing
+pub fn answer_info_requests<'r>(token: &'r str, answer: &ApplicantAnswer<'r>) -> Query<'r> {
+    sqlx::query(
+        "
+UPDATE registration_info_requests
+SET   answer = $2
+    , answered_at = NOW()
+WHERE answered_at IS NULL
+    AND registration_request_id = (SELECT id FROM registration_requests WHERE status_token = $1)
+",
+    )
+    .bind(token)
+    .bind(answer.answer)
+}
@@ -1,10 +1,22 @@
+use rocket::http::Status;
 use rocket::serde::{Serialize, json::Json};
-use rocket::{Route, State, get, post, routes};
+use rocket::{Route, State, get, patch, post, routes};
 
+use super::documents::{self, Pdf};
+use super::files::{self, File, FileInfo};
+use super::members::{
+    self, ContactUpdate, EmailChange, NewEmailChange, NewProfileChange, Profile,
+    ProfileChangeRequest,
+};
+use super::workplaces::{self, MemberWorkplace};
 use super::{ApiError, Response};
+use crate::config::Config;
 use crate::data::{Id, Member};
 use crate::db::{DbPool, QueryAs};
+use crate::dues::status::{DuesStatus, member_status};
+use crate::processing::{Command, QueueSender};
 use crate::server::oid::{JwtClaims, JwtToken, Provider};
+use crate::validation::Validated;
 
 #[derive(Debug, Serialize)]
 struct SessionInfo {
@@ -65,6 +77,187 @@ async fn pair_by_email(
     }
 }
 
+/// Member paired with account of the current user
+async fn profile_member_id(
+    db_pool: &DbPool,
+    oid_provider: &Provider,
+    token: &JwtToken<'_>,
+) -> Result<Id<Member>, ApiError> {
+    let token_data = oid_provider.decode_jwt(token)?;
+
+    let (member_id,) = get_user_id(&token_data.claims)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or(Status::NotFound)?;
+
+    Ok(member_id)
+}
+
+#[get("/current/profile")]
+async fn profile(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+) -> Response<Json<Profile>> {
+    let member_id = profile_member_id(db_pool, oid_provider, &token).await?;
+
+    let detail = members::query::detail(member_id)
+        .fetch_one(db_pool.inner())
+        .await?;
+
+    Ok(Json(detail.into()))
+}
+
+/// Members can change their contact details.
+/// Other personal data can only be changed through change request.
+#[patch("/current/profile", format = "json", data = "<contact>")]
+async fn update_profile(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    queue: &State<QueueSender>,
+    token: JwtToken<'_>,
+    contact: Validated<Json<ContactUpdate>>,
+) -> Response<Json<Profile>> {
+    let member_id = profile_member_id(db_pool, oid_provider, &token).await?;
+
+    let contact = contact.into_inner().into_inner();
+    let detail = members::query::update_contact(member_id, &contact)
+        .fetch_one(db_pool.inner())
+        .await?;
+
+    queue
+        .inner()
+        .send(Command::SyncListmonkSubscriber(member_id))
+        .await?;
+
+    Ok(Json(detail.into()))
+}
+
+#[get("/current/profile/change-requests")]
+async fn list_profile_change_requests(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+) -> Response<Json<Vec<ProfileChangeRequest>>> {
+    let member_id = profile_member_id(db_pool, oid_provider, &token).await?;
+
+    let requests = members::query::list_member_profile_change_requests(member_id)
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    Ok(Json(requests))
+}
+
+/// Request change of name or date of birth which needs to be approved by the board
+#[post("/current/profile/change-requests", format = "json", data = "<change>")]
+async fn request_profile_change(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    change: Json<NewProfileChange>,
+) -> Response<Json<ProfileChangeRequest>> {
+    let member_id = profile_member_id(db_pool, oid_provider, &token).await?;
+
+    if change.is_empty() {
+        return Err(ApiError::bad_request("Nothing to change"));
+    }
+
+    let request = members::query::create_profile_change_request(member_id, &change)
+        .fetch_one(db_pool.inner())
+        .await?;
+
+    Ok(Json(request))
+}
+
+/// Change of email needs to be confirmed from the new address
+#[post("/current/profile/email-changes", format = "json", data = "<change>")]
+async fn request_email_change(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    queue: &State<QueueSender>,
+    token: JwtToken<'_>,
+    change: Validated<Json<NewEmailChange>>,
+) -> Response<Json<EmailChange>> {
+    let member_id = profile_member_id(db_pool, oid_provider, &token).await?;
+
+    let email_change =
+        members::request_email_change(db_pool, queue, member_id, &change.into_inner()).await?;
+
+    Ok(Json(email_change))
+}
+
+#[get("/current/profile/workplaces")]
+async fn profile_workplaces(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+) -> Response<Json<Vec<MemberWorkplace>>> {
+    let member_id = profile_member_id(db_pool, oid_provider, &token).await?;
+
+    let workplaces = workplaces::query::list_member_workplaces(member_id)
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    Ok(Json(workplaces))
+}
+
+#[get("/current/profile/dues")]
+async fn profile_dues(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+) -> Response<Json<DuesStatus>> {
+    let member_id = profile_member_id(db_pool, oid_provider, &token).await?;
+
+    Ok(Json(member_status(db_pool, member_id).await?))
+}
+
+/// Membership card, confirmation of membership or of dues paid in `year`
+#[get("/current/profile/documents/<kind>?<year>")]
+async fn profile_document(
+    config: &State<Config>,
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    kind: documents::Kind,
+    year: Option<i32>,
+) -> Response<Pdf> {
+    let member_id = profile_member_id(db_pool, oid_provider, &token).await?;
+
+    documents::print(config, db_pool, member_id, kind, year).await
+}
+
+#[get("/current/profile/files")]
+async fn profile_files(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+) -> Response<Json<Vec<FileInfo>>> {
+    let member_id = profile_member_id(db_pool, oid_provider, &token).await?;
+
+    let files = members::query::list_member_files(member_id)
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    Ok(Json(files))
+}
+
+#[get("/current/profile/files/<id>")]
+async fn profile_file(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<File>,
+) -> Response<File> {
+    let member_id = profile_member_id(db_pool, oid_provider, &token).await?;
+
+    let file = files::read_member_file(member_id, id)
+        .fetch_one(db_pool.inner())
+        .await?;
+
+    Ok(file)
+}
+
 fn get_user_id(claims: &JwtClaims) -> QueryAs<'_, (Id<Member>,)> {
     sqlx::query_as(
         "
@@ -92,5 +285,18 @@ RETURNING id
 }
 #[expect(clippy::redundant_type_annotations, reason = "rocket macro expansion")]
 pub fn routes() -> Vec<Route> {
-    routes![current, pair_by_email]
+    routes![
+        current,
+        pair_by_email,
+        profile,
+        update_profile,
+        list_profile_change_requests,
+        request_profile_change,
+        request_email_change,
+        profile_workplaces,
+        profile_dues,
+        profile_document,
+        profile_files,
+        profile_file,
+    ]
 }
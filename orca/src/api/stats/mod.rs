@@ -1,11 +1,25 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Datelike, Months, NaiveDate, Utc};
+use rocket::http::Status;
 use rocket::serde::json::Json;
-use rocket::{Route, State, get, routes, serde::Serialize};
+use rocket::{FromForm, FromFormField, Route, State, get, routes, serde::Serialize};
 
 mod query;
 
 use super::Response;
+use super::companies;
+use super::workplaces::{self, scope::WorkplaceScope};
+use crate::data::{Company, Id, Workplace};
 use crate::db::DbPool;
-use crate::server::oid::{JwtToken, Provider};
+use crate::server::oid::{JwtToken, Provider, Role};
+
+/// Length of monthly series unless requested otherwise
+const DEFAULT_MONTHS: u32 = 12;
+/// The longest monthly series
+const MAX_MONTHS: u32 = 120;
+/// The longest time series
+const MAX_PERIODS: u32 = 366;
 
 #[derive(Debug, Serialize)]
 struct ApplicationsBasicStats {
@@ -13,6 +27,7 @@ struct ApplicationsBasicStats {
     accepted: i64,
     rejected: i64,
     processing: i64,
+    needs_info: i64,
     invalid: i64,
 }
 
@@ -41,6 +56,10 @@ async fn applications_basic_stats(
         .fetch_one(db_pool.inner())
         .await?;
 
+    let (needs_info,) = query::count_needs_info_applications()
+        .fetch_one(db_pool.inner())
+        .await?;
+
     let (invalid,) = query::count_invalid_applications()
         .fetch_one(db_pool.inner())
         .await?;
@@ -50,14 +69,85 @@ async fn applications_basic_stats(
         accepted,
         rejected,
         processing,
+        needs_info,
         invalid,
     }))
 }
 
+/// Where applicants drop off and how long each step takes
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct ApplicationsFunnel {
+    submitted: i64,
+    /// Applicants confirmed their email
+    confirmed: i64,
+    accepted: i64,
+    /// Accepted members who finished onboarding
+    onboarded: i64,
+    /// Share of submitted applications which were confirmed
+    confirmation_rate: Option<f64>,
+    /// Median days from submission to email confirmation
+    days_to_confirmation: Option<f64>,
+    /// Median days from email confirmation to acceptance
+    days_to_acceptance: Option<f64>,
+    /// Median days from acceptance to finished onboarding
+    days_to_onboarding: Option<f64>,
+    /// Median days from submission to finished onboarding
+    days_total: Option<f64>,
+}
+
+/// Applications submitted during the last `months` months (12 by default)
+#[get("/applications/funnel?<months>")]
+async fn applications_funnel(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    months: Option<u32>,
+) -> Response<Json<ApplicationsFunnel>> {
+    // Every authenticated user is able to see stats
+    oid_provider.inner().decode_jwt(&token)?;
+
+    let since = window_start(Utc::now().date_naive(), months_param(months));
+    let funnel = query::application_funnel(since)
+        .fetch_one(db_pool.inner())
+        .await?;
+
+    Ok(Json(funnel))
+}
+
+/// Confirmed applications waiting for decision of board
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct ApplicationsBacklog {
+    applications: i64,
+    oldest_confirmed_at: Option<DateTime<Utc>>,
+    /// Median days since email confirmation
+    median_age_days: Option<f64>,
+    older_than_week: i64,
+    older_than_month: i64,
+}
+
+#[get("/applications/backlog")]
+async fn applications_backlog(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+) -> Response<Json<ApplicationsBacklog>> {
+    // Every authenticated user is able to see stats
+    oid_provider.inner().decode_jwt(&token)?;
+
+    let backlog = query::applications_backlog()
+        .fetch_one(db_pool.inner())
+        .await?;
+
+    Ok(Json(backlog))
+}
+
+/// Counts are based on membership periods, members who re-joined are current
 #[derive(Debug, Serialize)]
 struct MembersBasicStats {
     new: i64,
     current: i64,
+    /// Current members with suspended membership
+    suspended: i64,
     past: i64,
 }
 
@@ -78,13 +168,472 @@ async fn members_basic_stats(
         .fetch_one(db_pool.inner())
         .await?;
 
+    let (suspended,) = query::count_suspended_members()
+        .fetch_one(db_pool.inner())
+        .await?;
+
     let (past,) = query::count_past_members()
         .fetch_one(db_pool.inner())
         .await?;
 
-    Ok(Json(MembersBasicStats { new, current, past }))
+    Ok(Json(MembersBasicStats {
+        new,
+        current,
+        suspended,
+        past,
+    }))
+}
+
+#[derive(Debug, Serialize)]
+struct MembershipPeriodsStats {
+    year: i32,
+    joined: i64,
+    /// Members who joined again after leaving, included in `joined`
+    rejoined: i64,
+    /// Members who left by reason, `unknown` for members who left before reasons were recorded
+    left: HashMap<String, i64>,
+}
+
+/// Joins and leaves during `year` (current year by default)
+#[get("/members/periods?<year>")]
+async fn membership_periods_stats(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    year: Option<i32>,
+) -> Response<Json<MembershipPeriodsStats>> {
+    // Every authenticated user is able to see stats
+    oid_provider.inner().decode_jwt(&token)?;
+
+    let year = year.unwrap_or_else(|| Utc::now().year());
+
+    let (joined, rejoined) = query::count_joined_in_year(year)
+        .fetch_one(db_pool.inner())
+        .await?;
+
+    let left = query::count_left_in_year(year)
+        .fetch_all(db_pool.inner())
+        .await?
+        .into_iter()
+        .map(|(reason, count)| (reason.unwrap_or_else(|| "unknown".to_string()), count))
+        .collect();
+
+    Ok(Json(MembershipPeriodsStats {
+        year,
+        joined,
+        rejoined,
+        left,
+    }))
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct CompanyStats {
+    id: Id<Company>,
+    name: String,
+    /// Current members currently employed by the company
+    members: i64,
+    /// Applications waiting to be accepted or rejected
+    applications: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct CompaniesStats {
+    companies: Vec<CompanyStats>,
+    /// Current members whose current employer is not linked to any company
+    unmatched_members: i64,
+}
+
+#[get("/companies")]
+async fn companies_stats(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+) -> Response<Json<CompaniesStats>> {
+    // Employers of members are only visible to those who can list members
+    oid_provider.require_role(&token, Role::ListMembers)?;
+
+    let companies = query::count_by_company().fetch_all(db_pool.inner()).await?;
+
+    let (unmatched_members,) = query::count_unmatched_company_members()
+        .fetch_one(db_pool.inner())
+        .await?;
+
+    Ok(Json(CompaniesStats {
+        companies,
+        unmatched_members,
+    }))
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct WorkplaceStats {
+    id: Id<Workplace>,
+    name: String,
+    /// Current members assigned to workplace
+    members: i64,
+    /// The latest estimate of number of people working in workplace
+    headcount: Option<i32>,
+    headcount_estimated_on: Option<NaiveDate>,
+    /// Share of people working in workplace who are members
+    density: Option<f64>,
+}
+
+/// Representatives only see their own workplaces
+#[get("/workplaces")]
+async fn workplaces_stats(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+) -> Response<Json<Vec<WorkplaceStats>>> {
+    oid_provider.require_role(&token, Role::ListWorkplaces)?;
+    let scope = WorkplaceScope::of(db_pool, oid_provider, &token).await?;
+
+    let mut stats = query::list_workplace_stats()
+        .fetch_all(db_pool.inner())
+        .await?;
+    stats.retain(|stats| scope.includes(stats.id));
+
+    Ok(Json(stats))
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct MonthStats {
+    /// First day of month
+    month: NaiveDate,
+    /// Members at the end of month
+    members: i64,
+    joined: i64,
+    left: i64,
+    /// Estimate valid at the end of month, always missing for companies
+    headcount: Option<i32>,
+    density: Option<f64>,
+}
+
+fn months_param(months: Option<u32>) -> i32 {
+    i32::try_from(months.unwrap_or(DEFAULT_MONTHS).clamp(1, MAX_MONTHS)).unwrap_or(1)
+}
+
+/// First day of window of `months` months ending with the current one
+fn window_start(today: NaiveDate, months: i32) -> NaiveDate {
+    let first_day = today.with_day(1).unwrap_or(today);
+    u32::try_from(months - 1)
+        .ok()
+        .and_then(|months| first_day.checked_sub_months(Months::new(months)))
+        .unwrap_or(first_day)
+}
+
+/// Members, joins, leaves and density of workplace in each of the last `months` months
+#[get("/workplaces/<workplace_id>/monthly?<months>")]
+async fn workplace_monthly_stats(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    workplace_id: Id<Workplace>,
+    months: Option<u32>,
+) -> Response<Json<Vec<MonthStats>>> {
+    oid_provider.require_role(&token, Role::ListWorkplaces)?;
+    WorkplaceScope::of(db_pool, oid_provider, &token)
+        .await?
+        .require_workplace(workplace_id)?;
+
+    workplaces::query::detail(workplace_id)
+        .fetch_optional(db_pool.inner())
+        .await?
+        .ok_or(Status::NotFound)?;
+
+    let stats = query::workplace_monthly(workplace_id, months_param(months))
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    Ok(Json(stats))
+}
+
+/// Members, joins and leaves of company in each of the last `months` months
+#[get("/companies/<company_id>/monthly?<months>")]
+async fn company_monthly_stats(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    company_id: Id<Company>,
+    months: Option<u32>,
+) -> Response<Json<Vec<MonthStats>>> {
+    // Employers of members are only visible to those who can list members
+    oid_provider.require_role(&token, Role::ListMembers)?;
+
+    companies::query::detail(company_id)
+        .fetch_optional(db_pool.inner())
+        .await?
+        .ok_or(Status::NotFound)?;
+
+    let stats = query::company_monthly(company_id, months_param(months))
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    Ok(Json(stats))
+}
+
+/// Length of period time series are bucketed by
+#[derive(Debug, Clone, Copy, FromFormField)]
+enum Bucket {
+    Day,
+    Week,
+    Month,
+}
+
+impl Bucket {
+    /// Field of `date_trunc`
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Day => "day",
+            Self::Week => "week",
+            Self::Month => "month",
+        }
+    }
+
+    fn default_periods(self) -> u32 {
+        match self {
+            Self::Day => 30,
+            Self::Week => 26,
+            Self::Month => 12,
+        }
+    }
+}
+
+/// Time series are monthly by default and end with the current period
+#[derive(Debug, FromForm)]
+struct SeriesFilter<'r> {
+    bucket: Option<Bucket>,
+    /// Number of periods
+    periods: Option<u32>,
+    city: Option<&'r str>,
+    language: Option<&'r str>,
+    /// Where application was filled in
+    source: Option<&'r str>,
+    /// Current employer for members
+    company_id: Option<Id<Company>>,
+}
+
+impl SeriesFilter<'_> {
+    fn bucket(&self) -> Bucket {
+        self.bucket.unwrap_or(Bucket::Month)
+    }
+
+    fn periods(&self) -> i32 {
+        let periods = self
+            .periods
+            .unwrap_or_else(|| self.bucket().default_periods())
+            .clamp(1, MAX_PERIODS);
+        i32::try_from(periods).unwrap_or(1)
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct ApplicationTransitions {
+    /// Start of period
+    period: DateTime<Utc>,
+    submitted: i64,
+    /// Applicants confirmed their email
+    confirmed: i64,
+    accepted: i64,
+    rejected: i64,
+    invalidated: i64,
+}
+
+#[get("/series/applications?<filter..>")]
+async fn application_series(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    filter: SeriesFilter<'_>,
+) -> Response<Json<Vec<ApplicationTransitions>>> {
+    // Every authenticated user is able to see stats
+    oid_provider.inner().decode_jwt(&token)?;
+
+    let series = query::application_series(&filter)
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    Ok(Json(series))
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct Acceptances {
+    period: DateTime<Utc>,
+    /// Membership periods started
+    accepted: i64,
+    /// Members who joined again after leaving, included in `accepted`
+    rejoined: i64,
+}
+
+#[get("/series/acceptances?<filter..>")]
+async fn acceptance_series(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    filter: SeriesFilter<'_>,
+) -> Response<Json<Vec<Acceptances>>> {
+    // Every authenticated user is able to see stats
+    oid_provider.inner().decode_jwt(&token)?;
+
+    let series = query::acceptance_series(&filter)
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    Ok(Json(series))
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct Leavers {
+    period: DateTime<Utc>,
+    left: i64,
+    resigned: i64,
+    expelled: i64,
+    lapsed: i64,
+    deceased: i64,
+    /// Members who left before reasons were recorded
+    unknown: i64,
 }
+
+#[get("/series/leavers?<filter..>")]
+async fn leaver_series(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    filter: SeriesFilter<'_>,
+) -> Response<Json<Vec<Leavers>>> {
+    // Every authenticated user is able to see stats
+    oid_provider.inner().decode_jwt(&token)?;
+
+    let series = query::leaver_series(&filter)
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    Ok(Json(series))
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct MembershipChange {
+    period: DateTime<Utc>,
+    joined: i64,
+    left: i64,
+    /// `joined` minus `left`
+    net: i64,
+    /// Members at the end of period
+    members: i64,
+}
+
+#[get("/series/membership?<filter..>")]
+async fn membership_series(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    filter: SeriesFilter<'_>,
+) -> Response<Json<Vec<MembershipChange>>> {
+    // Every authenticated user is able to see stats
+    oid_provider.inner().decode_jwt(&token)?;
+
+    let series = query::membership_series(&filter)
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    Ok(Json(series))
+}
+
 #[expect(clippy::redundant_type_annotations, reason = "rocket macro expansion")]
 pub fn routes() -> Vec<Route> {
-    routes![applications_basic_stats, members_basic_stats]
+    routes![
+        applications_basic_stats,
+        applications_funnel,
+        applications_backlog,
+        members_basic_stats,
+        membership_periods_stats,
+        companies_stats,
+        workplaces_stats,
+        workplace_monthly_stats,
+        company_monthly_stats,
+        application_series,
+        acceptance_series,
+        leaver_series,
+        membership_series,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monthly_stats_cover_a_year_by_default() {
+        assert_eq!(months_param(None), 12);
+        assert_eq!(months_param(Some(3)), 3);
+    }
+
+    #[test]
+    fn monthly_stats_length_is_limited() {
+        assert_eq!(months_param(Some(0)), 1);
+        assert_eq!(months_param(Some(1000)), 120);
+        assert_eq!(months_param(Some(u32::MAX)), 120);
+    }
+
+    fn filter(query: &str) -> SeriesFilter<'_> {
+        rocket::form::Form::<SeriesFilter<'_>>::parse(query).unwrap()
+    }
+
+    #[test]
+    fn series_are_monthly_by_default() {
+        let filter = filter("");
+
+        assert_eq!(filter.bucket().as_str(), "month");
+        assert_eq!(filter.periods(), 12);
+    }
+
+    #[test]
+    fn series_length_depends_on_bucket() {
+        assert_eq!(filter("bucket=day").periods(), 30);
+        assert_eq!(filter("bucket=week").periods(), 26);
+        assert_eq!(filter("bucket=week&periods=4").periods(), 4);
+    }
+
+    #[test]
+    fn series_length_is_limited() {
+        assert_eq!(filter("bucket=day&periods=0").periods(), 1);
+        assert_eq!(filter("bucket=day&periods=5000").periods(), 366);
+    }
+
+    #[test]
+    fn series_filters_are_optional() {
+        let filter = filter("city=Brno&language=cs&source=web");
+
+        assert_eq!(filter.city, Some("Brno"));
+        assert_eq!(filter.language, Some("cs"));
+        assert_eq!(filter.source, Some("web"));
+        assert!(filter.company_id.is_none());
+    }
+
+    #[test]
+    fn funnel_window_starts_at_beginning_of_month() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 15).unwrap();
+
+        assert_eq!(
+            window_start(today, 1),
+            NaiveDate::from_ymd_opt(2025, 3, 1).unwrap()
+        );
+        assert_eq!(
+            window_start(today, 12),
+            NaiveDate::from_ymd_opt(2024, 4, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn funnel_window_crosses_years() {
+        let today = NaiveDate::from_ymd_opt(2025, 1, 31).unwrap();
+
+        assert_eq!(
+            window_start(today, 2),
+            NaiveDate::from_ymd_opt(2024, 12, 1).unwrap()
+        );
+        assert_eq!(
+            window_start(today, months_param(Some(1000))),
+            NaiveDate::from_ymd_opt(2015, 2, 1).unwrap()
+        );
+    }
 }
@@ -1,5 +1,51 @@
+use super::{
+    Acceptances, ApplicationTransitions, ApplicationsBacklog, ApplicationsFunnel, CompanyStats,
+    Leavers, MembershipChange, MonthStats, SeriesFilter, WorkplaceStats,
+};
+use chrono::NaiveDate;
+
+use crate::data::{Company, Id, Workplace};
 use crate::db::QueryAs;
 
+/// Periods of a series, `$1` is the bucket (`day`, `week`, `month`)
+/// and `$2` the number of periods ending with the current one
+macro_rules! periods_cte {
+    () => {
+        "periods AS (
+    SELECT period
+    FROM generate_series(
+        date_trunc($1, NOW()) - ($2::INTEGER - 1) * ('1 ' || $1)::INTERVAL
+        , date_trunc($1, NOW())
+        , ('1 ' || $1)::INTERVAL
+    ) AS period
+)"
+    };
+}
+
+/// Membership periods of members matching the series filter bound as `$3` to `$6`
+macro_rules! memberships_cte {
+    () => {
+        "memberships AS (
+    SELECT p.member_id
+        , p.joined_at
+        , p.left_at
+        , p.left_reason
+    FROM membership_periods p
+    INNER JOIN members m ON m.id = p.member_id
+    LEFT JOIN registration_requests rr ON rr.id = m.registration_request_id
+    WHERE ($3::TEXT IS NULL OR search_normalize(m.city) = search_normalize($3))
+    AND ($4::TEXT IS NULL OR m.language = $4)
+    AND ($5::TEXT IS NULL OR rr.registration_source = $5)
+    AND ($6::UUID IS NULL OR EXISTS (
+        SELECT 1 FROM occupations o
+        WHERE o.member_id = m.id
+        AND o.company_id = $6
+        AND o.is_current
+    ))
+)"
+    };
+}
+
 pub fn count_unverified_applications<'a>() -> QueryAs<'a, (i64,)> {
     sqlx::query_as(
         "
@@ -32,6 +78,14 @@ SELECT COUNT(*) FROM registration_requests_processing
     )
 }
 
+pub fn count_needs_info_applications<'a>() -> QueryAs<'a, (i64,)> {
+    sqlx::query_as(
+        "
+SELECT COUNT(*) FROM registration_requests_needs_info
+",
+    )
+}
+
 pub fn count_invalid_applications<'a>() -> QueryAs<'a, (i64,)> {
     sqlx::query_as(
         "
@@ -43,15 +97,36 @@ SELECT COUNT(*) FROM registration_requests_invalid
 pub fn count_new_members<'a>() -> QueryAs<'a, (i64,)> {
     sqlx::query_as(
         "
-SELECT COUNT(*) FROM members_new;
+SELECT COUNT(*)
+FROM membership_periods p
+INNER JOIN members m ON m.id = p.member_id
+WHERE p.left_at IS NULL
+    AND m.onboarding_finished_at IS NULL
 ",
     )
 }
 
+/// Includes suspended members
 pub fn count_current_members<'a>() -> QueryAs<'a, (i64,)> {
     sqlx::query_as(
         "
-SELECT COUNT(*) FROM members_current;
+SELECT COUNT(*)
+FROM membership_periods p
+INNER JOIN members m ON m.id = p.member_id
+WHERE p.left_at IS NULL
+    AND m.onboarding_finished_at IS NOT NULL
+",
+    )
+}
+
+pub fn count_suspended_members<'a>() -> QueryAs<'a, (i64,)> {
+    sqlx::query_as(
+        "
+SELECT COUNT(*)
+FROM membership_periods p
+INNER JOIN members m ON m.id = p.member_id
+WHERE p.left_at IS NULL
+    AND m.suspended_at IS NOT NULL
 ",
     )
 }
@@ -59,7 +134,418 @@ SELECT COUNT(*) FROM members_current;
 pub fn count_past_members<'a>() -> QueryAs<'a, (i64,)> {
     sqlx::query_as(
         "
-SELECT COUNT(*) FROM members_past;
+SELECT COUNT(*)
+FROM members m
+WHERE NOT EXISTS (
+    SELECT 1 FROM membership_periods p
+    WHERE p.member_id = m.id
+    AND p.left_at IS NULL
+)
+",
+    )
+}
+
+/// Number of membership periods started in `year`
+/// together with how many of them were re-joins
+pub fn count_joined_in_year<'a>(year: i32) -> QueryAs<'a, (i64, i64)> {
+    sqlx::query_as(
+        "
+SELECT COUNT(*)
+    , COUNT(*) FILTER (WHERE EXISTS (
+        SELECT 1 FROM membership_periods earlier
+        WHERE earlier.member_id = p.member_id
+        AND earlier.joined_at < p.joined_at
+    ))
+FROM membership_periods p
+WHERE EXTRACT(YEAR FROM p.joined_at) = $1
+",
+    )
+    .bind(year)
+}
+
+/// Number of membership periods ended in `year` by reason
+pub fn count_left_in_year<'a>(year: i32) -> QueryAs<'a, (Option<String>, i64)> {
+    sqlx::query_as(
+        "
+SELECT left_reason
+    , COUNT(*)
+FROM membership_periods
+WHERE EXTRACT(YEAR FROM left_at) = $1
+GROUP BY left_reason
+",
+    )
+    .bind(year)
+}
+
+/// Companies ordered from the one with the most current members
+pub fn count_by_company<'a>() -> QueryAs<'a, CompanyStats> {
+    sqlx::query_as(
+        "
+SELECT c.id
+    , c.name
+    , (SELECT COUNT(DISTINCT o.member_id)
+        FROM occupations o
+        INNER JOIN membership_periods p ON p.member_id = o.member_id AND p.left_at IS NULL
+        WHERE o.company_id = c.id
+        AND o.is_current
+    ) AS members
+    , (SELECT COUNT(*)
+        FROM registration_requests rr
+        INNER JOIN registration_requests_processing processing ON processing.id = rr.id
+        WHERE rr.company_id = c.id
+    ) AS applications
+FROM companies c
+ORDER BY members DESC
+    , c.name
+",
+    )
+}
+
+pub fn count_unmatched_company_members<'a>() -> QueryAs<'a, (i64,)> {
+    sqlx::query_as(
+        "
+SELECT COUNT(DISTINCT o.member_id)
+FROM occupations o
+INNER JOIN membership_periods p ON p.member_id = o.member_id AND p.left_at IS NULL
+WHERE o.company_id IS NULL
+AND o.is_current
+AND o.company_name IS NOT NULL
+",
+    )
+}
+
+/// Active workplaces with their current members and the latest headcount estimate
+pub fn list_workplace_stats<'a>() -> QueryAs<'a, WorkplaceStats> {
+    sqlx::query_as(
+        "
+SELECT stats.*
+    , stats.members::FLOAT8 / stats.headcount AS density
+FROM (
+    SELECT w.id
+        , w.name
+        , (SELECT COUNT(*)
+            FROM members_workplaces mw
+            INNER JOIN membership_periods p ON p.member_id = mw.member_id AND p.left_at IS NULL
+            WHERE mw.workplace_id = w.id
+        ) AS members
+        , h.headcount
+        , h.estimated_on AS headcount_estimated_on
+    FROM workplaces w
+    LEFT JOIN LATERAL (
+        SELECT headcount
+            , estimated_on
+        FROM workplace_headcounts
+        WHERE workplace_id = w.id
+        ORDER BY estimated_on DESC
+            , created_at DESC
+        LIMIT 1
+    ) h ON TRUE
+    WHERE w.archived_at IS NULL
+) stats
+ORDER BY density DESC NULLS LAST
+    , stats.members DESC
+    , stats.name
+",
+    )
+}
+
+/// Last `months` months including the current one.
+/// Assignment to workplace has no history so members currently assigned
+/// to workplace are counted for the whole time they were members.
+pub fn workplace_monthly<'a>(id: Id<Workplace>, months: i32) -> QueryAs<'a, MonthStats> {
+    sqlx::query_as(
+        "
+WITH months AS (
+    SELECT month::DATE AS start
+        , (month + INTERVAL '1 month')::DATE AS next
+    FROM generate_series(
+        date_trunc('month', CURRENT_DATE) - make_interval(months => $2 - 1)
+        , date_trunc('month', CURRENT_DATE)
+        , INTERVAL '1 month'
+    ) AS month
+), periods AS (
+    SELECT p.joined_at
+        , p.left_at
+    FROM membership_periods p
+    INNER JOIN members_workplaces mw ON mw.member_id = p.member_id
+    WHERE mw.workplace_id = $1
+), series AS (
+    SELECT m.start AS month
+        , (SELECT COUNT(*) FROM periods p
+            WHERE p.joined_at < m.next
+            AND (p.left_at IS NULL OR p.left_at >= m.next)
+        ) AS members
+        , (SELECT COUNT(*) FROM periods p
+            WHERE p.joined_at >= m.start
+            AND p.joined_at < m.next
+        ) AS joined
+        , (SELECT COUNT(*) FROM periods p
+            WHERE p.left_at >= m.start
+            AND p.left_at < m.next
+        ) AS left
+        , (SELECT h.headcount FROM workplace_headcounts h
+            WHERE h.workplace_id = $1
+            AND h.estimated_on < m.next
+            ORDER BY h.estimated_on DESC
+                , h.created_at DESC
+            LIMIT 1
+        ) AS headcount
+    FROM months m
+)
+SELECT series.*
+    , series.members::FLOAT8 / series.headcount AS density
+FROM series
+ORDER BY month
+",
+    )
+    .bind(id)
+    .bind(months)
+}
+
+/// Last `months` months including the current one.
+/// Member is counted when they were employed by the company according to occupation history.
+pub fn company_monthly<'a>(id: Id<Company>, months: i32) -> QueryAs<'a, MonthStats> {
+    sqlx::query_as(
+        "
+WITH months AS (
+    SELECT month::DATE AS start
+        , (month + INTERVAL '1 month')::DATE AS next
+    FROM generate_series(
+        date_trunc('month', CURRENT_DATE) - make_interval(months => $2 - 1)
+        , date_trunc('month', CURRENT_DATE)
+        , INTERVAL '1 month'
+    ) AS month
+), periods AS (
+    SELECT p.id
+        , p.joined_at
+        , p.left_at
+        , o.started_on
+        , CASE WHEN o.is_current THEN 'infinity' ELSE o.ended_on END AS ended_on
+    FROM membership_periods p
+    INNER JOIN occupations o ON o.member_id = p.member_id
+    WHERE o.company_id = $1
+)
+SELECT m.start AS month
+    , (SELECT COUNT(DISTINCT p.id) FROM periods p
+        WHERE p.joined_at < m.next
+        AND (p.left_at IS NULL OR p.left_at >= m.next)
+        AND (p.started_on IS NULL OR p.started_on < m.next)
+        AND p.ended_on >= m.next - 1
+    ) AS members
+    , (SELECT COUNT(DISTINCT p.id) FROM periods p
+        WHERE p.joined_at >= m.start
+        AND p.joined_at < m.next
+        AND (p.started_on IS NULL OR p.started_on <= p.joined_at::DATE)
+        AND p.ended_on >= p.joined_at::DATE
+    ) AS joined
+    , (SELECT COUNT(DISTINCT p.id) FROM periods p
+        WHERE p.left_at >= m.start
+        AND p.left_at < m.next
+        AND (p.started_on IS NULL OR p.started_on <= p.left_at::DATE)
+        AND p.ended_on >= p.left_at::DATE
+    ) AS left
+    , NULL::INTEGER AS headcount
+    , NULL::FLOAT8 AS density
+FROM months m
+ORDER BY m.start
+",
+    )
+    .bind(id)
+    .bind(months)
+}
+
+/// Applications reaching each state in every period
+pub fn application_series<'a>(filter: &'a SeriesFilter<'_>) -> QueryAs<'a, ApplicationTransitions> {
+    sqlx::query_as(concat!(
+        "
+WITH ",
+        periods_cte!(),
+        ", events AS (
+    SELECT date_trunc($1, e.happened_at) AS period
+        , e.kind
+    FROM registration_requests rr
+    LEFT JOIN members m ON m.registration_request_id = rr.id
+    CROSS JOIN LATERAL (
+        VALUES ('submitted', rr.created_at)
+            , ('confirmed', rr.confirmed_at)
+            , ('accepted', m.created_at)
+            , ('rejected', rr.rejected_at)
+            , ('invalidated', rr.invalidated_at)
+    ) AS e(kind, happened_at)
+    WHERE e.happened_at >= (SELECT MIN(period) FROM periods)
+    AND ($3::TEXT IS NULL OR search_normalize(rr.city) = search_normalize($3))
+    AND ($4::TEXT IS NULL OR rr.registration_local = $4)
+    AND ($5::TEXT IS NULL OR rr.registration_source = $5)
+    AND ($6::UUID IS NULL OR rr.company_id = $6)
+)
+SELECT p.period
+    , COUNT(*) FILTER (WHERE e.kind = 'submitted') AS submitted
+    , COUNT(*) FILTER (WHERE e.kind = 'confirmed') AS confirmed
+    , COUNT(*) FILTER (WHERE e.kind = 'accepted') AS accepted
+    , COUNT(*) FILTER (WHERE e.kind = 'rejected') AS rejected
+    , COUNT(*) FILTER (WHERE e.kind = 'invalidated') AS invalidated
+FROM periods p
+LEFT JOIN events e ON e.period = p.period
+GROUP BY p.period
+ORDER BY p.period
+",
+    ))
+    .bind(filter.bucket().as_str())
+    .bind(filter.periods())
+    .bind(filter.city)
+    .bind(filter.language)
+    .bind(filter.source)
+    .bind(filter.company_id)
+}
+
+/// Membership periods started in every period
+pub fn acceptance_series<'a>(filter: &'a SeriesFilter<'_>) -> QueryAs<'a, Acceptances> {
+    sqlx::query_as(concat!(
+        "
+WITH ",
+        periods_cte!(),
+        ", ",
+        memberships_cte!(),
+        "
+SELECT p.period
+    , COUNT(m.joined_at) AS accepted
+    , COUNT(m.joined_at) FILTER (WHERE EXISTS (
+        SELECT 1 FROM membership_periods earlier
+        WHERE earlier.member_id = m.member_id
+        AND earlier.joined_at < m.joined_at
+    )) AS rejoined
+FROM periods p
+LEFT JOIN memberships m ON date_trunc($1, m.joined_at) = p.period
+GROUP BY p.period
+ORDER BY p.period
+",
+    ))
+    .bind(filter.bucket().as_str())
+    .bind(filter.periods())
+    .bind(filter.city)
+    .bind(filter.language)
+    .bind(filter.source)
+    .bind(filter.company_id)
+}
+
+/// Membership periods ended in every period by reason
+pub fn leaver_series<'a>(filter: &'a SeriesFilter<'_>) -> QueryAs<'a, Leavers> {
+    sqlx::query_as(concat!(
+        "
+WITH ",
+        periods_cte!(),
+        ", ",
+        memberships_cte!(),
+        "
+SELECT p.period
+    , COUNT(m.left_at) AS left
+    , COUNT(m.left_at) FILTER (WHERE m.left_reason = 'resigned') AS resigned
+    , COUNT(m.left_at) FILTER (WHERE m.left_reason = 'expelled') AS expelled
+    , COUNT(m.left_at) FILTER (WHERE m.left_reason = 'lapsed') AS lapsed
+    , COUNT(m.left_at) FILTER (WHERE m.left_reason = 'deceased') AS deceased
+    , COUNT(m.left_at) FILTER (WHERE m.left_reason IS NULL) AS unknown
+FROM periods p
+LEFT JOIN memberships m ON date_trunc($1, m.left_at) = p.period
+GROUP BY p.period
+ORDER BY p.period
+",
+    ))
+    .bind(filter.bucket().as_str())
+    .bind(filter.periods())
+    .bind(filter.city)
+    .bind(filter.language)
+    .bind(filter.source)
+    .bind(filter.company_id)
+}
+
+/// Joins, leaves and members at the end of every period
+pub fn membership_series<'a>(filter: &'a SeriesFilter<'_>) -> QueryAs<'a, MembershipChange> {
+    sqlx::query_as(concat!(
+        "
+WITH ",
+        periods_cte!(),
+        ", ",
+        memberships_cte!(),
+        ", changes AS (
+    SELECT p.period
+        , (SELECT COUNT(*) FROM memberships m
+            WHERE date_trunc($1, m.joined_at) = p.period
+        ) AS joined
+        , (SELECT COUNT(*) FROM memberships m
+            WHERE date_trunc($1, m.left_at) = p.period
+        ) AS left
+        , (SELECT COUNT(*) FROM memberships m
+            WHERE m.joined_at < p.period + ('1 ' || $1)::INTERVAL
+            AND (m.left_at IS NULL OR m.left_at >= p.period + ('1 ' || $1)::INTERVAL)
+        ) AS members
+    FROM periods p
+)
+SELECT c.period
+    , c.joined
+    , c.left
+    , c.joined - c.left AS net
+    , c.members
+FROM changes c
+ORDER BY c.period
+",
+    ))
+    .bind(filter.bucket().as_str())
+    .bind(filter.periods())
+    .bind(filter.city)
+    .bind(filter.language)
+    .bind(filter.source)
+    .bind(filter.company_id)
+}
+
+/// Applications submitted on `since` or later.
+/// Durations are medians in days of applications which reached both steps.
+pub fn application_funnel<'a>(since: NaiveDate) -> QueryAs<'a, ApplicationsFunnel> {
+    sqlx::query_as(
+        "
+WITH requests AS (
+    SELECT rr.created_at
+        , rr.confirmed_at
+        , m.created_at AS accepted_at
+        , m.onboarding_finished_at
+    FROM registration_requests rr
+    LEFT JOIN members m ON m.registration_request_id = rr.id
+    WHERE rr.created_at >= $1
+)
+SELECT COUNT(*) AS submitted
+    , COUNT(confirmed_at) AS confirmed
+    , COUNT(accepted_at) AS accepted
+    , COUNT(onboarding_finished_at) AS onboarded
+    , COUNT(confirmed_at)::FLOAT8 / NULLIF(COUNT(*), 0) AS confirmation_rate
+    , EXTRACT(EPOCH FROM percentile_cont(0.5) WITHIN GROUP (
+        ORDER BY confirmed_at - created_at
+    ))::FLOAT8 / 86400 AS days_to_confirmation
+    , EXTRACT(EPOCH FROM percentile_cont(0.5) WITHIN GROUP (
+        ORDER BY accepted_at - confirmed_at
+    ))::FLOAT8 / 86400 AS days_to_acceptance
+    , EXTRACT(EPOCH FROM percentile_cont(0.5) WITHIN GROUP (
+        ORDER BY onboarding_finished_at - accepted_at
+    ))::FLOAT8 / 86400 AS days_to_onboarding
+    , EXTRACT(EPOCH FROM percentile_cont(0.5) WITHIN GROUP (
+        ORDER BY onboarding_finished_at - created_at
+    ))::FLOAT8 / 86400 AS days_total
+FROM requests
+",
+    )
+    .bind(since)
+}
+
+/// Confirmed applications waiting for decision of board, age is counted from confirmation
+pub fn applications_backlog<'a>() -> QueryAs<'a, ApplicationsBacklog> {
+    sqlx::query_as(
+        "
+SELECT COUNT(*) AS applications
+    , MIN(confirmed_at) AS oldest_confirmed_at
+    , EXTRACT(EPOCH FROM percentile_cont(0.5) WITHIN GROUP (
+        ORDER BY NOW() - confirmed_at
+    ))::FLOAT8 / 86400 AS median_age_days
+    , COUNT(*) FILTER (WHERE confirmed_at < NOW() - INTERVAL '7 days') AS older_than_week
+    , COUNT(*) FILTER (WHERE confirmed_at < NOW() - INTERVAL '30 days') AS older_than_month
+FROM registration_requests_processing
 ",
     )
 }
@@ -2,18 +2,25 @@ use super::{ApiError, SuccessResponse};
 use crate::api::Response;
 use crate::api::members::Summary;
 use crate::api::members::query::get_status_data;
-use crate::data::{Id, Member, Workplace};
+use crate::data::{Id, Member, MemberNumber, Workplace, WorkplacePosition};
 use crate::db::DbPool;
 use crate::server::oid::{JwtToken, Provider, Role};
 use crate::validation::Validated;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+use log::error;
+use rocket::http::Status;
 use rocket::serde::json::Json;
-use rocket::{Route, State, delete, get, post, put, routes};
+use rocket::{Route, State, delete, get, patch, post, put, routes};
 use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
 use uuid::Uuid;
 use validator::Validate;
 
 pub mod query;
+pub mod scope;
+pub mod sync;
+
+use scope::WorkplaceScope;
 
 #[derive(Debug, Serialize, sqlx::FromRow)]
 pub struct WorkplaceSummary {
@@ -22,21 +29,48 @@ pub struct WorkplaceSummary {
     email: String,
     created_at: DateTime<Utc>,
     keycloak_group_id: Uuid,
+    /// Workplace was closed down
+    archived_at: Option<DateTime<Utc>>,
     member_count: i64,
 }
 
-#[get("/")]
+impl WorkplaceSummary {
+    fn require_active(&self) -> Result<(), ApiError> {
+        if self.archived_at.is_some() {
+            return Err(ApiError::data_conflict(&format!(
+                "Workplace {} is archived",
+                self.id
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Workplace as shown to its own members, who can't see details for the board
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct MemberWorkplace {
+    id: Id<Workplace>,
+    name: String,
+    email: String,
+}
+
+/// Representatives only see their own workplaces.
+/// Archived workplaces are only included when `archived` is set.
+#[get("/?<archived>")]
 async fn list_all(
     db_pool: &State<DbPool>,
     oid_provider: &State<Provider>,
     token: JwtToken<'_>,
+    archived: Option<bool>,
 ) -> Response<Json<Vec<WorkplaceSummary>>> {
-    // We should restrict this only for Admins/Board (people with ManageWorkplaces), until we have permissions to ViewMember separated by workplace
-    // If we allowed access to this EP to anyone with ListWorkplaces, reps could see members from other workplaces
-    // We still need to be careful when sharing links to specific workplaces, because every rep will have combination of ListWorkplaces and ViewMember, which allow them to open link to list of members of any workplace
-    oid_provider.require_role(&token, Role::ManageWorkplaces)?;
+    oid_provider.require_role(&token, Role::ListWorkplaces)?;
+    let scope = WorkplaceScope::of(db_pool, oid_provider, &token).await?;
 
-    let summaries = query::list_summaries().fetch_all(db_pool.inner()).await?;
+    let mut summaries = query::list_summaries(archived.unwrap_or(false))
+        .fetch_all(db_pool.inner())
+        .await?;
+    summaries.retain(|summary| scope.includes(summary.id));
 
     Ok(Json(summaries))
 }
@@ -49,6 +83,9 @@ async fn detail(
     workplace_id: Id<Workplace>,
 ) -> Response<Json<WorkplaceSummary>> {
     oid_provider.require_any_role(&token, &[Role::ListWorkplaces])?;
+    WorkplaceScope::of(db_pool, oid_provider, &token)
+        .await?
+        .require_workplace(workplace_id)?;
 
     let detail = query::detail(workplace_id)
         .fetch_one(db_pool.inner())
@@ -84,6 +121,311 @@ async fn create_workplace(
     Ok(Json(workplace))
 }
 
+#[derive(Debug, Deserialize, Validate)]
+#[serde(crate = "rocket::serde")]
+pub struct UpdateWorkplace {
+    name: Option<String>,
+    #[validate(email)]
+    email: Option<String>,
+    /// Members of workplace are moved to the new group
+    keycloak_group_id: Option<Uuid>,
+}
+
+async fn move_keycloak_user(
+    oid_provider: &Provider,
+    token: &JwtToken<'_>,
+    user: Uuid,
+    from: Uuid,
+    to: Uuid,
+) -> Response<()> {
+    oid_provider
+        .connect_keycloak_user_and_group(token, user, to)
+        .await?;
+    oid_provider
+        .remove_keycloak_user_from_group(token, user, from)
+        .await?;
+
+    Ok(())
+}
+
+/// Move OIDC accounts from Keycloak group `from` to group `to`.
+/// When any move fails, accounts moved so far are moved back.
+async fn move_keycloak_users(
+    oid_provider: &Provider,
+    token: &JwtToken<'_>,
+    users: &[(Uuid,)],
+    from: Uuid,
+    to: Uuid,
+) -> Response<()> {
+    if from == to {
+        return Ok(());
+    }
+
+    for (index, (user,)) in users.iter().enumerate() {
+        if let Err(err) = move_keycloak_user(oid_provider, token, *user, from, to).await {
+            // Failed user could have been added to the new group already
+            for (moved,) in &users[..=index] {
+                if let Err(revert_err) =
+                    move_keycloak_user(oid_provider, token, *moved, to, from).await
+                {
+                    error!(
+                        "Failed to move Keycloak user {moved} back to group {from}: {revert_err:?}"
+                    );
+                }
+            }
+            return Err(err);
+        }
+    }
+
+    Ok(())
+}
+
+#[patch("/<workplace_id>", format = "json", data = "<workplace>")]
+async fn update_workplace(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    workplace_id: Id<Workplace>,
+    workplace: Validated<Json<UpdateWorkplace>>,
+) -> Response<Json<WorkplaceSummary>> {
+    oid_provider.require_role(&token, Role::ManageWorkplaces)?;
+
+    let workplace = workplace.into_inner().into_inner();
+
+    let current = query::detail(workplace_id)
+        .fetch_optional(db_pool.inner())
+        .await?
+        .ok_or(Status::NotFound)?;
+    let users = query::list_keycloak_users(workplace_id)
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    let mut tx = db_pool.begin().await?;
+
+    query::update_workplace(workplace_id, &workplace)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    // Group memberships are moved before commit so that failure leaves workplace unchanged
+    if let Some(keycloak_group_id) = workplace.keycloak_group_id {
+        move_keycloak_users(
+            oid_provider,
+            &token,
+            &users,
+            current.keycloak_group_id,
+            keycloak_group_id,
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    let detail = query::detail(workplace_id)
+        .fetch_one(db_pool.inner())
+        .await?;
+
+    Ok(Json(detail))
+}
+
+/// Close down workplace. Its members and positions are kept.
+#[put("/<workplace_id>/archive")]
+async fn archive(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    workplace_id: Id<Workplace>,
+) -> Response<Json<WorkplaceSummary>> {
+    oid_provider.require_role(&token, Role::ManageWorkplaces)?;
+
+    query::detail(workplace_id)
+        .fetch_one(db_pool.inner())
+        .await?
+        .require_active()?;
+
+    query::set_archived(workplace_id, true)
+        .execute(db_pool.inner())
+        .await?;
+
+    let detail = query::detail(workplace_id)
+        .fetch_one(db_pool.inner())
+        .await?;
+
+    Ok(Json(detail))
+}
+
+#[delete("/<workplace_id>/archive")]
+async fn restore(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    workplace_id: Id<Workplace>,
+) -> Response<Json<WorkplaceSummary>> {
+    oid_provider.require_role(&token, Role::ManageWorkplaces)?;
+
+    let workplace = query::detail(workplace_id)
+        .fetch_one(db_pool.inner())
+        .await?;
+    if workplace.archived_at.is_none() {
+        return Err(ApiError::data_conflict("Workplace is not archived"));
+    }
+
+    query::set_archived(workplace_id, false)
+        .execute(db_pool.inner())
+        .await?;
+
+    let detail = query::detail(workplace_id)
+        .fetch_one(db_pool.inner())
+        .await?;
+
+    Ok(Json(detail))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Merge {
+    /// Duplicate which is merged into workplace and deleted
+    workplace_id: Id<Workplace>,
+}
+
+impl Merge {
+    fn check(&self, workplace_id: Id<Workplace>) -> Result<(), ApiError> {
+        if self.workplace_id == workplace_id {
+            return Err(ApiError::bad_request(
+                "Workplace can't be merged into itself",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// People of duplicate work in the merged workplace now, so the latest estimates
+/// of both are added up. Estimates are ordered the latest first.
+fn merged_headcount(
+    target: &[HeadcountEstimate],
+    source: &[HeadcountEstimate],
+    note: String,
+) -> Option<NewHeadcountEstimate> {
+    let source = source.first()?;
+    let target = target.first().map_or(0, |estimate| estimate.headcount);
+
+    Some(NewHeadcountEstimate {
+        headcount: source.headcount.saturating_add(target),
+        estimated_on: None,
+        note: Some(note),
+    })
+}
+
+/// Merge duplicate workplace into this one. Members, positions and Keycloak group
+/// memberships of the duplicate are moved and the duplicate is deleted.
+#[post("/<workplace_id>/merge", format = "json", data = "<merge>")]
+async fn merge(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    workplace_id: Id<Workplace>,
+    merge: Json<Merge>,
+) -> Response<Json<WorkplaceSummary>> {
+    oid_provider.require_role(&token, Role::ManageWorkplaces)?;
+
+    merge.check(workplace_id)?;
+
+    let target = query::detail(workplace_id)
+        .fetch_optional(db_pool.inner())
+        .await?
+        .ok_or(Status::NotFound)?;
+    target.require_active()?;
+    let source = query::detail(merge.workplace_id)
+        .fetch_optional(db_pool.inner())
+        .await?
+        .ok_or(Status::NotFound)?;
+    let users = query::list_keycloak_users(source.id)
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    let mut tx = db_pool.begin().await?;
+
+    query::merge_members(target.id, source.id)
+        .execute(&mut *tx)
+        .await?;
+    query::merge_positions(target.id, source.id)
+        .execute(&mut *tx)
+        .await?;
+    let target_headcounts = query::list_headcounts(target.id)
+        .fetch_all(&mut *tx)
+        .await?;
+    let source_headcounts = query::list_headcounts(source.id)
+        .fetch_all(&mut *tx)
+        .await?;
+    if let Some(headcount) = merged_headcount(
+        &target_headcounts,
+        &source_headcounts,
+        format!("Merged with {}", source.name),
+    ) {
+        query::create_headcount(target.id, &headcount)
+            .fetch_one(&mut *tx)
+            .await?;
+    }
+    query::remove_all_members(source.id)
+        .execute(&mut *tx)
+        .await?;
+    query::move_legacy_workplace(source.id, Some(target.id))
+        .execute(&mut *tx)
+        .await?;
+    query::delete_workplace(source.id).execute(&mut *tx).await?;
+
+    move_keycloak_users(
+        oid_provider,
+        &token,
+        &users,
+        source.keycloak_group_id,
+        target.keycloak_group_id,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    let detail = query::detail(target.id).fetch_one(db_pool.inner()).await?;
+
+    Ok(Json(detail))
+}
+
+/// Only workplaces without members and positions can be deleted,
+/// others need to be archived or merged
+#[delete("/<workplace_id>")]
+async fn delete_workplace(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    workplace_id: Id<Workplace>,
+) -> Response<SuccessResponse> {
+    oid_provider.require_role(&token, Role::ManageWorkplaces)?;
+
+    let (in_use,) = query::is_in_use(workplace_id)
+        .fetch_one(db_pool.inner())
+        .await?;
+    if in_use {
+        return Err(ApiError::data_conflict(
+            "Workplace has members or positions, archive or merge it instead",
+        ));
+    }
+
+    let mut tx = db_pool.begin().await?;
+
+    query::move_legacy_workplace(workplace_id, None)
+        .execute(&mut *tx)
+        .await?;
+    let result = query::delete_workplace(workplace_id)
+        .execute(&mut *tx)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(Status::NotFound.into());
+    }
+
+    tx.commit().await?;
+
+    Ok(SuccessResponse::NoContent)
+}
+
 #[put("/<workplace_id>/members/<member_id>")]
 async fn assign_member_to_workplace(
     db_pool: &State<DbPool>,
@@ -94,13 +436,14 @@ async fn assign_member_to_workplace(
 ) -> Response<SuccessResponse> {
     oid_provider.require_role(&token, Role::ManageWorkplaces)?;
 
-    let result = query::create_connection_between_member_and_workplace(workplace_id, member_id)
-        .execute(db_pool.inner())
-        .await?;
-
     let workplace_details = query::detail(workplace_id)
         .fetch_one(db_pool.inner())
         .await?;
+    workplace_details.require_active()?;
+
+    let result = query::create_connection_between_member_and_workplace(workplace_id, member_id)
+        .execute(db_pool.inner())
+        .await?;
 
     let user_data = get_status_data(member_id)
         .fetch_one(db_pool.inner())
@@ -167,6 +510,9 @@ async fn get_all_workplace_members(
 ) -> Response<Json<Vec<Summary>>> {
     oid_provider.require_role(&token, Role::ListWorkplaces)?;
     oid_provider.require_role(&token, Role::ViewMember)?;
+    WorkplaceScope::of(db_pool, oid_provider, &token)
+        .await?
+        .require_workplace(workplace_id)?;
 
     let summaries = query::get_all_workplace_members(workplace_id)
         .fetch_all(db_pool.inner())
@@ -175,14 +521,463 @@ async fn get_all_workplace_members(
     Ok(Json(summaries))
 }
 
+/// Position in workplace, only reps and deputies can see members of the workplace
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PositionKind {
+    Rep,
+    Deputy,
+    Steward,
+    SafetyOfficer,
+}
+
+impl PositionKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Rep => "rep",
+            Self::Deputy => "deputy",
+            Self::Steward => "steward",
+            Self::SafetyOfficer => "safety_officer",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Position {
+    id: Id<WorkplacePosition>,
+    workplace_id: Id<Workplace>,
+    workplace_name: String,
+    member_id: Id<Member>,
+    member_number: MemberNumber,
+    first_name: Option<String>,
+    last_name: Option<String>,
+    kind: String,
+    started_on: NaiveDate,
+    /// `None` while member holds the position
+    ended_on: Option<NaiveDate>,
+    note: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl Position {
+    fn check_end(&self, ended_on: NaiveDate) -> Result<(), ApiError> {
+        if self.ended_on.is_some() {
+            return Err(ApiError::data_conflict("Position has already ended"));
+        }
+        if ended_on < self.started_on {
+            return Err(ApiError::bad_request(
+                "Position can't end before it started",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Member is in Keycloak group of workplace as long as they belong to workplace
+/// or hold a position in it. Members without paired account are skipped.
+/// Reads through `connection` so that uncommitted changes of positions are taken into account.
+pub async fn sync_keycloak_group(
+    connection: &mut PgConnection,
+    oid_provider: &Provider,
+    token: &JwtToken<'_>,
+    workplace_id: Id<Workplace>,
+    member_id: Id<Member>,
+) -> Response<()> {
+    let Some(keycloak_id) = get_status_data(member_id)
+        .fetch_one(&mut *connection)
+        .await?
+        .sub()
+    else {
+        return Ok(());
+    };
+
+    let workplace = query::detail(workplace_id)
+        .fetch_one(&mut *connection)
+        .await?;
+    let (belongs,) = query::belongs_to_workplace(workplace_id, member_id)
+        .fetch_one(&mut *connection)
+        .await?;
+
+    if belongs {
+        oid_provider
+            .connect_keycloak_user_and_group(token, keycloak_id, workplace.keycloak_group_id)
+            .await?;
+    } else {
+        oid_provider
+            .remove_keycloak_user_from_group(token, keycloak_id, workplace.keycloak_group_id)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Current positions unless `history` is set
+#[get("/<workplace_id>/positions?<history>")]
+async fn list_positions(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    workplace_id: Id<Workplace>,
+    history: Option<bool>,
+) -> Response<Json<Vec<Position>>> {
+    oid_provider.require_role(&token, Role::ListWorkplaces)?;
+    WorkplaceScope::of(db_pool, oid_provider, &token)
+        .await?
+        .require_workplace(workplace_id)?;
+
+    let positions = query::list_workplace_positions(workplace_id, history.unwrap_or(false))
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    Ok(Json(positions))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct NewPosition {
+    member_id: Id<Member>,
+    kind: PositionKind,
+    /// Today by default
+    started_on: Option<NaiveDate>,
+    note: Option<String>,
+}
+
+#[post("/<workplace_id>/positions", format = "json", data = "<position>")]
+async fn assign_position(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    workplace_id: Id<Workplace>,
+    position: Json<NewPosition>,
+) -> Response<Json<Position>> {
+    oid_provider.require_role(&token, Role::ManageWorkplaces)?;
+
+    let status = get_status_data(position.member_id)
+        .fetch_optional(db_pool.inner())
+        .await?
+        .ok_or(Status::NotFound)?;
+    if status.has_left() {
+        return Err(ApiError::data_conflict(&format!(
+            "Id {} is no longer a member of organization",
+            position.member_id
+        )));
+    }
+
+    query::detail(workplace_id)
+        .fetch_one(db_pool.inner())
+        .await?
+        .require_active()?;
+
+    let mut tx = db_pool.begin().await?;
+
+    let (id,) = query::create_position(workplace_id, &position)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    // Position is only stored once Keycloak group is updated
+    sync_keycloak_group(
+        &mut tx,
+        oid_provider,
+        &token,
+        workplace_id,
+        position.member_id,
+    )
+    .await?;
+
+    let position = query::get_position(id).fetch_one(&mut *tx).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(position))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct EndPosition {
+    /// Today by default
+    ended_on: Option<NaiveDate>,
+}
+
+#[post("/<workplace_id>/positions/<id>/end", format = "json", data = "<end>")]
+async fn end_position(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    workplace_id: Id<Workplace>,
+    id: Id<WorkplacePosition>,
+    end: Json<EndPosition>,
+) -> Response<Json<Position>> {
+    oid_provider.require_role(&token, Role::ManageWorkplaces)?;
+
+    let position = query::get_position(id)
+        .fetch_optional(db_pool.inner())
+        .await?
+        .filter(|position| position.workplace_id == workplace_id)
+        .ok_or(Status::NotFound)?;
+
+    let ended_on = end.ended_on.unwrap_or_else(|| Utc::now().date_naive());
+    position.check_end(ended_on)?;
+
+    let mut tx = db_pool.begin().await?;
+
+    query::end_position(position.id, ended_on)
+        .execute(&mut *tx)
+        .await?;
+
+    sync_keycloak_group(
+        &mut tx,
+        oid_provider,
+        &token,
+        workplace_id,
+        position.member_id,
+    )
+    .await?;
+
+    let position = query::get_position(position.id).fetch_one(&mut *tx).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(position))
+}
+
+/// All positions of member including past ones
+#[get("/<id>/positions")]
+async fn list_member_positions(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    id: Id<Member>,
+) -> Response<Json<Vec<Position>>> {
+    oid_provider.require_any_role(&token, &[Role::ListMembers, Role::ViewMember])?;
+    WorkplaceScope::of(db_pool, oid_provider, &token)
+        .await?
+        .require_member(db_pool, id)
+        .await?;
+
+    let positions = query::list_member_positions(id)
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    Ok(Json(positions))
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct HeadcountEstimate {
+    id: Id<HeadcountEstimate>,
+    workplace_id: Id<Workplace>,
+    headcount: i32,
+    estimated_on: NaiveDate,
+    note: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+/// Estimates of number of people working in workplace, the latest first
+#[get("/<workplace_id>/headcounts")]
+async fn list_headcounts(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    workplace_id: Id<Workplace>,
+) -> Response<Json<Vec<HeadcountEstimate>>> {
+    oid_provider.require_role(&token, Role::ListWorkplaces)?;
+    WorkplaceScope::of(db_pool, oid_provider, &token)
+        .await?
+        .require_workplace(workplace_id)?;
+
+    let headcounts = query::list_headcounts(workplace_id)
+        .fetch_all(db_pool.inner())
+        .await?;
+
+    Ok(Json(headcounts))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[serde(crate = "rocket::serde")]
+pub struct NewHeadcountEstimate {
+    #[validate(range(min = 1))]
+    headcount: i32,
+    /// Today by default
+    estimated_on: Option<NaiveDate>,
+    note: Option<String>,
+}
+
+/// Reps can estimate headcount of their own workplaces
+#[post("/<workplace_id>/headcounts", format = "json", data = "<headcount>")]
+async fn create_headcount(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    workplace_id: Id<Workplace>,
+    headcount: Validated<Json<NewHeadcountEstimate>>,
+) -> Response<Json<HeadcountEstimate>> {
+    oid_provider.require_role(&token, Role::ListWorkplaces)?;
+    WorkplaceScope::of(db_pool, oid_provider, &token)
+        .await?
+        .require_workplace(workplace_id)?;
+
+    query::detail(workplace_id)
+        .fetch_optional(db_pool.inner())
+        .await?
+        .ok_or(Status::NotFound)?
+        .require_active()?;
+
+    let headcount = query::create_headcount(workplace_id, &headcount.into_inner())
+        .fetch_one(db_pool.inner())
+        .await?;
+
+    Ok(Json(headcount))
+}
+
+/// Differences between Keycloak groups and members of workplaces.
+/// Reconciliation with `fix` makes the chosen side match the other one.
+#[get("/keycloak/reconciliation")]
+async fn keycloak_reconciliation(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+) -> Response<Json<Vec<sync::Drift>>> {
+    oid_provider.require_role(&token, Role::ManageWorkplaces)?;
+
+    let report = sync::reconcile(db_pool, oid_provider, &token, None).await?;
+
+    Ok(Json(report))
+}
+
+#[post("/keycloak/reconciliation?<fix>")]
+async fn fix_keycloak_reconciliation(
+    db_pool: &State<DbPool>,
+    oid_provider: &State<Provider>,
+    token: JwtToken<'_>,
+    fix: sync::Direction,
+) -> Response<Json<Vec<sync::Drift>>> {
+    oid_provider.require_role(&token, Role::ManageWorkplaces)?;
+
+    let report = sync::reconcile(db_pool, oid_provider, &token, Some(fix)).await?;
+
+    Ok(Json(report))
+}
+
 #[expect(clippy::redundant_type_annotations, reason = "rocket macro expansion")]
 pub fn routes() -> Vec<Route> {
     routes![
         list_all,
         detail,
         create_workplace,
+        update_workplace,
+        archive,
+        restore,
+        merge,
+        delete_workplace,
         assign_member_to_workplace,
         remove_member_from_workplace,
         get_all_workplace_members,
+        list_positions,
+        assign_position,
+        end_position,
+        list_headcounts,
+        create_headcount,
+        keycloak_reconciliation,
+        fix_keycloak_reconciliation,
     ]
 }
+
+/// Routes mounted under `/members`
+#[expect(clippy::redundant_type_annotations, reason = "rocket macro expansion")]
+pub fn member_routes() -> Vec<Route> {
+    routes![list_member_positions]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(started_on: NaiveDate, ended_on: Option<NaiveDate>) -> Position {
+        Position {
+            id: Id::from(Uuid::new_v4()),
+            workplace_id: Id::from(Uuid::new_v4()),
+            workplace_name: "Warehouse".to_string(),
+            member_id: Id::from(Uuid::new_v4()),
+            member_number: rocket::serde::json::from_str("1").unwrap(),
+            first_name: None,
+            last_name: None,
+            kind: PositionKind::Rep.as_str().to_string(),
+            started_on,
+            ended_on,
+            note: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn estimate(headcount: i32) -> HeadcountEstimate {
+        HeadcountEstimate {
+            id: Id::from(Uuid::new_v4()),
+            workplace_id: Id::from(Uuid::new_v4()),
+            headcount,
+            estimated_on: Utc::now().date_naive(),
+            note: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn workplace_is_not_merged_into_itself() {
+        let id = Id::from(Uuid::new_v4());
+
+        Merge {
+            workplace_id: Id::from(Uuid::new_v4()),
+        }
+        .check(id)
+        .unwrap();
+        assert!(Merge { workplace_id: id }.check(id).is_err());
+    }
+
+    #[test]
+    fn merge_adds_up_latest_headcounts() {
+        let headcount = merged_headcount(
+            &[estimate(40), estimate(10)],
+            &[estimate(15), estimate(100)],
+            "Merged with Depot".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(headcount.headcount, 55);
+        assert_eq!(headcount.estimated_on, None);
+        assert_eq!(headcount.note.as_deref(), Some("Merged with Depot"));
+    }
+
+    #[test]
+    fn merge_keeps_headcount_of_duplicate_without_target_estimate() {
+        let headcount = merged_headcount(&[], &[estimate(15)], String::new()).unwrap();
+        assert_eq!(headcount.headcount, 15);
+    }
+
+    #[test]
+    fn merge_without_duplicate_estimate_keeps_target_headcount() {
+        assert!(merged_headcount(&[estimate(40)], &[], String::new()).is_none());
+    }
+
+    #[test]
+    fn position_kinds_are_stored_as_serialized() {
+        for kind in [
+            PositionKind::Rep,
+            PositionKind::Deputy,
+            PositionKind::Steward,
+            PositionKind::SafetyOfficer,
+        ] {
+            assert_eq!(
+                rocket::serde::json::to_string(&kind).unwrap(),
+                format!("\"{}\"", kind.as_str())
+            );
+        }
+    }
+
+    #[test]
+    fn position_ends_once_and_not_before_it_started() {
+        let date = |day| NaiveDate::from_ymd_opt(2024, 5, day).unwrap();
+
+        position(date(2), None).check_end(date(2)).unwrap();
+        position(date(2), None).check_end(date(3)).unwrap();
+        assert!(position(date(2), None).check_end(date(1)).is_err());
+        assert!(position(date(2), Some(date(3))).check_end(date(4)).is_err());
+    }
+}
@@ -1,12 +1,19 @@
-use super::{NewWorkplace, WorkplaceSummary};
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use super::{
+    HeadcountEstimate, MemberWorkplace, NewHeadcountEstimate, NewPosition, NewWorkplace, Position,
+    UpdateWorkplace, WorkplaceSummary,
+};
 use crate::api::members::Summary;
-use crate::data::{Id, Member, Workplace};
+use crate::data::{Id, Member, Workplace, WorkplacePosition};
 use crate::db::{Query, QueryAs};
 
 // member count includes also members, who have already left union
 // current process is to remove association between past members and workplaces manually
 // in the future, it should be done when clicking on "remove member" in member detail
-pub fn list_summaries() -> QueryAs<'static, WorkplaceSummary> {
+// archived workplaces are only included when `archived` is set
+pub fn list_summaries(archived: bool) -> QueryAs<'static, WorkplaceSummary> {
     sqlx::query_as(
         "
 SELECT id
@@ -14,17 +21,21 @@ SELECT id
     , email
     , created_at
     , keycloak_group_id
+    , archived_at
     , COUNT(mw.member_id) AS member_count
 FROM workplaces
 LEFT JOIN members_workplaces mw ON mw.workplace_id = workplaces.id
+WHERE workplaces.archived_at IS NULL OR $1
 GROUP BY workplaces.id
     , workplaces.name
     , workplaces.email
     , workplaces.created_at
     , workplaces.keycloak_group_id
+    , workplaces.archived_at
 ORDER BY workplaces.created_at DESC
 ",
     )
+    .bind(archived)
 }
 
 pub fn detail<'a>(id: Id<Workplace>) -> QueryAs<'a, WorkplaceSummary> {
@@ -35,6 +46,7 @@ SELECT id
     , email
     , created_at
     , keycloak_group_id
+    , archived_at
     , count(mw.member_id) AS member_count
 FROM workplaces
 LEFT JOIN members_workplaces mw ON mw.workplace_id = workplaces.id
@@ -44,11 +56,27 @@ GROUP BY workplaces.id
     , workplaces.email
     , workplaces.created_at
     , workplaces.keycloak_group_id
+    , workplaces.archived_at
 ",
     )
     .bind(id)
 }
 
+pub fn list_member_workplaces<'a>(member_id: Id<Member>) -> QueryAs<'a, MemberWorkplace> {
+    sqlx::query_as(
+        "
+SELECT w.id
+    , w.name
+    , w.email
+FROM workplaces w
+INNER JOIN members_workplaces mw ON mw.workplace_id = w.id
+WHERE mw.member_id = $1
+ORDER BY w.name
+",
+    )
+    .bind(member_id)
+}
+
 pub fn create_workplace(new_workplace: &NewWorkplace) -> QueryAs<'_, WorkplaceSummary> {
     sqlx::query_as(
         "
@@ -64,6 +92,7 @@ RETURNING id
     , email
     , created_at
     , keycloak_group_id
+    , archived_at
     , 0::bigint AS member_count
 ",
     )
@@ -129,12 +158,12 @@ SELECT m.id
     , m.city
     , m.language
     , m.left_at
-    , array_agg(o.company_name ORDER BY o.created_at DESC) AS company_names
+    , array_agg(o.company_name ORDER BY o.is_current DESC, o.started_on DESC NULLS LAST, o.created_at DESC) AS company_names
     , m.created_at
     , ARRAY(SELECT wp.workplace_id FROM members_workplaces wp WHERE wp.member_id = m.id) AS workplace_ids
     , m.sub
 FROM members AS m
-LEFT JOIN occupations o ON o.member_id = m.id
+LEFT JOIN occupations o ON o.member_id = m.id AND o.is_current
 LEFT JOIN members_workplaces mw ON mw.member_id = m.id
 WHERE mw.workplace_id = $1 AND left_at IS NULL
 GROUP BY m.id
@@ -154,3 +183,423 @@ ORDER BY m.member_number DESC
     )
     .bind(workplace_id)
 }
+
+/// Workplaces which user with OIDC `sub` belongs to or is rep or deputy of
+pub fn list_represented_workplaces<'a>(sub: Uuid) -> QueryAs<'a, (Id<Workplace>,)> {
+    sqlx::query_as(
+        "
+SELECT mw.workplace_id
+FROM members_workplaces mw
+INNER JOIN members m ON m.id = mw.member_id
+WHERE m.sub = $1
+AND m.left_at IS NULL
+UNION
+SELECT p.workplace_id
+FROM workplace_positions p
+INNER JOIN members m ON m.id = p.member_id
+WHERE m.sub = $1
+AND m.left_at IS NULL
+AND p.position IN ('rep', 'deputy')
+AND p.ended_on IS NULL
+",
+    )
+    .bind(sub)
+}
+
+pub fn is_member_of_any(
+    member_id: Id<Member>,
+    workplaces: &[Id<Workplace>],
+) -> QueryAs<'_, (bool,)> {
+    sqlx::query_as(
+        "
+SELECT EXISTS (
+    SELECT 1 FROM members_workplaces
+    WHERE member_id = $1
+    AND workplace_id = ANY($2)
+)
+",
+    )
+    .bind(member_id)
+    .bind(workplaces)
+}
+
+pub fn list_workplace_positions<'a>(
+    workplace_id: Id<Workplace>,
+    history: bool,
+) -> QueryAs<'a, Position> {
+    sqlx::query_as(
+        "
+SELECT p.id
+    , p.workplace_id
+    , w.name AS workplace_name
+    , p.member_id
+    , m.member_number
+    , m.first_name
+    , m.last_name
+    , p.position AS kind
+    , p.started_on
+    , p.ended_on
+    , p.note
+    , p.created_at
+FROM workplace_positions p
+INNER JOIN workplaces w ON w.id = p.workplace_id
+INNER JOIN members m ON m.id = p.member_id
+WHERE p.workplace_id = $1
+AND (p.ended_on IS NULL OR $2)
+ORDER BY p.ended_on DESC NULLS FIRST
+    , p.position
+    , p.started_on DESC
+",
+    )
+    .bind(workplace_id)
+    .bind(history)
+}
+
+pub fn list_member_positions<'a>(member_id: Id<Member>) -> QueryAs<'a, Position> {
+    sqlx::query_as(
+        "
+SELECT p.id
+    , p.workplace_id
+    , w.name AS workplace_name
+    , p.member_id
+    , m.member_number
+    , m.first_name
+    , m.last_name
+    , p.position AS kind
+    , p.started_on
+    , p.ended_on
+    , p.note
+    , p.created_at
+FROM workplace_positions p
+INNER JOIN workplaces w ON w.id = p.workplace_id
+INNER JOIN members m ON m.id = p.member_id
+WHERE p.member_id = $1
+ORDER BY p.ended_on DESC NULLS FIRST
+    , p.started_on DESC
+",
+    )
+    .bind(member_id)
+}
+
+pub fn get_position<'a>(id: Id<WorkplacePosition>) -> QueryAs<'a, Position> {
+    sqlx::query_as(
+        "
+SELECT p.id
+    , p.workplace_id
+    , w.name AS workplace_name
+    , p.member_id
+    , m.member_number
+    , m.first_name
+    , m.last_name
+    , p.position AS kind
+    , p.started_on
+    , p.ended_on
+    , p.note
+    , p.created_at
+FROM workplace_positions p
+INNER JOIN workplaces w ON w.id = p.workplace_id
+INNER JOIN members m ON m.id = p.member_id
+WHERE p.id = $1
+",
+    )
+    .bind(id)
+}
+
+pub fn create_position(
+    workplace_id: Id<Workplace>,
+    position: &NewPosition,
+) -> QueryAs<'_, (Id<WorkplacePosition>,)> {
+    sqlx::query_as(
+        "
+INSERT INTO workplace_positions
+    ( workplace_id
+    , member_id
+    , position
+    , started_on
+    , note
+    )
+VALUES
+    ( $1, $2, $3, COALESCE($4, CURRENT_DATE), $5 )
+RETURNING id
+",
+    )
+    .bind(workplace_id)
+    .bind(position.member_id)
+    .bind(position.kind.as_str())
+    .bind(position.started_on)
+    .bind(&position.note)
+}
+
+/// Positions of member who left end today (or the day they start).
+/// Returns each workplace of ended positions once.
+pub fn end_member_positions<'a>(member_id: Id<Member>) -> QueryAs<'a, (Id<Workplace>,)> {
+    sqlx::query_as(
+        "
+WITH ended AS (
+    UPDATE workplace_positions
+    SET ended_on = GREATEST(started_on, CURRENT_DATE)
+    WHERE member_id = $1
+        AND ended_on IS NULL
+    RETURNING workplace_id
+)
+SELECT DISTINCT workplace_id
+FROM ended
+",
+    )
+    .bind(member_id)
+}
+
+pub fn end_position<'a>(id: Id<WorkplacePosition>, ended_on: NaiveDate) -> Query<'a> {
+    sqlx::query(
+        "
+UPDATE workplace_positions
+SET ended_on = $2
+WHERE id = $1
+",
+    )
+    .bind(id)
+    .bind(ended_on)
+}
+
+/// Member is assigned to workplace or holds a position in it
+pub fn holds_position<'a>(
+    workplace_id: Id<Workplace>,
+    member_id: Id<Member>,
+) -> QueryAs<'a, (bool,)> {
+    sqlx::query_as(
+        "
+SELECT EXISTS (
+    SELECT 1 FROM workplace_positions
+    WHERE workplace_id = $1
+    AND member_id = $2
+    AND ended_on IS NULL
+)
+",
+    )
+    .bind(workplace_id)
+    .bind(member_id)
+}
+
+pub fn belongs_to_workplace<'a>(
+    workplace_id: Id<Workplace>,
+    member_id: Id<Member>,
+) -> QueryAs<'a, (bool,)> {
+    sqlx::query_as(
+        "
+SELECT EXISTS (
+    SELECT 1 FROM members_workplaces
+    WHERE workplace_id = $1
+    AND member_id = $2
+) OR EXISTS (
+    SELECT 1 FROM workplace_positions
+    WHERE workplace_id = $1
+    AND member_id = $2
+    AND ended_on IS NULL
+)
+",
+    )
+    .bind(workplace_id)
+    .bind(member_id)
+}
+
+pub fn update_workplace(
+    id: Id<Workplace>,
+    workplace: &UpdateWorkplace,
+) -> QueryAs<'_, (Id<Workplace>,)> {
+    sqlx::query_as(
+        "
+UPDATE workplaces
+SET name = COALESCE($2, name)
+    , email = COALESCE($3, email)
+    , keycloak_group_id = COALESCE($4, keycloak_group_id)
+WHERE id = $1
+RETURNING id
+",
+    )
+    .bind(id)
+    .bind(&workplace.name)
+    .bind(&workplace.email)
+    .bind(workplace.keycloak_group_id)
+}
+
+pub fn set_archived<'a>(id: Id<Workplace>, archived: bool) -> Query<'a> {
+    sqlx::query(
+        "
+UPDATE workplaces
+SET archived_at = CASE WHEN $2 THEN NOW() END
+WHERE id = $1
+",
+    )
+    .bind(id)
+    .bind(archived)
+}
+
+/// OIDC accounts which are in Keycloak group of workplace
+pub fn list_keycloak_users<'a>(id: Id<Workplace>) -> QueryAs<'a, (Uuid,)> {
+    sqlx::query_as(
+        "
+SELECT m.sub
+FROM members m
+WHERE m.sub IS NOT NULL
+AND (
+    EXISTS (
+        SELECT 1 FROM members_workplaces mw
+        WHERE mw.member_id = m.id
+        AND mw.workplace_id = $1
+    ) OR EXISTS (
+        SELECT 1 FROM workplace_positions p
+        WHERE p.member_id = m.id
+        AND p.workplace_id = $1
+        AND p.ended_on IS NULL
+    )
+)
+",
+    )
+    .bind(id)
+}
+
+/// Workplace can only be deleted when it has no members nor positions
+pub fn is_in_use<'a>(id: Id<Workplace>) -> QueryAs<'a, (bool,)> {
+    sqlx::query_as(
+        "
+SELECT EXISTS (
+    SELECT 1 FROM members_workplaces
+    WHERE workplace_id = $1
+) OR EXISTS (
+    SELECT 1 FROM workplace_positions
+    WHERE workplace_id = $1
+)
+",
+    )
+    .bind(id)
+}
+
+pub fn merge_members<'a>(target: Id<Workplace>, source: Id<Workplace>) -> Query<'a> {
+    sqlx::query(
+        "
+INSERT INTO members_workplaces
+    ( member_id
+    , workplace_id
+    )
+SELECT member_id, $1
+FROM members_workplaces
+WHERE workplace_id = $2
+ON CONFLICT DO NOTHING
+",
+    )
+    .bind(target)
+    .bind(source)
+}
+
+/// Positions held in both workplaces at once are not moved
+/// and are deleted together with the source workplace
+pub fn merge_positions<'a>(target: Id<Workplace>, source: Id<Workplace>) -> Query<'a> {
+    sqlx::query(
+        "
+UPDATE workplace_positions p
+SET workplace_id = $1
+WHERE p.workplace_id = $2
+AND NOT (p.ended_on IS NULL AND EXISTS (
+    SELECT 1 FROM workplace_positions t
+    WHERE t.workplace_id = $1
+    AND t.member_id = p.member_id
+    AND t.position = p.position
+    AND t.ended_on IS NULL
+))
+",
+    )
+    .bind(target)
+    .bind(source)
+}
+
+pub fn remove_all_members<'a>(id: Id<Workplace>) -> Query<'a> {
+    sqlx::query(
+        "
+DELETE FROM members_workplaces
+WHERE workplace_id = $1
+",
+    )
+    .bind(id)
+}
+
+/// Legacy `members.workplace_id` column still references workplaces
+pub fn move_legacy_workplace<'a>(from: Id<Workplace>, to: Option<Id<Workplace>>) -> Query<'a> {
+    sqlx::query(
+        "
+UPDATE members
+SET workplace_id = $2
+WHERE workplace_id = $1
+",
+    )
+    .bind(from)
+    .bind(to)
+}
+
+pub fn delete_workplace<'a>(id: Id<Workplace>) -> Query<'a> {
+    sqlx::query(
+        "
+DELETE FROM workplaces
+WHERE id = $1
+",
+    )
+    .bind(id)
+}
+
+/// Members paired with OIDC accounts
+pub fn find_members_by_subs(subs: &[Uuid]) -> QueryAs<'_, (Uuid, Id<Member>)> {
+    sqlx::query_as(
+        "
+SELECT sub
+    , id
+FROM members
+WHERE sub = ANY($1)
+",
+    )
+    .bind(subs)
+}
+
+pub fn list_headcounts<'a>(workplace_id: Id<Workplace>) -> QueryAs<'a, HeadcountEstimate> {
+    sqlx::query_as(
+        "
+SELECT id
+    , workplace_id
+    , headcount
+    , estimated_on
+    , note
+    , created_at
+FROM workplace_headcounts
+WHERE workplace_id = $1
+ORDER BY estimated_on DESC
+    , created_at DESC
+",
+    )
+    .bind(workplace_id)
+}
+
+pub fn create_headcount(
+    workplace_id: Id<Workplace>,
+    headcount: &NewHeadcountEstimate,
+) -> QueryAs<'_, HeadcountEstimate> {
+    sqlx::query_as(
+        "
+INSERT INTO workplace_headcounts
+    ( workplace_id
+    , headcount
+    , estimated_on
+    , note
+    )
+VALUES
+    ( $1, $2, COALESCE($3, CURRENT_DATE), $4 )
+RETURNING id
+    , workplace_id
+    , headcount
+    , estimated_on
+    , note
+    , created_at
+",
+    )
+    .bind(workplace_id)
+    .bind(headcount.headcount)
+    .bind(headcount.estimated_on)
+    .bind(&headcount.note)
+}
@@ -0,0 +1,116 @@
+//! Workplaces whose members user can access
+//!
+//! Roles in token are global. Workplace representatives hold `ListWorkplaces`
+//! and `ViewMember` which alone would let them see members of any workplace.
+//! Unless user also holds one of board roles, access is limited to workplaces
+//! their own member belongs to (which is mirrored to Keycloak group of workplace)
+//! or which they are rep or deputy of.
+use rocket::http::Status;
+
+use super::query;
+use crate::api::{ApiError, Response};
+use crate::data::{Id, Member, Workplace};
+use crate::db::DbPool;
+use crate::server::oid::{JwtToken, Provider, Role};
+
+/// Roles which give access to members of all workplaces
+const UNRESTRICTED_ROLES: [Role; 3] = [
+    Role::ListMembers,
+    Role::ManageMembers,
+    Role::ManageWorkplaces,
+];
+
+#[derive(Debug)]
+pub enum WorkplaceScope {
+    All,
+    Only(Vec<Id<Workplace>>),
+}
+
+impl WorkplaceScope {
+    pub async fn of(
+        db_pool: &DbPool,
+        oid_provider: &Provider,
+        token: &JwtToken<'_>,
+    ) -> Response<Self> {
+        if oid_provider
+            .require_any_role(token, &UNRESTRICTED_ROLES)
+            .is_ok()
+        {
+            return Ok(Self::All);
+        }
+
+        let token_data = oid_provider.decode_jwt(token)?;
+        let workplaces = query::list_represented_workplaces(token_data.claims.sub)
+            .fetch_all(db_pool)
+            .await?
+            .into_iter()
+            .map(|(id,)| id)
+            .collect();
+
+        Ok(Self::Only(workplaces))
+    }
+
+    pub fn includes(&self, workplace_id: Id<Workplace>) -> bool {
+        match self {
+            Self::All => true,
+            Self::Only(workplaces) => workplaces.contains(&workplace_id),
+        }
+    }
+
+    pub fn require_workplace(&self, workplace_id: Id<Workplace>) -> Result<(), ApiError> {
+        if self.includes(workplace_id) {
+            Ok(())
+        } else {
+            Err(Status::Forbidden.into())
+        }
+    }
+
+    /// Member needs to belong to one of workplaces in scope
+    pub async fn require_member(&self, db_pool: &DbPool, member_id: Id<Member>) -> Response<()> {
+        let Self::Only(workplaces) = self else {
+            return Ok(());
+        };
+
+        let (in_scope,) = query::is_member_of_any(member_id, workplaces)
+            .fetch_one(db_pool)
+            .await?;
+        if in_scope {
+            Ok(())
+        } else {
+            Err(Status::Forbidden.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn board_sees_all_workplaces() {
+        let workplace = Id::from(Uuid::new_v4());
+
+        assert!(WorkplaceScope::All.includes(workplace));
+        WorkplaceScope::All.require_workplace(workplace).unwrap();
+    }
+
+    #[test]
+    fn representative_sees_only_own_workplaces() {
+        let own = Id::from(Uuid::new_v4());
+        let other = Id::from(Uuid::new_v4());
+        let scope = WorkplaceScope::Only(vec![own]);
+
+        assert!(scope.includes(own));
+        assert!(!scope.includes(other));
+        scope.require_workplace(own).unwrap();
+        assert!(scope.require_workplace(other).is_err());
+    }
+
+    #[test]
+    fn representative_roles_are_not_unrestricted() {
+        assert!(!UNRESTRICTED_ROLES.contains(&Role::ListWorkplaces));
+        assert!(!UNRESTRICTED_ROLES.contains(&Role::ViewMember));
+    }
+}
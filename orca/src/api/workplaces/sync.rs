@@ -0,0 +1,286 @@
+//! Reconciliation of Keycloak groups of workplaces with workplace membership
+//!
+//! Members are put to Keycloak group of workplace when they are assigned to it
+//! or given a position there. Keycloak is updated separately from the database
+//! and groups can be edited directly in Keycloak so the two can drift apart.
+use std::collections::{BTreeSet, HashMap};
+
+use log::info;
+use reqwest::StatusCode;
+use rocket::FromFormField;
+use serde::Serialize;
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::query;
+use crate::api::ApiError;
+use crate::data::{Id, Member, Workplace};
+use crate::db::DbPool;
+use crate::server::oid::{self, JwtToken, Provider};
+
+#[derive(Debug, Error)]
+pub(crate) enum Error {
+    #[error("SQL Error: {0}")]
+    Sql(#[from] sqlx::Error),
+    #[error("OID provider error: {0}")]
+    Oid(#[from] oid::Error),
+}
+
+impl From<Error> for ApiError {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Sql(err) => err.into(),
+            Error::Oid(err) => err.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum DriftKind {
+    /// Member belongs to workplace but their account is not in the group
+    MissingInGroup,
+    /// Account is in the group but its member doesn't belong to workplace
+    MissingInOrca,
+    /// Keycloak group of workplace doesn't exist
+    GroupMissing,
+}
+
+/// Which side is considered correct when fixing the drift
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromFormField)]
+pub(crate) enum Direction {
+    /// Add and remove accounts in Keycloak groups
+    Keycloak,
+    /// Assign and unassign members to workplaces.
+    /// Accounts not paired with any member can't be fixed this way.
+    Orca,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Drift {
+    workplace_id: Id<Workplace>,
+    workplace_name: String,
+    keycloak_group_id: Uuid,
+    kind: DriftKind,
+    /// Account in Keycloak, `None` when the whole group is missing
+    user_id: Option<Uuid>,
+    /// Member paired with the account
+    member_id: Option<Id<Member>>,
+    /// Drift was fixed during reconciliation
+    fixed: bool,
+}
+
+/// Accounts which are only expected in the group or only present in it
+fn differences(expected: &[Uuid], actual: &[Uuid]) -> Vec<(DriftKind, Uuid)> {
+    let expected: BTreeSet<Uuid> = expected.iter().copied().collect();
+    let actual: BTreeSet<Uuid> = actual.iter().copied().collect();
+
+    expected
+        .difference(&actual)
+        .map(|user| (DriftKind::MissingInGroup, *user))
+        .chain(
+            actual
+                .difference(&expected)
+                .map(|user| (DriftKind::MissingInOrca, *user)),
+        )
+        .collect()
+}
+
+/// Change of workplace assignments which makes Orca match the group
+#[derive(Debug, PartialEq, Eq)]
+enum OrcaFix {
+    Assign,
+    Unassign,
+    Keep,
+}
+
+fn orca_fix(kind: DriftKind, holds_position: bool) -> OrcaFix {
+    match kind {
+        // Position keeps member in the group even without assignment
+        DriftKind::MissingInGroup if holds_position => OrcaFix::Keep,
+        DriftKind::MissingInGroup => OrcaFix::Unassign,
+        DriftKind::MissingInOrca => OrcaFix::Assign,
+        DriftKind::GroupMissing => OrcaFix::Keep,
+    }
+}
+
+/// Apply one side to the other, returns whether the drift is gone
+async fn fix(
+    db_pool: &DbPool,
+    oid_provider: &Provider,
+    token: &JwtToken<'_>,
+    direction: Direction,
+    drift: &Drift,
+) -> Result<bool, Error> {
+    let Some(user_id) = drift.user_id else {
+        return Ok(false);
+    };
+
+    match (direction, drift.kind) {
+        (Direction::Keycloak, DriftKind::MissingInGroup) => {
+            oid_provider
+                .connect_keycloak_user_and_group(token, user_id, drift.keycloak_group_id)
+                .await?;
+            Ok(true)
+        }
+        (Direction::Keycloak, DriftKind::MissingInOrca) => {
+            oid_provider
+                .remove_keycloak_user_from_group(token, user_id, drift.keycloak_group_id)
+                .await?;
+            Ok(true)
+        }
+        (Direction::Keycloak, DriftKind::GroupMissing) => Ok(false),
+        (Direction::Orca, kind) => {
+            let Some(member_id) = drift.member_id else {
+                return Ok(false);
+            };
+            let (holds_position,) = query::holds_position(drift.workplace_id, member_id)
+                .fetch_one(db_pool)
+                .await?;
+            match orca_fix(kind, holds_position) {
+                OrcaFix::Assign => {
+                    query::create_connection_between_member_and_workplace(
+                        drift.workplace_id,
+                        member_id,
+                    )
+                    .execute(db_pool)
+                    .await?;
+                    Ok(true)
+                }
+                OrcaFix::Unassign => {
+                    query::remove_connection_between_member_and_workplace(
+                        drift.workplace_id,
+                        member_id,
+                    )
+                    .execute(db_pool)
+                    .await?;
+                    Ok(true)
+                }
+                OrcaFix::Keep => Ok(false),
+            }
+        }
+    }
+}
+
+/// Compare Keycloak groups of all workplaces with members who belong to them
+/// and list every difference. When `direction` is given the differences are also fixed.
+pub(crate) async fn reconcile(
+    db_pool: &DbPool,
+    oid_provider: &Provider,
+    token: &JwtToken<'_>,
+    direction: Option<Direction>,
+) -> Result<Vec<Drift>, Error> {
+    let workplaces = query::list_summaries(true).fetch_all(db_pool).await?;
+    let mut report = Vec::new();
+
+    for workplace in workplaces {
+        let actual = match oid_provider
+            .get_group_members(token, workplace.keycloak_group_id)
+            .await
+        {
+            Ok(actual) => actual,
+            Err(oid::Error::Proxy(StatusCode::NOT_FOUND)) => {
+                report.push(Drift {
+                    workplace_id: workplace.id,
+                    workplace_name: workplace.name,
+                    keycloak_group_id: workplace.keycloak_group_id,
+                    kind: DriftKind::GroupMissing,
+                    user_id: None,
+                    member_id: None,
+                    fixed: false,
+                });
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let expected: Vec<Uuid> = query::list_keycloak_users(workplace.id)
+            .fetch_all(db_pool)
+            .await?
+            .into_iter()
+            .map(|(user,)| user)
+            .collect();
+
+        let differences = differences(&expected, &actual);
+        if differences.is_empty() {
+            continue;
+        }
+
+        let users: Vec<Uuid> = differences.iter().map(|(_, user)| *user).collect();
+        let members: HashMap<Uuid, Id<Member>> = query::find_members_by_subs(&users)
+            .fetch_all(db_pool)
+            .await?
+            .into_iter()
+            .collect();
+
+        for (kind, user_id) in differences {
+            let mut drift = Drift {
+                workplace_id: workplace.id,
+                workplace_name: workplace.name.clone(),
+                keycloak_group_id: workplace.keycloak_group_id,
+                kind,
+                user_id: Some(user_id),
+                member_id: members.get(&user_id).copied(),
+                fixed: false,
+            };
+            if let Some(direction) = direction {
+                drift.fixed = fix(db_pool, oid_provider, token, direction, &drift).await?;
+            }
+            report.push(drift);
+        }
+    }
+
+    info!(
+        "Found {} difference(s) between workplaces and Keycloak groups",
+        report.len()
+    );
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(n: u128) -> Uuid {
+        Uuid::from_u128(n)
+    }
+
+    #[test]
+    fn same_members_have_no_differences() {
+        assert!(differences(&[user(1), user(2)], &[user(2), user(1)]).is_empty());
+    }
+
+    #[test]
+    fn differences_in_both_directions_are_reported() {
+        assert_eq!(
+            differences(&[user(1), user(2)], &[user(2), user(3)]),
+            vec![
+                (DriftKind::MissingInGroup, user(1)),
+                (DriftKind::MissingInOrca, user(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_accounts_are_reported_once() {
+        assert_eq!(
+            differences(&[user(1), user(1)], &[]),
+            vec![(DriftKind::MissingInGroup, user(1))]
+        );
+    }
+
+    #[test]
+    fn member_holding_position_is_not_unassigned() {
+        assert_eq!(orca_fix(DriftKind::MissingInGroup, true), OrcaFix::Keep);
+        assert_eq!(
+            orca_fix(DriftKind::MissingInGroup, false),
+            OrcaFix::Unassign
+        );
+    }
+
+    #[test]
+    fn accounts_in_group_are_assigned() {
+        assert_eq!(orca_fix(DriftKind::MissingInOrca, false), OrcaFix::Assign);
+        assert_eq!(orca_fix(DriftKind::GroupMissing, false), OrcaFix::Keep);
+    }
+}
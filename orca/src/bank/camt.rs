@@ -0,0 +1,249 @@
+//! ISO 20022 camt.053 (Bank to Customer Statement) XML
+//!
+//! Every `Ntry` element is one transaction.
+//! Banks put variable symbol into different elements,
+//! we look into creditor reference and end to end id.
+use chrono::NaiveDate;
+use quick_xml::Reader;
+use quick_xml::events::Event;
+
+use super::{Error, Transaction, non_empty, normalize_symbol, parse_amount};
+
+#[derive(Default)]
+struct Entry {
+    id: Option<String>,
+    booked_on: Option<String>,
+    amount: Option<String>,
+    currency: Option<String>,
+    credit: bool,
+    reference: Option<String>,
+    end_to_end_id: Option<String>,
+    account: Option<String>,
+    name: Option<String>,
+    message: Option<String>,
+}
+
+/// Extract variable symbol from values like `VS:123`, `VS123/SS/KS` or plain `123`
+fn variable_symbol(value: &str) -> Option<String> {
+    let value = value.trim();
+    let upper = value.to_ascii_uppercase();
+
+    let symbol = match upper.find("VS") {
+        Some(position) => value[position + 2..]
+            .trim_start_matches([':', ' '])
+            .split(|c: char| !c.is_ascii_digit())
+            .next()
+            .unwrap_or_default(),
+        None => value,
+    };
+
+    normalize_symbol(symbol)
+}
+
+fn ends_with(path: &[String], suffix: &[&str]) -> bool {
+    path.len() >= suffix.len()
+        && path[path.len() - suffix.len()..]
+            .iter()
+            .zip(suffix)
+            .all(|(a, b)| a == b)
+}
+
+impl Entry {
+    fn text(&mut self, path: &[String], text: &str) {
+        let field = if ends_with(path, &["Ntry", "AcctSvcrRef"])
+            || (self.id.is_none() && ends_with(path, &["TxDtls", "Refs", "AcctSvcrRef"]))
+        {
+            &mut self.id
+        } else if ends_with(path, &["BookgDt", "Dt"]) || ends_with(path, &["BookgDt", "DtTm"]) {
+            &mut self.booked_on
+        } else if ends_with(path, &["Ntry", "Amt"]) {
+            &mut self.amount
+        } else if ends_with(path, &["Ntry", "CdtDbtInd"]) {
+            self.credit = text == "CRDT";
+            return;
+        } else if ends_with(path, &["CdtrRefInf", "Ref"]) {
+            &mut self.reference
+        } else if ends_with(path, &["Refs", "EndToEndId"]) {
+            &mut self.end_to_end_id
+        } else if ends_with(path, &["DbtrAcct", "Id", "IBAN"])
+            || ends_with(path, &["DbtrAcct", "Id", "Othr", "Id"])
+        {
+            &mut self.account
+        } else if ends_with(path, &["Dbtr", "Nm"]) || ends_with(path, &["Dbtr", "Pty", "Nm"]) {
+            &mut self.name
+        } else if ends_with(path, &["RmtInf", "Ustrd"]) {
+            &mut self.message
+        } else {
+            return;
+        };
+
+        if field.is_none() {
+            *field = non_empty(text);
+        }
+    }
+
+    fn into_transaction(self, position: usize) -> Result<Transaction, Error> {
+        let invalid = |what: &str| Error::InvalidEntry(position, format!("invalid {what}"));
+
+        let booked_on = self
+            .booked_on
+            .as_deref()
+            .and_then(|date| date.get(..10))
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            .ok_or_else(|| invalid("booking date"))?;
+
+        let amount = self
+            .amount
+            .as_deref()
+            .and_then(parse_amount)
+            .ok_or_else(|| invalid("amount"))?;
+
+        let variable_symbol = self
+            .reference
+            .as_deref()
+            .and_then(variable_symbol)
+            .or_else(|| {
+                self.end_to_end_id
+                    .as_deref()
+                    .filter(|id| *id != "NOTPROVIDED")
+                    .and_then(variable_symbol)
+            });
+
+        Ok(Transaction {
+            id: self.id.ok_or_else(|| invalid("reference"))?,
+            booked_on,
+            amount: if self.credit { amount } else { -amount },
+            currency: self.currency.unwrap_or_else(|| "CZK".to_string()),
+            variable_symbol,
+            counterparty_account: self.account,
+            counterparty_name: self.name,
+            message: self.message,
+        })
+    }
+}
+
+pub(super) fn parse(text: &str) -> Result<Vec<Transaction>, Error> {
+    let mut reader = Reader::from_str(text);
+    reader.config_mut().trim_text(true);
+
+    let mut path: Vec<String> = Vec::new();
+    let mut entry: Option<Entry> = None;
+    let mut transactions = Vec::new();
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) => {
+                let name = String::from_utf8_lossy(element.local_name().as_ref()).into_owned();
+
+                if name == "Ntry" {
+                    entry = Some(Entry::default());
+                }
+
+                if let Some(entry) = entry.as_mut()
+                    && name == "Amt"
+                    && path.last().is_some_and(|parent| parent == "Ntry")
+                {
+                    entry.currency = element
+                        .try_get_attribute("Ccy")
+                        .ok()
+                        .flatten()
+                        .and_then(|attr| attr.unescape_value().ok())
+                        .and_then(|value| non_empty(&value));
+                }
+
+                path.push(name);
+            }
+            Event::Text(value) => {
+                if let Some(entry) = entry.as_mut() {
+                    entry.text(&path, &value.unescape()?);
+                }
+            }
+            Event::End(_) => {
+                if path.pop().as_deref() == Some("Ntry")
+                    && let Some(finished) = entry.take()
+                {
+                    transactions.push(finished.into_transaction(transactions.len() + 1)?);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(transactions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATEMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+  <BkToCstmrStmt>
+    <Stmt>
+      <Ntry>
+        <Amt Ccy="CZK">150.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <BookgDt><Dt>2024-01-05</Dt></BookgDt>
+        <AcctSvcrRef>TX-1</AcctSvcrRef>
+        <NtryDtls><TxDtls>
+          <Refs><EndToEndId>VS0000000042/SS/KS0308</EndToEndId></Refs>
+          <RltdPties>
+            <Dbtr><Nm>Jana Novakova</Nm></Dbtr>
+            <DbtrAcct><Id><IBAN>CZ6508000000192000145399</IBAN></Id></DbtrAcct>
+          </RltdPties>
+          <RmtInf><Ustrd>clenstvi leden</Ustrd></RmtInf>
+        </TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="CZK">20.00</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <BookgDt><Dt>2024-01-06</Dt></BookgDt>
+        <NtryDtls><TxDtls>
+          <Refs><AcctSvcrRef>TX-2</AcctSvcrRef><EndToEndId>NOTPROVIDED</EndToEndId></Refs>
+        </TxDtls></NtryDtls>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>"#;
+
+    #[test]
+    fn parses_entries() {
+        let transactions = parse(STATEMENT).unwrap();
+
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0].id, "TX-1");
+        assert_eq!(transactions[0].amount, 15_000);
+        assert_eq!(transactions[0].variable_symbol.as_deref(), Some("42"));
+        assert_eq!(
+            transactions[0].counterparty_name.as_deref(),
+            Some("Jana Novakova")
+        );
+        assert_eq!(
+            transactions[0].counterparty_account.as_deref(),
+            Some("CZ6508000000192000145399")
+        );
+        assert_eq!(transactions[0].message.as_deref(), Some("clenstvi leden"));
+        assert_eq!(transactions[1].id, "TX-2");
+        assert_eq!(transactions[1].amount, -2_000);
+        assert_eq!(transactions[1].variable_symbol, None);
+    }
+
+    #[test]
+    fn extracts_variable_symbol() {
+        assert_eq!(variable_symbol("VS:123"), Some("123".to_string()));
+        assert_eq!(variable_symbol("vs 0042/SS"), Some("42".to_string()));
+        assert_eq!(variable_symbol("000077"), Some("77".to_string()));
+        assert_eq!(variable_symbol("invoice"), None);
+    }
+
+    #[test]
+    fn reports_invalid_entry_by_its_position() {
+        let statement = STATEMENT.replace("2024-01-06", "06.01.2024");
+
+        assert_eq!(
+            parse(&statement).unwrap_err().to_string(),
+            "Entry 2: invalid booking date"
+        );
+    }
+}
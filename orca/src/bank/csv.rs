@@ -0,0 +1,156 @@
+//! CSV export of transactions
+//!
+//! Header names differ between banks so every column accepts few aliases
+//! (english ones and those used by Czech banks).
+//! Lines before the header (like account summary) are skipped.
+use chrono::NaiveDate;
+use csv::{ReaderBuilder, StringRecord};
+
+use super::{Error, Transaction, non_empty, normalize_symbol, parse_amount};
+
+const ID: &[&str] = &["id", "transaction_id", "id pohybu", "id transakce"];
+const DATE: &[&str] = &["date", "booked_on", "datum", "datum zaúčtování"];
+const AMOUNT: &[&str] = &["amount", "objem", "částka"];
+const CURRENCY: &[&str] = &["currency", "měna"];
+const VARIABLE_SYMBOL: &[&str] = &["variable_symbol", "vs", "variabilní symbol"];
+const ACCOUNT: &[&str] = &["counterparty_account", "account", "protiúčet"];
+const NAME: &[&str] = &["counterparty_name", "name", "název protiúčtu"];
+const MESSAGE: &[&str] = &["message", "zpráva pro příjemce", "poznámka"];
+
+struct Columns {
+    id: usize,
+    date: usize,
+    amount: usize,
+    currency: Option<usize>,
+    variable_symbol: Option<usize>,
+    account: Option<usize>,
+    name: Option<usize>,
+    message: Option<usize>,
+}
+
+fn position(header: &StringRecord, aliases: &[&str]) -> Option<usize> {
+    header.iter().position(|column| {
+        let column = column.trim().to_lowercase();
+        aliases.contains(&column.as_str())
+    })
+}
+
+impl Columns {
+    fn find(header: &StringRecord) -> Option<Self> {
+        Some(Self {
+            id: position(header, ID)?,
+            date: position(header, DATE)?,
+            amount: position(header, AMOUNT)?,
+            currency: position(header, CURRENCY),
+            variable_symbol: position(header, VARIABLE_SYMBOL),
+            account: position(header, ACCOUNT),
+            name: position(header, NAME),
+            message: position(header, MESSAGE),
+        })
+    }
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%d.%m.%Y"))
+        .ok()
+}
+
+fn delimiter(text: &str) -> u8 {
+    let first_line = text.lines().next().unwrap_or_default();
+    if first_line.matches(';').count() > first_line.matches(',').count() {
+        b';'
+    } else {
+        b','
+    }
+}
+
+pub(super) fn parse(text: &str) -> Result<Vec<Transaction>, Error> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(delimiter(text))
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+
+    let mut columns: Option<Columns> = None;
+    let mut transactions = Vec::new();
+
+    for (index, record) in reader.records().enumerate() {
+        let record = record?;
+        let line = index + 1;
+
+        let Some(cols) = &columns else {
+            columns = Columns::find(&record);
+            continue;
+        };
+
+        let field = |i: usize| record.get(i).unwrap_or_default();
+        let optional = |i: Option<usize>| i.and_then(|i| non_empty(field(i)));
+
+        // Empty lines or summaries after the transactions
+        let Some(id) = non_empty(field(cols.id)) else {
+            continue;
+        };
+
+        transactions.push(Transaction {
+            id,
+            booked_on: parse_date(field(cols.date)).ok_or_else(|| {
+                Error::Invalid(line, format!("invalid date {}", field(cols.date)))
+            })?,
+            amount: parse_amount(field(cols.amount)).ok_or_else(|| {
+                Error::Invalid(line, format!("invalid amount {}", field(cols.amount)))
+            })?,
+            currency: optional(cols.currency).unwrap_or_else(|| "CZK".to_string()),
+            variable_symbol: cols
+                .variable_symbol
+                .and_then(|i| normalize_symbol(field(i))),
+            counterparty_account: optional(cols.account),
+            counterparty_name: optional(cols.name),
+            message: optional(cols.message),
+        });
+    }
+
+    if columns.is_none() {
+        return Err(Error::MissingColumn("id, date or amount"));
+    }
+
+    Ok(transactions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_czech_export_with_preamble() {
+        let text = "\
+\"Číslo účtu\";\"2000000000/2010\"
+\"ID pohybu\";\"Datum\";\"Objem\";\"Měna\";\"Protiúčet\";\"Název protiúčtu\";\"VS\";\"Zpráva pro příjemce\"
+\"1001\";\"05.01.2024\";\"150,00\";\"CZK\";\"123/0100\";\"Jana Nováková\";\"0042\";\"členství; leden\"
+\"1002\";\"06.01.2024\";\"-20,00\";\"CZK\";\"\";\"\";\"\";\"poplatek\"
+";
+
+        let transactions = parse(text).unwrap();
+
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0].id, "1001");
+        assert_eq!(
+            transactions[0].booked_on,
+            NaiveDate::from_ymd_opt(2024, 1, 5).unwrap()
+        );
+        assert_eq!(transactions[0].amount, 15_000);
+        assert_eq!(transactions[0].variable_symbol.as_deref(), Some("42"));
+        assert_eq!(transactions[0].message.as_deref(), Some("členství; leden"));
+        assert_eq!(transactions[1].amount, -2_000);
+        assert_eq!(transactions[1].variable_symbol, None);
+    }
+
+    #[test]
+    fn requires_header() {
+        assert!(matches!(
+            parse("a,b,c\n1,2,3\n"),
+            Err(Error::MissingColumn(_))
+        ));
+    }
+}
@@ -0,0 +1,152 @@
+//! GPC (ABO) format used by Czech banks
+//!
+//! Fixed width lines. `074` line is statement header,
+//! `075` lines are transactions optionally followed by `078` and `079`
+//! lines with message for the recipient.
+use chrono::NaiveDate;
+
+use super::{Error, Transaction, non_empty, normalize_symbol};
+
+/// Characters on 1-based inclusive positions
+fn slice(chars: &[char], from: usize, to: usize) -> String {
+    chars
+        .get(from - 1..to.min(chars.len()))
+        .map(|c| c.iter().collect())
+        .unwrap_or_default()
+}
+
+/// ISO 4217 numeric code with leading zero.
+/// Unknown codes are kept as they are so such transactions
+/// never match dues and wait for review instead.
+/// Statements in the older format without currency are in CZK.
+fn currency(code: &str) -> String {
+    match code.trim().trim_start_matches('0') {
+        "" | "203" => "CZK".to_string(),
+        "978" => "EUR".to_string(),
+        "840" => "USD".to_string(),
+        "826" => "GBP".to_string(),
+        code => code.to_string(),
+    }
+}
+
+fn parse_transaction(chars: &[char], line: usize) -> Result<Transaction, Error> {
+    let invalid = |what: &str| Error::Invalid(line, format!("invalid {what}"));
+
+    let amount: i64 = slice(chars, 49, 60)
+        .trim()
+        .parse()
+        .map_err(|_err| invalid("amount"))?;
+
+    // 1 debit, 2 credit, 4 cancelled debit, 5 cancelled credit
+    let amount = match slice(chars, 61, 61).as_str() {
+        "2" | "4" => amount,
+        "1" | "5" => -amount,
+        _ => return Err(invalid("accounting code")),
+    };
+
+    let booked_on = NaiveDate::parse_from_str(&slice(chars, 92, 97), "%d%m%y")
+        .map_err(|_err| invalid("date"))?;
+
+    let counterparty_account = slice(chars, 20, 35);
+    let bank_code = slice(chars, 74, 77);
+    let counterparty_account = normalize_symbol(&counterparty_account)
+        .map(|account| format!("{account}/{}", bank_code.trim()));
+
+    Ok(Transaction {
+        id: slice(chars, 36, 48).trim().to_string(),
+        booked_on,
+        amount,
+        currency: currency(&slice(chars, 119, 122)),
+        variable_symbol: normalize_symbol(&slice(chars, 62, 71)),
+        counterparty_account,
+        counterparty_name: non_empty(&slice(chars, 98, 117)),
+        message: None,
+    })
+}
+
+pub(super) fn parse(text: &str) -> Result<Vec<Transaction>, Error> {
+    let mut transactions: Vec<Transaction> = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let chars: Vec<char> = line.chars().collect();
+
+        match slice(&chars, 1, 3).as_str() {
+            "075" => transactions.push(parse_transaction(&chars, index + 1)?),
+            "078" | "079" => {
+                let text = non_empty(&chars.iter().skip(3).collect::<String>());
+                if let (Some(last), Some(text)) = (transactions.last_mut(), text) {
+                    last.message = Some(match last.message.take() {
+                        Some(message) => format!("{message} {text}"),
+                        None => text,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(transactions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction_line(accounting: char, vs: &str) -> String {
+        format!(
+            "075{:0>16}{:0>16}{:0>13}{:0>12}{accounting}{vs:0>10}00{:0>4}{:0>4}{:0>10}{}{:<20}0{:0>4}{}",
+            "2000000000",
+            "123",
+            "9876",
+            "15000",
+            "0100",
+            "0308",
+            "0",
+            "050124",
+            "NOVAKOVA JANA",
+            "203",
+            "050124"
+        )
+    }
+
+    #[test]
+    fn parses_transactions_with_messages() {
+        let text = format!(
+            "0740000002000000000UNIE                01012400000000000000+00000000000000+000000000000000000000000000000000000000031012400\n{}\n078clenstvi leden\n{}\n",
+            transaction_line('2', "42"),
+            transaction_line('1', "")
+        );
+
+        let transactions = parse(&text).unwrap();
+
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0].id, "0000000009876");
+        assert_eq!(transactions[0].amount, 15_000);
+        assert_eq!(transactions[0].variable_symbol.as_deref(), Some("42"));
+        assert_eq!(
+            transactions[0].counterparty_account.as_deref(),
+            Some("123/0100")
+        );
+        assert_eq!(
+            transactions[0].counterparty_name.as_deref(),
+            Some("NOVAKOVA JANA")
+        );
+        assert_eq!(transactions[0].message.as_deref(), Some("clenstvi leden"));
+        assert_eq!(transactions[0].currency, "CZK");
+        assert_eq!(
+            transactions[0].booked_on,
+            NaiveDate::from_ymd_opt(2024, 1, 5).unwrap()
+        );
+        assert_eq!(transactions[1].amount, -15_000);
+        assert_eq!(transactions[1].variable_symbol, None);
+    }
+
+    #[test]
+    fn keeps_unknown_currency_codes() {
+        assert_eq!(currency("0203"), "CZK");
+        assert_eq!(currency("0978"), "EUR");
+        assert_eq!(currency("0826"), "GBP");
+        assert_eq!(currency("0756"), "756");
+        assert_eq!(currency("    "), "CZK");
+    }
+}
@@ -0,0 +1,237 @@
+//! Parsing of bank statements
+//!
+//! Treasurers download statements from internet banking
+//! in one of the supported formats. We only care about incoming
+//! transactions which are matched to members by variable symbol.
+use chrono::NaiveDate;
+use encoding_rs::WINDOWS_1250;
+use rocket::FromFormField;
+use serde::Deserialize;
+use thiserror::Error;
+
+mod camt;
+mod csv;
+mod gpc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, FromFormField)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Csv,
+    Gpc,
+    Camt053,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("CSV error: {0}")]
+    Csv(#[from] ::csv::Error),
+    #[error("XML error: {0}")]
+    Xml(#[from] quick_xml::Error),
+    #[error("Line {0}: {1}")]
+    Invalid(usize, String),
+    #[error("Entry {0}: {1}")]
+    InvalidEntry(usize, String),
+    #[error("Missing column {0}")]
+    MissingColumn(&'static str),
+}
+
+/// Transaction from bank statement.
+/// Amount is in minor units (hundredths), negative for outgoing transactions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    pub id: String,
+    pub booked_on: NaiveDate,
+    pub amount: i64,
+    pub currency: String,
+    pub variable_symbol: Option<String>,
+    pub counterparty_account: Option<String>,
+    pub counterparty_name: Option<String>,
+    pub message: Option<String>,
+}
+
+/// Banks still like to export in windows-1250
+fn decode(data: &[u8]) -> String {
+    match std::str::from_utf8(data) {
+        Ok(text) => text.trim_start_matches('\u{feff}').to_string(),
+        Err(_) => WINDOWS_1250.decode(data).0.into_owned(),
+    }
+}
+
+/// Guess format from content of the file
+fn detect(text: &str) -> Format {
+    let start = text.trim_start();
+    if start.starts_with("074") {
+        Format::Gpc
+    } else if start.starts_with('<') {
+        Format::Camt053
+    } else {
+        Format::Csv
+    }
+}
+
+/// Parse statement, detecting the format when not given
+///
+/// # Errors
+///
+/// When statement is not valid in the given format
+pub fn parse(format: Option<Format>, data: &[u8]) -> Result<Vec<Transaction>, Error> {
+    let text = decode(data);
+
+    match format.unwrap_or_else(|| detect(&text)) {
+        Format::Csv => csv::parse(&text),
+        Format::Gpc => gpc::parse(&text),
+        Format::Camt053 => camt::parse(&text),
+    }
+}
+
+/// Parse decimal amount like `1 234,50` or `-150.5` into minor units,
+/// `None` when it doesn't fit
+fn parse_amount(value: &str) -> Option<i64> {
+    let cleaned: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '\'')
+        .map(|c| if c == ',' { '.' } else { c })
+        .collect();
+
+    let (negative, digits) = match cleaned.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, cleaned.strip_prefix('+').unwrap_or(&cleaned)),
+    };
+
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if whole.is_empty() || fraction.len() > 2 {
+        return None;
+    }
+
+    let whole: i64 = whole.parse().ok()?;
+    let fraction: i64 = format!("{fraction:0<2}").parse().ok()?;
+    let amount = whole.checked_mul(100)?.checked_add(fraction)?;
+
+    Some(if negative { -amount } else { amount })
+}
+
+/// Variable symbol without leading zeros, `None` if empty
+fn normalize_symbol(value: &str) -> Option<String> {
+    let trimmed = value.trim().trim_start_matches('0');
+    if trimmed.is_empty() || !trimmed.chars().all(|c| c.is_ascii_digit()) {
+        None
+    } else {
+        Some(trimmed.to_string())
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_string())
+    }
+}
+
+/// Result of matching transaction to member
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Match<T> {
+    Member(T),
+    Unmatched,
+    Ambiguous,
+}
+
+/// Match incoming transaction to member by variable symbol.
+///
+/// `find` looks up member by member number returning
+/// the member together with flag whether it's current member.
+/// Transactions in other currency than `currency`, or pointing
+/// to former member need to be reviewed by a human.
+pub fn match_member<T>(
+    transaction: &Transaction,
+    currency: &str,
+    find: impl Fn(i32) -> Option<(T, bool)>,
+) -> Match<T> {
+    let Some(symbol) = &transaction.variable_symbol else {
+        return Match::Unmatched;
+    };
+
+    let Some((member, current)) = symbol.parse().ok().and_then(find) else {
+        return Match::Unmatched;
+    };
+
+    if current && transaction.currency.eq_ignore_ascii_case(currency) {
+        Match::Member(member)
+    } else {
+        Match::Ambiguous
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(variable_symbol: Option<&str>, currency: &str) -> Transaction {
+        Transaction {
+            id: "1".to_string(),
+            booked_on: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
+            amount: 15_000,
+            currency: currency.to_string(),
+            variable_symbol: variable_symbol.map(ToString::to_string),
+            counterparty_account: None,
+            counterparty_name: None,
+            message: None,
+        }
+    }
+
+    #[test]
+    fn matches_members_by_variable_symbol() {
+        // member 1 is current, member 2 left
+        let find = |number: i32| match number {
+            1 => Some(("current", true)),
+            2 => Some(("former", false)),
+            _ => None,
+        };
+
+        assert_eq!(
+            match_member(&transaction(Some("1"), "CZK"), "CZK", find),
+            Match::Member("current")
+        );
+        assert_eq!(
+            match_member(&transaction(Some("2"), "CZK"), "CZK", find),
+            Match::Ambiguous
+        );
+        assert_eq!(
+            match_member(&transaction(Some("1"), "EUR"), "CZK", find),
+            Match::Ambiguous
+        );
+        assert_eq!(
+            match_member(&transaction(Some("3"), "CZK"), "CZK", find),
+            Match::Unmatched
+        );
+        assert_eq!(
+            match_member(&transaction(None, "CZK"), "CZK", find),
+            Match::Unmatched
+        );
+    }
+
+    #[test]
+    fn parses_amounts() {
+        assert_eq!(parse_amount("150"), Some(15_000));
+        assert_eq!(parse_amount("1 234,5"), Some(123_450));
+        assert_eq!(parse_amount("-99.99"), Some(-9_999));
+        assert_eq!(parse_amount("1.234"), None);
+        assert_eq!(parse_amount("abc"), None);
+        assert_eq!(parse_amount("92233720368547758,08"), None);
+    }
+
+    #[test]
+    fn normalizes_symbols() {
+        assert_eq!(normalize_symbol("0000000042"), Some("42".to_string()));
+        assert_eq!(normalize_symbol("0000000000"), None);
+        assert_eq!(normalize_symbol("12a"), None);
+    }
+
+    #[test]
+    fn detects_format() {
+        assert_eq!(detect("0740000002001234567..."), Format::Gpc);
+        assert_eq!(detect("<?xml version=\"1.0\"?><Document>"), Format::Camt053);
+        assert_eq!(detect("date;amount;vs"), Format::Csv);
+    }
+}
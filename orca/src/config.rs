@@ -5,7 +5,10 @@ use rocket::figment::{
 
 use std::collections::HashMap;
 
+use self::listmonk::Routing;
 use self::templates::Templates;
+use crate::dues::ReminderLevel;
+pub mod listmonk;
 pub mod templates;
 
 #[derive(Debug, Clone)]
@@ -22,8 +25,30 @@ pub struct Config {
     pub tex_exe: String,
     pub processing_queue_size: usize,
     pub verify_redirects_to: HashMap<String, String>,
+    pub application_status_pages: HashMap<String, String>,
     pub notification_email: Option<String>,
     pub email_confirmation_subjects: HashMap<String, String>,
+    pub info_request_subjects: HashMap<String, String>,
+    pub welcome_email_subjects: HashMap<String, String>,
+    pub email_change_confirmation_subjects: HashMap<String, String>,
+    pub email_change_notification_subjects: HashMap<String, String>,
+    /// Pages member is redirected to after confirming new email
+    pub email_change_redirects_to: HashMap<String, String>,
+    pub dues_reminder_subjects: HashMap<String, String>,
+    pub dues_second_reminder_subjects: HashMap<String, String>,
+    pub dues_final_reminder_subjects: HashMap<String, String>,
+    /// How often (in seconds) members in arrears are reminded. 0 (the default) disables
+    /// automatic reminders, arrears are only reliable once payments are imported since joining
+    pub dues_reminder_interval: u64,
+    /// Members owing fewer monthly fees are not reminded automatically
+    pub dues_reminder_min_months: i64,
+    /// Days between reminders of increasing level
+    pub dues_reminder_escalation_days: u64,
+    /// IBAN (optionally followed by `+BIC`) of account for payments of dues.
+    /// Payment QR codes are not generated when missing.
+    pub payment_account: Option<String>,
+    /// Message for recipient included in payment QR codes
+    pub payment_message: Option<String>,
     /// This is rocket level value, not logger one
     pub log_level: rocket::config::LogLevel,
     pub smtp_host: String,
@@ -32,10 +57,33 @@ pub struct Config {
     pub keycloak_host: Option<String>,
     pub keycloak_realm: Option<String>,
     pub keycloak_client_id: Option<String>,
+    pub keycloak_client_secret: Option<String>,
     pub templates: Templates<'static>,
     pub listmonk_password: Option<String>,
     pub listmonk_username: Option<String>,
     pub listmonk_host: Option<String>,
+    pub listmonk_routing: Routing,
+    /// How often (in seconds) subscription statuses are pulled from Listmonk. 0 disables pulling
+    pub listmonk_sync_interval: u64,
+    /// How many times subscribing of new member to Listmonk
+    /// or pushing changes of member to it is attempted
+    pub listmonk_subscribe_attempts: u32,
+    /// Delay (in seconds) before first retry of failed Listmonk subscription, doubled with every attempt
+    pub listmonk_retry_delay: u64,
+    /// How often (in seconds) Keycloak groups of workplaces are compared with their members.
+    /// Differences are only logged. 0 disables the check
+    pub workplace_groups_sync_interval: u64,
+}
+
+/// Missing routing falls back to default but invalid one should fail at startup
+fn listmonk_routing(figment: &Figment) -> Routing {
+    if figment.contains("listmonk_routing") {
+        figment
+            .extract_inner("listmonk_routing")
+            .expect("listmonk_routing is not valid")
+    } else {
+        Routing::default()
+    }
 }
 
 impl Config {
@@ -45,6 +93,7 @@ impl Config {
     ///
     /// If one or more required configuration fields aren't present. It's pointless to continue execution.
     #[must_use]
+    #[expect(clippy::too_many_lines, reason = "reads every configuration key")]
     pub fn get() -> Self {
         let figment = Figment::from(rocket::Config::default())
             .merge(Serialized::defaults(rocket::Config::default()))
@@ -84,12 +133,59 @@ impl Config {
             .extract_inner("verify_redirects_to")
             .unwrap_or_default();
 
+        let application_status_pages = figment
+            .extract_inner("application_status_pages")
+            .unwrap_or_default();
+
         let notification_email = figment.extract_inner("notification_email").ok();
 
         let email_confirmation_subjects: HashMap<String, String> = figment
             .extract_inner("email_confirmation_subjects")
             .unwrap_or_default();
 
+        let info_request_subjects: HashMap<String, String> = figment
+            .extract_inner("info_request_subjects")
+            .unwrap_or_default();
+
+        let welcome_email_subjects: HashMap<String, String> = figment
+            .extract_inner("welcome_email_subjects")
+            .unwrap_or_default();
+
+        let email_change_confirmation_subjects: HashMap<String, String> = figment
+            .extract_inner("email_change_confirmation_subjects")
+            .unwrap_or_default();
+
+        let email_change_notification_subjects: HashMap<String, String> = figment
+            .extract_inner("email_change_notification_subjects")
+            .unwrap_or_default();
+
+        let email_change_redirects_to = figment
+            .extract_inner("email_change_redirects_to")
+            .unwrap_or_default();
+
+        let dues_reminder_subjects: HashMap<String, String> = figment
+            .extract_inner("dues_reminder_subjects")
+            .unwrap_or_default();
+
+        let dues_second_reminder_subjects: HashMap<String, String> = figment
+            .extract_inner("dues_second_reminder_subjects")
+            .unwrap_or_default();
+
+        let dues_final_reminder_subjects: HashMap<String, String> = figment
+            .extract_inner("dues_final_reminder_subjects")
+            .unwrap_or_default();
+
+        let dues_reminder_interval = figment.extract_inner("dues_reminder_interval").unwrap_or(0);
+        let dues_reminder_min_months = figment
+            .extract_inner("dues_reminder_min_months")
+            .unwrap_or(2);
+        let dues_reminder_escalation_days = figment
+            .extract_inner("dues_reminder_escalation_days")
+            .unwrap_or(14);
+
+        let payment_account = figment.extract_inner("payment_account").ok();
+        let payment_message = figment.extract_inner("payment_message").ok();
+
         let log_level = figment
             .extract_inner("log_level")
             .unwrap_or(rocket::config::LogLevel::Normal);
@@ -119,12 +215,31 @@ impl Config {
 
         let keycloak_client_id: Option<String> = figment.extract_inner("keycloak_client_id").ok();
 
+        // Empty secret in example config means it isn't configured
+        let keycloak_client_secret: Option<String> = figment
+            .extract_inner("keycloak_client_secret")
+            .ok()
+            .filter(|secret: &String| !secret.is_empty());
+
         let listmonk_password: Option<String> = figment.extract_inner("listmonk_password").ok();
 
         let listmonk_username: Option<String> = figment.extract_inner("listmonk_username").ok();
 
         let listmonk_host: Option<String> = figment.extract_inner("listmonk_host").ok();
 
+        let listmonk_routing = listmonk_routing(&figment);
+
+        let listmonk_sync_interval = figment
+            .extract_inner("listmonk_sync_interval")
+            .unwrap_or(3600);
+        let workplace_groups_sync_interval = figment
+            .extract_inner("workplace_groups_sync_interval")
+            .unwrap_or(0);
+        let listmonk_subscribe_attempts = figment
+            .extract_inner("listmonk_subscribe_attempts")
+            .unwrap_or(5);
+        let listmonk_retry_delay = figment.extract_inner("listmonk_retry_delay").unwrap_or(60);
+
         Self {
             email_sender_email,
             email_sender_name,
@@ -138,8 +253,22 @@ impl Config {
             tex_exe,
             processing_queue_size,
             verify_redirects_to,
+            application_status_pages,
             notification_email,
             email_confirmation_subjects,
+            info_request_subjects,
+            welcome_email_subjects,
+            email_change_confirmation_subjects,
+            email_change_notification_subjects,
+            email_change_redirects_to,
+            dues_reminder_subjects,
+            dues_second_reminder_subjects,
+            dues_final_reminder_subjects,
+            dues_reminder_interval,
+            dues_reminder_min_months,
+            dues_reminder_escalation_days,
+            payment_account,
+            payment_message,
             log_level,
             smtp_host,
             smtp_user,
@@ -147,10 +276,16 @@ impl Config {
             keycloak_host,
             keycloak_realm,
             keycloak_client_id,
+            keycloak_client_secret,
             templates,
             listmonk_password,
             listmonk_username,
             listmonk_host,
+            listmonk_routing,
+            listmonk_sync_interval,
+            listmonk_subscribe_attempts,
+            listmonk_retry_delay,
+            workplace_groups_sync_interval,
         }
     }
 
@@ -166,6 +301,21 @@ impl Config {
         }
     }
 
+    /// Link applicant can use to check status of their application.
+    /// Status token is passed as `token` query parameter to the page.
+    /// When no page is configured link points directly to the API.
+    #[must_use]
+    pub fn application_status_link_for_local(&self, lang: &str, token: &str) -> String {
+        match self
+            .application_status_pages
+            .get(lang)
+            .or_else(|| self.application_status_pages.get("default"))
+        {
+            Some(url) => format!("{url}?token={token}"),
+            None => format!("{}/registration/{token}/status", self.host),
+        }
+    }
+
     #[must_use]
     pub fn email_confirmation_subject_for_local(&self, lang: &str) -> String {
         match self.email_confirmation_subjects.get(lang) {
@@ -177,4 +327,87 @@ impl Config {
                 .clone(),
         }
     }
+
+    #[must_use]
+    pub fn info_request_subject_for_local(&self, lang: &str) -> String {
+        match self.info_request_subjects.get(lang) {
+            Some(sub) => sub.clone(),
+            None => self
+                .info_request_subjects
+                .get("default")
+                .unwrap_or(&"Question About Your Application".to_string())
+                .clone(),
+        }
+    }
+
+    #[must_use]
+    pub fn welcome_email_subject_for_local(&self, lang: &str) -> String {
+        match self.welcome_email_subjects.get(lang) {
+            Some(sub) => sub.clone(),
+            None => self
+                .welcome_email_subjects
+                .get("default")
+                .unwrap_or(&"Welcome to the Union".to_string())
+                .clone(),
+        }
+    }
+
+    #[must_use]
+    pub fn email_change_confirmation_subject_for_local(&self, lang: &str) -> String {
+        match self.email_change_confirmation_subjects.get(lang) {
+            Some(sub) => sub.clone(),
+            None => self
+                .email_change_confirmation_subjects
+                .get("default")
+                .unwrap_or(&"Confirm Your New Email Address".to_string())
+                .clone(),
+        }
+    }
+
+    #[must_use]
+    pub fn email_change_notification_subject_for_local(&self, lang: &str) -> String {
+        match self.email_change_notification_subjects.get(lang) {
+            Some(sub) => sub.clone(),
+            None => self
+                .email_change_notification_subjects
+                .get("default")
+                .unwrap_or(&"Your Email Address Was Changed".to_string())
+                .clone(),
+        }
+    }
+
+    /// Falls back to the admin interface when no page is configured
+    #[must_use]
+    pub fn email_change_redirect_for_local(&self, lang: &str) -> String {
+        self.email_change_redirects_to
+            .get(lang)
+            .or_else(|| self.email_change_redirects_to.get("default"))
+            .or(self.admin_host.as_ref())
+            .unwrap_or(&self.host)
+            .clone()
+    }
+
+    #[must_use]
+    pub(crate) fn dues_reminder_subject_for_local(
+        &self,
+        level: ReminderLevel,
+        lang: &str,
+    ) -> String {
+        let (subjects, default) = match level {
+            ReminderLevel::First => (&self.dues_reminder_subjects, "Membership Dues Reminder"),
+            ReminderLevel::Second => (
+                &self.dues_second_reminder_subjects,
+                "Second Membership Dues Reminder",
+            ),
+            ReminderLevel::Final => (
+                &self.dues_final_reminder_subjects,
+                "Final Notice: Unpaid Membership Dues",
+            ),
+        };
+
+        subjects
+            .get(lang)
+            .or_else(|| subjects.get("default"))
+            .map_or_else(|| default.to_string(), Clone::clone)
+    }
 }
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Rule assigning Listmonk lists to subscribers.
+///
+/// All conditions which are not empty must match for rule to apply.
+/// Each condition matches if any of its values does.
+/// Rule without any condition applies to everyone.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Rule {
+    pub lists: Vec<u32>,
+    /// Matches if city contains any of the values (case insensitive)
+    pub cities: Vec<String>,
+    pub postal_code_prefixes: Vec<String>,
+    /// ISO 639-1 language codes
+    pub languages: Vec<String>,
+    pub workplaces: Vec<Uuid>,
+}
+
+/// Data about subscriber used for picking the lists
+#[derive(Debug, Default)]
+pub struct Subscriber<'a> {
+    pub city: Option<&'a str>,
+    pub postal_code: Option<&'a str>,
+    pub language: Option<&'a str>,
+    pub workplaces: &'a [Uuid],
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Routing {
+    #[serde(default)]
+    rules: Vec<Rule>,
+    /// Lists used when no rule matches
+    #[serde(default)]
+    fallback: Vec<u32>,
+}
+
+impl Default for Routing {
+    /// Routing we used before it was configurable:
+    /// 5 is the main list, 4 is Prague and 3 is Brno
+    fn default() -> Self {
+        Self {
+            rules: vec![
+                Rule {
+                    lists: vec![5],
+                    ..Rule::default()
+                },
+                Rule {
+                    lists: vec![4],
+                    cities: vec!["prague".to_string(), "praha".to_string()],
+                    ..Rule::default()
+                },
+                Rule {
+                    lists: vec![3],
+                    cities: vec!["brno".to_string()],
+                    ..Rule::default()
+                },
+            ],
+            fallback: vec![5],
+        }
+    }
+}
+
+fn matches_any<T>(values: &[T], pred: impl Fn(&T) -> bool) -> bool {
+    values.is_empty() || values.iter().any(pred)
+}
+
+impl Rule {
+    fn matches(&self, subscriber: &Subscriber) -> bool {
+        let city = subscriber.city.map(str::to_lowercase);
+        let postal_code = subscriber
+            .postal_code
+            .map(|code| code.replace(char::is_whitespace, ""));
+
+        matches_any(&self.cities, |c| {
+            city.as_deref()
+                .is_some_and(|city| city.contains(&c.to_lowercase()))
+        }) && matches_any(&self.postal_code_prefixes, |prefix| {
+            postal_code
+                .as_deref()
+                .is_some_and(|code| code.starts_with(prefix.as_str()))
+        }) && matches_any(&self.languages, |lang| {
+            subscriber
+                .language
+                .is_some_and(|l| l.eq_ignore_ascii_case(lang))
+        }) && matches_any(&self.workplaces, |id| subscriber.workplaces.contains(id))
+    }
+}
+
+impl Routing {
+    /// Sorted list of Listmonk list ids subscriber belongs to
+    #[must_use]
+    pub fn lists_for(&self, subscriber: &Subscriber) -> Vec<u32> {
+        let mut lists: Vec<u32> = self
+            .rules
+            .iter()
+            .filter(|rule| rule.matches(subscriber))
+            .flat_map(|rule| rule.lists.iter().copied())
+            .collect();
+
+        if lists.is_empty() {
+            lists.clone_from(&self.fallback);
+        }
+
+        lists.sort_unstable();
+        lists.dedup();
+        lists
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_routes_prague() {
+        let subscriber = Subscriber {
+            city: Some("Praha 5"),
+            ..Subscriber::default()
+        };
+
+        assert_eq!(Routing::default().lists_for(&subscriber), vec![4, 5]);
+    }
+
+    #[test]
+    fn default_routes_brno() {
+        let subscriber = Subscriber {
+            city: Some("BRNO"),
+            ..Subscriber::default()
+        };
+
+        assert_eq!(Routing::default().lists_for(&subscriber), vec![3, 5]);
+    }
+
+    #[test]
+    fn missing_city_does_not_panic() {
+        assert_eq!(
+            Routing::default().lists_for(&Subscriber::default()),
+            vec![5]
+        );
+    }
+
+    #[test]
+    fn all_conditions_must_match() {
+        let routing = Routing {
+            rules: vec![Rule {
+                lists: vec![7],
+                postal_code_prefixes: vec!["60".to_string()],
+                languages: vec!["en".to_string()],
+                ..Rule::default()
+            }],
+            fallback: vec![1],
+        };
+
+        let english = Subscriber {
+            postal_code: Some("602 00"),
+            language: Some("en"),
+            ..Subscriber::default()
+        };
+        let czech = Subscriber {
+            postal_code: Some("602 00"),
+            language: Some("cs"),
+            ..Subscriber::default()
+        };
+
+        assert_eq!(routing.lists_for(&english), vec![7]);
+        assert_eq!(routing.lists_for(&czech), vec![1]);
+    }
+
+    #[test]
+    fn routes_by_workplace() {
+        let workplace = Uuid::new_v4();
+        let routing = Routing {
+            rules: vec![Rule {
+                lists: vec![9],
+                workplaces: vec![workplace],
+                ..Rule::default()
+            }],
+            fallback: Vec::new(),
+        };
+
+        let member = Subscriber {
+            workplaces: &[workplace],
+            ..Subscriber::default()
+        };
+
+        assert_eq!(routing.lists_for(&member), vec![9]);
+        assert!(routing.lists_for(&Subscriber::default()).is_empty());
+    }
+}
@@ -15,6 +15,32 @@ pub const TREASURER_NOTIFICATION: Template = Template {
     name: "treasurer_notification",
 };
 
+pub const INFO_REQUEST: Template = Template {
+    name: "info_request",
+};
+
+pub const WELCOME: Template = Template { name: "welcome" };
+
+pub const EMAIL_CHANGE: Template = Template {
+    name: "email_change",
+};
+
+pub const EMAIL_CHANGED: Template = Template {
+    name: "email_changed",
+};
+
+pub const DUES_REMINDER: Template = Template {
+    name: "dues_reminder",
+};
+
+pub const DUES_SECOND_REMINDER: Template = Template {
+    name: "dues_second_reminder",
+};
+
+pub const DUES_FINAL_REMINDER: Template = Template {
+    name: "dues_final_reminder",
+};
+
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub struct Template {
     name: &'static str,
@@ -86,6 +112,13 @@ impl<'a> Templates<'a> {
         self.load_template(path, &EMAIL_VERIFICATION)?;
         self.load_template(path, &NEW_APPLICATION_NOTICE)?;
         self.load_template(path, &TREASURER_NOTIFICATION)?;
+        self.load_template(path, &INFO_REQUEST)?;
+        self.load_template(path, &WELCOME)?;
+        self.load_template(path, &EMAIL_CHANGE)?;
+        self.load_template(path, &EMAIL_CHANGED)?;
+        self.load_template(path, &DUES_REMINDER)?;
+        self.load_template(path, &DUES_SECOND_REMINDER)?;
+        self.load_template(path, &DUES_FINAL_REMINDER)?;
         Ok(())
     }
 
@@ -1,10 +1,13 @@
+use rocket::form::{self, FromFormField, ValueField};
 use rocket::serde::Deserializer;
 use rocket::{request::FromParam, serde::Serialize};
 use serde::Deserialize;
 use sqlx::encode::IsNull;
+use sqlx::postgres::{PgHasArrayType, PgTypeInfo};
 use sqlx::{Decode, Encode, Postgres, Type};
 use std::error::Error;
 use std::fmt::Display;
+use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
 use uuid::Uuid;
 
@@ -19,6 +22,20 @@ impl<T> From<Uuid> for Id<T> {
     }
 }
 
+impl<T> PartialEq for Id<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T> Eq for Id<T> {}
+
+impl<T> Hash for Id<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
 impl<T> Display for Id<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
         self.0.fmt(f)
@@ -31,6 +48,12 @@ impl<T> Type<Postgres> for Id<T> {
     }
 }
 
+impl<T> PgHasArrayType for Id<T> {
+    fn array_type_info() -> PgTypeInfo {
+        <Uuid as PgHasArrayType>::array_type_info()
+    }
+}
+
 impl<T> Serialize for Id<T> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -49,6 +72,14 @@ impl<'a, T> FromParam<'a> for Id<T> {
     }
 }
 
+impl<'v, T: Send> FromFormField<'v> for Id<T> {
+    fn from_value(field: ValueField<'v>) -> form::Result<'v, Self> {
+        let uuid =
+            Uuid::parse_str(field.value).map_err(|err| form::Error::validation(err.to_string()))?;
+        Ok(Id(uuid, PhantomData))
+    }
+}
+
 // This nees to be implemented specifically for Postgres
 // because not all db drivers implement decoding for i32.
 // `'r` is the lifetime of the `Row` being decoded
@@ -93,8 +124,43 @@ pub struct Member;
 pub struct Workplace;
 
 #[derive(Debug, Clone, Copy)]
+pub struct InfoRequest;
+
+#[derive(Debug, Clone, Copy)]
+pub struct FeeSchedule;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Payment;
+
+#[derive(Debug, Clone, Copy)]
+pub struct BankTransaction;
+
+#[derive(Debug, Clone, Copy)]
+pub struct DuesReminder;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ProfileChangeRequest;
+
+#[derive(Debug, Clone, Copy)]
+pub struct EmailChange;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Company;
+
+#[derive(Debug, Clone, Copy)]
+pub struct WorkplacePosition;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct MemberNumber(i32);
 
+impl MemberNumber {
+    /// Number following this one, `None` for the highest possible number
+    #[must_use]
+    pub fn next(self) -> Option<Self> {
+        self.0.checked_add(1).map(Self)
+    }
+}
+
 impl Display for MemberNumber {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         self.0.fmt(f)
@@ -0,0 +1,288 @@
+//! Computation of membership dues
+//!
+//! Dues are paid monthly. Fee of each month is given by fee schedule
+//! valid on the first day of that month.
+//! All amounts are in minor units (hundredths) of currency
+//! while incomes are in whole units.
+use chrono::{Datelike, Days, Months, NaiveDate};
+
+pub(crate) mod query;
+pub(crate) mod status;
+
+/// Fee for members with monthly income of at least `income_from`
+#[derive(Debug, Clone)]
+pub struct Band {
+    pub income_from: i64,
+    pub amount: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    pub valid_from: NaiveDate,
+    pub flat_amount: Option<i64>,
+    pub bands: Vec<Band>,
+}
+
+impl Schedule {
+    /// Schedule with bands uses band with highest `income_from` not exceeding the income.
+    /// Members who haven't declared their income pay the lowest band.
+    #[must_use]
+    pub fn monthly_fee(&self, income: Option<i64>) -> i64 {
+        let lowest = self.bands.iter().min_by_key(|band| band.income_from);
+
+        let band = match income {
+            Some(income) => self
+                .bands
+                .iter()
+                .filter(|band| band.income_from <= income)
+                .max_by_key(|band| band.income_from)
+                .or(lowest),
+            None => lowest,
+        };
+
+        band.map_or_else(|| self.flat_amount.unwrap_or(0), |band| band.amount)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Schedules(Vec<Schedule>);
+
+fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+impl Schedules {
+    #[must_use]
+    pub fn new(mut schedules: Vec<Schedule>) -> Self {
+        schedules.sort_by_key(|schedule| schedule.valid_from);
+        Self(schedules)
+    }
+
+    fn valid_at(&self, date: NaiveDate) -> Option<&Schedule> {
+        self.0
+            .iter()
+            .rev()
+            .find(|schedule| schedule.valid_from <= date)
+    }
+
+    /// Fee for the month containing `date`
+    #[must_use]
+    pub fn monthly_fee_at(&self, date: NaiveDate, income: Option<i64>) -> i64 {
+        self.valid_at(first_of_month(date))
+            .map_or(0, |schedule| schedule.monthly_fee(income))
+    }
+
+    /// Sum of fees for every month from month of `from` to month of `to` (both inclusive)
+    #[must_use]
+    pub fn owed(&self, from: NaiveDate, to: NaiveDate, income: Option<i64>) -> i64 {
+        let mut month = first_of_month(from);
+        let mut total = 0;
+
+        while month <= to {
+            total += self.monthly_fee_at(month, income);
+            month = match month.checked_add_months(Months::new(1)) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+
+        total
+    }
+}
+
+/// Reminders of unpaid dues escalate until final notice before membership lapses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReminderLevel {
+    First,
+    Second,
+    Final,
+}
+
+impl ReminderLevel {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::First => "first",
+            Self::Second => "second",
+            Self::Final => "final",
+        }
+    }
+
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "first" => Some(Self::First),
+            "second" => Some(Self::Second),
+            "final" => Some(Self::Final),
+            _ => None,
+        }
+    }
+
+    /// Level following this one, `None` after final notice
+    #[must_use]
+    pub fn next(self) -> Option<Self> {
+        match self {
+            Self::First => Some(Self::Second),
+            Self::Second => Some(Self::Final),
+            Self::Final => None,
+        }
+    }
+}
+
+/// Reminder member in arrears should get today given the `last` reminder
+/// (level and date) sent since their last payment.
+/// Reminders escalate once `interval` passed since the previous one
+/// and stop after final notice.
+#[must_use]
+pub fn next_reminder(
+    last: Option<(ReminderLevel, NaiveDate)>,
+    today: NaiveDate,
+    interval: Days,
+) -> Option<ReminderLevel> {
+    match last {
+        None => Some(ReminderLevel::First),
+        Some((level, sent_on)) => {
+            let due = sent_on.checked_add_days(interval)?;
+            if today >= due { level.next() } else { None }
+        }
+    }
+}
+
+/// Format amount in minor units as decimal number like `150.00`
+#[must_use]
+pub fn format_amount(amount: i64) -> String {
+    let sign = if amount < 0 { "-" } else { "" };
+    let amount = amount.unsigned_abs();
+    format!("{sign}{}.{:02}", amount / 100, amount % 100)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn banded() -> Schedule {
+        Schedule {
+            valid_from: date(2024, 1, 1),
+            flat_amount: None,
+            bands: vec![
+                Band {
+                    income_from: 30_000,
+                    amount: 20_000,
+                },
+                Band {
+                    income_from: 0,
+                    amount: 10_000,
+                },
+                Band {
+                    income_from: 60_000,
+                    amount: 40_000,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn picks_income_band() {
+        let schedule = banded();
+
+        assert_eq!(schedule.monthly_fee(Some(10_000)), 10_000);
+        assert_eq!(schedule.monthly_fee(Some(30_000)), 20_000);
+        assert_eq!(schedule.monthly_fee(Some(100_000)), 40_000);
+        assert_eq!(schedule.monthly_fee(None), 10_000);
+    }
+
+    #[test]
+    fn flat_schedule_ignores_income() {
+        let schedule = Schedule {
+            valid_from: date(2024, 1, 1),
+            flat_amount: Some(15_000),
+            bands: Vec::new(),
+        };
+
+        assert_eq!(schedule.monthly_fee(Some(100_000)), 15_000);
+        assert_eq!(schedule.monthly_fee(None), 15_000);
+    }
+
+    #[test]
+    fn owed_uses_schedule_valid_in_each_month() {
+        let schedules = Schedules::new(vec![
+            Schedule {
+                valid_from: date(2024, 4, 1),
+                flat_amount: Some(20_000),
+                bands: Vec::new(),
+            },
+            Schedule {
+                valid_from: date(2024, 1, 1),
+                flat_amount: Some(10_000),
+                bands: Vec::new(),
+            },
+        ]);
+
+        // Dec 2023 has no schedule, Jan - Mar cost 100, Apr - May cost 200
+        assert_eq!(
+            schedules.owed(date(2023, 12, 15), date(2024, 5, 2), None),
+            3 * 10_000 + 2 * 20_000
+        );
+    }
+
+    #[test]
+    fn nothing_owed_before_joining() {
+        let schedules = Schedules::new(vec![banded()]);
+
+        assert_eq!(schedules.owed(date(2024, 6, 1), date(2024, 5, 31), None), 0);
+    }
+
+    #[test]
+    fn formats_amounts() {
+        assert_eq!(format_amount(15_000), "150.00");
+        assert_eq!(format_amount(5), "0.05");
+        assert_eq!(format_amount(-1_050), "-10.50");
+    }
+
+    #[test]
+    fn reminders_escalate_after_interval() {
+        let interval = Days::new(14);
+        let today = date(2024, 3, 15);
+
+        assert_eq!(
+            next_reminder(None, today, interval),
+            Some(ReminderLevel::First)
+        );
+        assert_eq!(
+            next_reminder(
+                Some((ReminderLevel::First, date(2024, 3, 10))),
+                today,
+                interval
+            ),
+            None
+        );
+        assert_eq!(
+            next_reminder(
+                Some((ReminderLevel::First, date(2024, 3, 1))),
+                today,
+                interval
+            ),
+            Some(ReminderLevel::Second)
+        );
+        assert_eq!(
+            next_reminder(
+                Some((ReminderLevel::Second, date(2024, 2, 1))),
+                today,
+                interval
+            ),
+            Some(ReminderLevel::Final)
+        );
+        assert_eq!(
+            next_reminder(
+                Some((ReminderLevel::Final, date(2024, 1, 1))),
+                today,
+                interval
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use super::status::{DUES_CURRENCY, DuesMember, FeeBand, FeeSchedule};
+use crate::data::{Id, Member};
+use crate::db::QueryAs;
+
+/// Columns of `DuesMember` selected from `members m`.
+/// Only payments in currency bound as `$currency` count towards dues.
+macro_rules! dues_member_columns {
+    ($currency:literal) => {
+        concat!(
+            "
+SELECT m.id
+    , m.member_number
+    , m.first_name
+    , m.last_name
+    , m.email
+    , m.declared_income
+    , ARRAY(SELECT mp.joined_at::DATE FROM membership_periods mp WHERE mp.member_id = m.id ORDER BY mp.joined_at) AS joined_on
+    , ARRAY(SELECT mp.left_at::DATE FROM membership_periods mp WHERE mp.member_id = m.id ORDER BY mp.joined_at) AS left_on
+    , m.left_at
+    , COALESCE((
+        SELECT SUM(p.amount) FROM payments p
+        WHERE p.member_number = m.member_number
+        AND p.currency = ",
+            $currency,
+            "
+    ), 0)::BIGINT AS paid
+    , (m.dues_reminders_paused_at IS NOT NULL
+        AND COALESCE(m.dues_reminders_paused_until >= CURRENT_DATE, TRUE)) AS reminders_paused"
+        )
+    };
+}
+
+pub fn list_schedules<'a>() -> QueryAs<'a, FeeSchedule> {
+    sqlx::query_as(
+        "
+SELECT id
+    , name
+    , valid_from
+    , currency
+    , flat_amount
+    , created_at
+FROM fee_schedules
+ORDER BY valid_from DESC
+",
+    )
+}
+
+pub fn list_bands<'a>() -> QueryAs<'a, FeeBand> {
+    sqlx::query_as(
+        "
+SELECT fee_schedule_id
+    , income_from
+    , amount
+FROM fee_schedule_bands
+ORDER BY income_from
+",
+    )
+}
+
+pub fn get_dues_member<'a>(id: Id<Member>) -> QueryAs<'a, DuesMember> {
+    sqlx::query_as(concat!(
+        dues_member_columns!("$2"),
+        "
+FROM members m
+WHERE m.id = $1
+",
+    ))
+    .bind(id)
+    .bind(DUES_CURRENCY)
+}
+
+pub fn list_current_dues_members<'a>() -> QueryAs<'a, DuesMember> {
+    sqlx::query_as(concat!(
+        dues_member_columns!("$1"),
+        "
+FROM members m
+WHERE m.left_at IS NULL
+ORDER BY m.member_number
+",
+    ))
+    .bind(DUES_CURRENCY)
+}
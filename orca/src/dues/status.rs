@@ -0,0 +1,180 @@
+//! Dues of members computed from fee schedules and payments stored in the database
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+
+use super::query;
+use super::{Band, Schedule, Schedules};
+use crate::config::Config;
+use crate::data::{self, Id, Member, MemberNumber};
+use crate::db::DbPool;
+use crate::spayd::PaymentRequest;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct FeeBand {
+    #[serde(skip)]
+    fee_schedule_id: Id<data::FeeSchedule>,
+    income_from: i64,
+    amount: i64,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct FeeSchedule {
+    pub(crate) id: Id<data::FeeSchedule>,
+    name: String,
+    valid_from: NaiveDate,
+    currency: String,
+    flat_amount: Option<i64>,
+    created_at: DateTime<Utc>,
+    #[sqlx(skip)]
+    pub(crate) bands: Vec<FeeBand>,
+}
+
+impl From<&FeeSchedule> for Schedule {
+    fn from(schedule: &FeeSchedule) -> Self {
+        Self {
+            valid_from: schedule.valid_from,
+            flat_amount: schedule.flat_amount,
+            bands: schedule
+                .bands
+                .iter()
+                .map(|band| Band {
+                    income_from: band.income_from,
+                    amount: band.amount,
+                })
+                .collect(),
+        }
+    }
+}
+
+pub(crate) async fn fetch_schedules(db_pool: &DbPool) -> Result<Vec<FeeSchedule>, sqlx::Error> {
+    let mut schedules = query::list_schedules().fetch_all(db_pool).await?;
+    let bands = query::list_bands().fetch_all(db_pool).await?;
+
+    for band in bands {
+        if let Some(schedule) = schedules
+            .iter_mut()
+            .find(|schedule| schedule.id == band.fee_schedule_id)
+        {
+            schedule.bands.push(band);
+        }
+    }
+
+    Ok(schedules)
+}
+
+pub(crate) async fn load_schedules(db_pool: &DbPool) -> Result<Schedules, sqlx::Error> {
+    let schedules = fetch_schedules(db_pool).await?;
+    Ok(Schedules::new(
+        schedules.iter().map(Schedule::from).collect(),
+    ))
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct DuesMember {
+    id: Id<Member>,
+    member_number: MemberNumber,
+    first_name: Option<String>,
+    last_name: Option<String>,
+    email: Option<String>,
+    declared_income: Option<i64>,
+    /// Start of each membership period
+    joined_on: Vec<NaiveDate>,
+    /// End of each membership period, `None` for the ongoing one
+    left_on: Vec<Option<NaiveDate>>,
+    left_at: Option<DateTime<Utc>>,
+    paid: i64,
+    reminders_paused: bool,
+}
+
+/// Dues of member for membership periods up to today (or the day member left)
+#[derive(Debug, Serialize)]
+pub struct DuesStatus {
+    pub(crate) member_id: Id<Member>,
+    pub(crate) member_number: MemberNumber,
+    first_name: Option<String>,
+    last_name: Option<String>,
+    email: Option<String>,
+    declared_income: Option<i64>,
+    pub(crate) monthly_fee: i64,
+    owed: i64,
+    paid: i64,
+    pub(crate) arrears: i64,
+    pub(crate) months_in_arrears: i64,
+    /// Automatic reminders are paused
+    pub(crate) reminders_paused: bool,
+}
+
+impl DuesMember {
+    pub(crate) fn status(self, schedules: &Schedules, today: NaiveDate) -> DuesStatus {
+        let until = self.left_at.map_or(today, |left_at| left_at.date_naive());
+        // Nothing is owed for time between leaving and re-joining
+        let owed: i64 = self
+            .joined_on
+            .iter()
+            .zip(&self.left_on)
+            .map(|(from, to)| schedules.owed(*from, to.unwrap_or(today), self.declared_income))
+            .sum();
+        let monthly_fee = schedules.monthly_fee_at(until, self.declared_income);
+        let arrears = (owed - self.paid).max(0);
+
+        DuesStatus {
+            member_id: self.id,
+            member_number: self.member_number,
+            first_name: self.first_name,
+            last_name: self.last_name,
+            email: self.email,
+            declared_income: self.declared_income,
+            monthly_fee,
+            owed,
+            paid: self.paid,
+            arrears,
+            months_in_arrears: if monthly_fee > 0 {
+                arrears / monthly_fee
+            } else {
+                0
+            },
+            reminders_paused: self.reminders_paused,
+        }
+    }
+}
+
+pub(crate) async fn member_status(
+    db_pool: &DbPool,
+    id: Id<Member>,
+) -> Result<DuesStatus, sqlx::Error> {
+    let schedules = load_schedules(db_pool).await?;
+    let member = query::get_dues_member(id).fetch_one(db_pool).await?;
+
+    Ok(member.status(&schedules, Utc::now().date_naive()))
+}
+
+/// Payment of dues to account configured in `payment_account`
+pub(crate) fn payment_request(
+    config: &Config,
+    member_number: MemberNumber,
+    amount: i64,
+) -> Option<PaymentRequest<'_>> {
+    Some(PaymentRequest {
+        account: config.payment_account.as_deref()?,
+        amount,
+        currency: DUES_CURRENCY,
+        member_number,
+        message: config.payment_message.as_deref(),
+    })
+}
+
+pub(crate) async fn current_statuses(db_pool: &DbPool) -> Result<Vec<DuesStatus>, sqlx::Error> {
+    let schedules = load_schedules(db_pool).await?;
+    let today = Utc::now().date_naive();
+
+    Ok(query::list_current_dues_members()
+        .fetch_all(db_pool)
+        .await?
+        .into_iter()
+        .map(|member| member.status(&schedules, today))
+        .collect())
+}
+
+/// Currency in which dues are collected,
+/// transactions in other currencies go to manual review
+pub(crate) const DUES_CURRENCY: &str = "CZK";
@@ -1,13 +1,16 @@
 mod api;
+mod bank;
 pub mod config;
 mod data;
 mod db;
+mod dues;
 mod generate;
 mod listmonk;
 mod logging;
 mod media;
 mod processing;
 mod server;
+mod spayd;
 mod validation;
 
 use config::Config;
@@ -54,6 +57,10 @@ pub async fn start(config: Config) -> Result<(), StartupError> {
     // This will not work, until we figure out how to provide JWT Token for Keycloak calls
     processing::ensure_member_subs(&web_db_pool, &queue).await;
 
+    // Subscriptions interrupted by restart
+    processing::ensure_listmonk_subscriptions(&web_db_pool, &queue).await;
+    processing::ensure_listmonk_syncs(&web_db_pool, &queue).await;
+
     api::build()
         .attach(server::cors::Cors)
         .manage(web_db_pool)
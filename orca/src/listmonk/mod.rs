@@ -1,26 +1,94 @@
-use reqwest::Client;
-use rocket::serde::json;
+use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use crate::config::Config;
+use crate::config::listmonk::{Routing, Subscriber};
 
 use crate::api::applications::Detail;
-use crate::data::{Id, Member, MemberNumber};
+use crate::data::MemberNumber;
 
 use crate::api::ApiError;
 
-use log::{debug, error};
+pub(crate) mod query;
+pub(crate) mod sync;
+
+#[derive(Debug, Error)]
+pub(crate) enum Error {
+    #[error("Listmonk configuration is missing {0}")]
+    ConfigMissing(&'static str),
+    #[error("Http error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Listmonk responded with {0}: {1}")]
+    Response(StatusCode, String),
+    #[error("Parsing error: {0}")]
+    Parsing(String),
+    #[error("Missing {0}")]
+    DataMissing(&'static str),
+    #[error("SQL Error: {0}")]
+    Sql(#[from] sqlx::Error),
+}
 
-mod query;
+impl From<Error> for ApiError {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Sql(err) => err.into(),
+            Error::ConfigMissing(item) => ApiError::config_missing(item),
+            Error::DataMissing(item) => ApiError::data_missing(item),
+            err => ApiError::listmonk_error(&err.to_string()),
+        }
+    }
+}
 
-#[derive(Deserialize)]
-struct ListmonkResponseData {
-    id: i32,
+/// Authenticated access to Listmonk API
+pub(crate) struct Connection<'a> {
+    host: &'a str,
+    username: &'a str,
+    password: &'a str,
+    client: Client,
 }
 
-#[derive(Deserialize)]
-struct ListmonkResponse {
-    data: ListmonkResponseData,
+/// If config file doesn't contain `listmonk_host` value we skip all Listmonk calls.
+/// DEV environment will be probably missing `listmonk_host`, unless devs setup their own testing listmonk instance.
+/// We don't want to call production listmonk during development
+pub(crate) fn is_enabled(config: &Config) -> bool {
+    config
+        .listmonk_host
+        .as_deref()
+        .is_some_and(|h| !h.is_empty())
+}
+
+impl<'a> Connection<'a> {
+    /// Returns `None` when Listmonk is not enabled
+    pub(crate) fn from_config(config: &'a Config) -> Result<Option<Self>, Error> {
+        let Some(host) = config.listmonk_host.as_deref().filter(|h| !h.is_empty()) else {
+            return Ok(None);
+        };
+
+        let username = config
+            .listmonk_username
+            .as_deref()
+            .ok_or(Error::ConfigMissing("listmonk_username"))?;
+
+        let password = config
+            .listmonk_password
+            .as_deref()
+            .ok_or(Error::ConfigMissing("listmonk_password"))?;
+
+        Ok(Some(Self {
+            host,
+            username,
+            password,
+            client: Client::new(),
+        }))
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        self.client
+            .request(method, format!("{}/api/{path}", self.host))
+            .header("Content-Type", "application/json")
+            .basic_auth(self.username, Some(self.password))
+    }
 }
 
 #[derive(Serialize)]
@@ -38,146 +106,31 @@ struct ListMonkSubscribe {
     attribs: ListMonkAtrribs,
 }
 
-#[derive(Serialize, Deserialize, Copy, Clone)]
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub(crate) enum ListMonkStatus {
     Enabled,
     Disabled,
+    Blocklisted,
 }
 
-pub(crate) struct ListMonkDetail<'a> {
-    detail: &'a Detail,
-    member_number: MemberNumber,
-    pub(crate) lists: Vec<u32>,
-}
-
-pub(crate) fn set_listmonk_lists(list_monk_detail: &mut ListMonkDetail<'_>) {
-    // vec![5] to main List, 4 prague, 3 brno
-
-    let location = list_monk_detail
-        .detail
-        .city
-        .clone()
-        .expect("City is required")
-        .to_lowercase();
-
-    if location.contains("prague") || location.contains("praha") {
-        list_monk_detail.lists = vec![4u32, 5u32];
-        return;
-    }
-    if location.contains("brno") {
-        list_monk_detail.lists = vec![3u32, 5u32];
-        return;
+impl ListMonkStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Enabled => "enabled",
+            Self::Disabled => "disabled",
+            Self::Blocklisted => "blocklisted",
+        }
     }
-    // fallback — maybe assign a default list or do nothing
-    list_monk_detail.lists = vec![5u32];
 }
 
-pub(crate) async fn subscribe_to_listmonk(
-    application_detail: &Detail,
-    member_number: MemberNumber,
-    config: &Config,
-    mut tx: sqlx::Transaction<'_, sqlx::Postgres>,
-    member_id: Id<Member>,
-) -> Result<(), ApiError> {
-    let mut list_monk_detail: ListMonkDetail<'_> = ListMonkDetail {
-        detail: application_detail,
-        lists: vec![0],
-        member_number,
-    };
-
-    set_listmonk_lists(&mut list_monk_detail);
-
-    let password = config
-        .listmonk_password
-        .as_ref()
-        .ok_or_else(|| ApiError::config_missing("listmonk_password"))?;
-
-    let username = config
-        .listmonk_username
-        .as_ref()
-        .ok_or_else(|| ApiError::config_missing("listmonk_username"))?;
-
-    let host = config
-        .listmonk_host
-        .as_ref()
-        .ok_or_else(|| ApiError::config_missing("listmonk_host"))?;
-
-    let url: String = format!("{host}/api/subscribers");
-
-    let client = Client::new();
-
-    let first_name = list_monk_detail
-        .detail
-        .first_name
-        .clone()
-        .ok_or_else(|| ApiError::data_missing("member.first_name"))?;
-
-    let last_name = list_monk_detail
-        .detail
-        .last_name
-        .clone()
-        .ok_or_else(|| ApiError::data_missing("member.last_name"))?;
-
-    let name = format!("{first_name} {last_name}");
-
-    let member_number = list_monk_detail.member_number;
-
-    let lang = list_monk_detail
-        .detail
-        .language
-        .clone()
-        .ok_or_else(|| ApiError::data_missing("member.language"))?;
-
-    let attribs = ListMonkAtrribs {
-        lang,
-        member_number,
-    };
-
-    let payload: ListMonkSubscribe = ListMonkSubscribe {
-        email: list_monk_detail
-            .detail
-            .email
-            .clone()
-            .ok_or_else(|| ApiError::data_missing("member.email"))?,
-        name,
-        status: ListMonkStatus::Enabled,
-        attribs,
-        lists: list_monk_detail.lists.clone(),
-    };
-
-    let res: reqwest::Response = client
-        .post(url)
-        .header("Content-Type", "application/json")
-        .basic_auth(username, Some(password))
-        .json(&payload)
-        .send()
-        .await?;
-    let res_status = res.status();
-    let text: String = res.text().await?;
-
-    debug!("Status: {res_status}");
-
-    if !res_status.is_success() {
-        error!("Error subscribing user to Listmonk: {text}");
-        return Err(ApiError::listmonk_error(&text));
-    }
-
-    let listmonk_id: i32 = json::from_str::<ListmonkResponse>(&text)
-        .map(|r| r.data.id)
-        .map_err(|_ignored| ApiError::data_missing("Listmonk.Id"))?;
-
-    let status = "enabled";
-
-    let lists = list_monk_detail.lists;
-    for list_value in lists {
-        let unsingned_list_value = i64::from(list_value);
-        query::create_email_subscription(member_id, unsingned_list_value, status, listmonk_id)
-            .execute(&mut *tx)
-            .await?;
-    }
-
-    tx.commit().await?;
-
-    Ok(())
+/// Lists application would be subscribed to on acceptance
+pub(crate) fn lists_for_application(detail: &Detail, routing: &Routing) -> Vec<u32> {
+    routing.lists_for(&Subscriber {
+        city: detail.city.as_deref(),
+        postal_code: detail.postal_code.as_deref(),
+        language: detail.language.as_deref(),
+        // Application can't be assigned to workplace yet
+        workplaces: &[],
+    })
 }
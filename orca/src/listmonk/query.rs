@@ -1,16 +1,65 @@
+use super::sync::SubscribedMember;
 use crate::data::{Id, Member};
-use crate::db::Query;
+use crate::db::{Query, QueryAs};
 
-pub(crate) fn create_email_subscription<'a>(
+pub(crate) fn get_subscribed_member<'a>(member_id: Id<Member>) -> QueryAs<'a, SubscribedMember> {
+    sqlx::query_as(
+        "
+SELECT m.id AS member_id
+    , m.member_number
+    , m.email
+    , m.first_name
+    , m.last_name
+    , m.language
+    , m.city
+    , m.postal_code
+    , m.left_at
+    , ARRAY(SELECT mw.workplace_id FROM members_workplaces mw WHERE mw.member_id = m.id) AS workplace_ids
+    , (SELECT MAX(es.listmonk_id) FROM email_subscriptions es WHERE es.member_id = m.id) AS listmonk_id
+FROM members m
+WHERE m.id = $1
+",
+    )
+    .bind(member_id)
+}
+
+// Current members together with past members who still have some subscription
+pub(crate) fn list_subscribed_members<'a>() -> QueryAs<'a, SubscribedMember> {
+    sqlx::query_as(
+        "
+SELECT m.id AS member_id
+    , m.member_number
+    , m.email
+    , m.first_name
+    , m.last_name
+    , m.language
+    , m.city
+    , m.postal_code
+    , m.left_at
+    , ARRAY(SELECT mw.workplace_id FROM members_workplaces mw WHERE mw.member_id = m.id) AS workplace_ids
+    , (SELECT MAX(es.listmonk_id) FROM email_subscriptions es WHERE es.member_id = m.id) AS listmonk_id
+FROM members m
+WHERE m.left_at IS NULL
+OR EXISTS (SELECT es.id FROM email_subscriptions es WHERE es.member_id = m.id)
+ORDER BY m.member_number
+",
+    )
+}
+
+pub(crate) fn upsert_email_subscription<'a>(
     member_id: Id<Member>,
-    list: i64,
-    listmonk_status: &'static str,
+    list: String,
+    listmonk_status: String,
     listmonk_id: i32,
 ) -> Query<'a> {
     sqlx::query(
         "
         INSERT INTO email_subscriptions (member_id, list, listmonk_status, listmonk_id, created_at, updated_at)
         VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+        ON CONFLICT (member_id, list) DO UPDATE
+        SET listmonk_status = EXCLUDED.listmonk_status
+        , listmonk_id = EXCLUDED.listmonk_id
+        , updated_at = CURRENT_TIMESTAMP
         ",
     )
     .bind(member_id)
@@ -18,3 +67,126 @@ pub(crate) fn create_email_subscription<'a>(
     .bind(listmonk_status)
     .bind(listmonk_id)
 }
+
+// Marks subscriptions to lists other than the given ones
+pub(crate) fn mark_email_subscriptions_removed<'a>(
+    member_id: Id<Member>,
+    lists: Vec<String>,
+    listmonk_status: &'static str,
+) -> Query<'a> {
+    sqlx::query(
+        "
+        UPDATE email_subscriptions
+        SET listmonk_status = $3
+        , updated_at = CURRENT_TIMESTAMP
+        WHERE member_id = $1
+        AND NOT (list = ANY($2))
+        AND listmonk_status <> $3
+        ",
+    )
+    .bind(member_id)
+    .bind(lists)
+    .bind(listmonk_status)
+}
+
+pub(crate) fn set_email_subscriptions_status<'a>(
+    member_id: Id<Member>,
+    listmonk_status: &'static str,
+) -> Query<'a> {
+    sqlx::query(
+        "
+        UPDATE email_subscriptions
+        SET listmonk_status = $2
+        , updated_at = CURRENT_TIMESTAMP
+        WHERE member_id = $1
+        ",
+    )
+    .bind(member_id)
+    .bind(listmonk_status)
+}
+
+pub(crate) fn create_subscription_request<'a>(member_id: Id<Member>) -> Query<'a> {
+    sqlx::query(
+        "
+        INSERT INTO listmonk_subscription_requests (member_id)
+        VALUES ($1)
+        ON CONFLICT (member_id) DO UPDATE
+        SET status = 'pending'
+        , attempts = 0
+        , last_error = NULL
+        , updated_at = CURRENT_TIMESTAMP
+        ",
+    )
+    .bind(member_id)
+}
+
+pub(crate) fn set_subscription_request_status<'a>(
+    member_id: Id<Member>,
+    status: &'static str,
+) -> Query<'a> {
+    sqlx::query(
+        "
+        UPDATE listmonk_subscription_requests
+        SET status = $2
+        , updated_at = CURRENT_TIMESTAMP
+        WHERE member_id = $1
+        ",
+    )
+    .bind(member_id)
+    .bind(status)
+}
+
+pub(crate) fn record_subscription_failure<'a>(
+    member_id: Id<Member>,
+    error: String,
+    max_attempts: u32,
+) -> QueryAs<'a, (i32,)> {
+    sqlx::query_as(
+        "
+        UPDATE listmonk_subscription_requests
+        SET attempts = attempts + 1
+        , last_error = $2
+        , status = CASE WHEN attempts + 1 >= $3 THEN 'failed' ELSE 'pending' END
+        , updated_at = CURRENT_TIMESTAMP
+        WHERE member_id = $1
+        RETURNING attempts
+        ",
+    )
+    .bind(member_id)
+    .bind(error)
+    .bind(i64::from(max_attempts))
+}
+
+/// Track failed push of member to Listmonk.
+/// Request is marked as failed once `max_attempts` is reached.
+pub(crate) fn record_sync_failure<'a>(
+    member_id: Id<Member>,
+    error: String,
+    max_attempts: u32,
+) -> QueryAs<'a, (i32,)> {
+    sqlx::query_as(
+        "
+        INSERT INTO listmonk_sync_requests AS r (member_id, attempts, last_error, status)
+        VALUES ($1, 1, $2, CASE WHEN 1 >= $3 THEN 'failed' ELSE 'pending' END)
+        ON CONFLICT (member_id) DO UPDATE
+        SET attempts = r.attempts + 1
+        , last_error = $2
+        , status = CASE WHEN r.attempts + 1 >= $3 THEN 'failed' ELSE 'pending' END
+        , updated_at = CURRENT_TIMESTAMP
+        RETURNING attempts
+        ",
+    )
+    .bind(member_id)
+    .bind(error)
+    .bind(i64::from(max_attempts))
+}
+
+pub(crate) fn delete_sync_request<'a>(member_id: Id<Member>) -> Query<'a> {
+    sqlx::query(
+        "
+        DELETE FROM listmonk_sync_requests
+        WHERE member_id = $1
+        ",
+    )
+    .bind(member_id)
+}
@@ -0,0 +1,827 @@
+//! Keeping Listmonk subscribers in sync with members
+//!
+//! Changes of members are pushed to Listmonk
+//! while subscription statuses (like unsubscribes) are periodically pulled back
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use reqwest::{Method, StatusCode, Url};
+use rocket::serde::json;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{Connection, Error, ListMonkAtrribs, ListMonkStatus, ListMonkSubscribe, query};
+use crate::config::listmonk::{Routing, Subscriber};
+use crate::data::{Id, Member, MemberNumber};
+use crate::db::DbPool;
+
+/// Value of `listmonk_status` for subscriptions of blocklisted subscribers
+const BLOCKLISTED: &str = "blocklisted";
+/// Value of `listmonk_status` for subscriptions whose subscriber no longer exists in Listmonk
+const MISSING: &str = "missing";
+/// Value of `listmonk_status` for lists subscriber was removed from in Listmonk
+const REMOVED: &str = "removed";
+/// Status of subscription request which was processed
+const SUBSCRIBED: &str = "subscribed";
+/// Subscription status Listmonk uses for lists subscriber opted out of
+const UNSUBSCRIBED: &str = "unsubscribed";
+/// Number of subscribers fetched at once when listing all of them
+const PAGE_SIZE: usize = 500;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct RemoteList {
+    id: u32,
+    subscription_status: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct RemoteSubscriber {
+    id: i32,
+    email: String,
+    name: String,
+    status: ListMonkStatus,
+    #[serde(default)]
+    lists: Vec<RemoteList>,
+}
+
+#[derive(Deserialize)]
+struct SubscriberResponse {
+    data: RemoteSubscriber,
+}
+
+#[derive(Deserialize)]
+struct SubscriberPage {
+    results: Vec<RemoteSubscriber>,
+}
+
+#[derive(Deserialize)]
+struct SubscribersResponse {
+    data: SubscriberPage,
+}
+
+impl RemoteSubscriber {
+    fn status_for(&self, list: &RemoteList) -> String {
+        if self.status == ListMonkStatus::Blocklisted {
+            BLOCKLISTED.to_string()
+        } else {
+            list.subscription_status.clone()
+        }
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub(crate) struct SubscribedMember {
+    member_id: Id<Member>,
+    member_number: MemberNumber,
+    email: Option<String>,
+    first_name: Option<String>,
+    last_name: Option<String>,
+    language: Option<String>,
+    city: Option<String>,
+    postal_code: Option<String>,
+    left_at: Option<DateTime<Utc>>,
+    workplace_ids: Vec<Uuid>,
+    listmonk_id: Option<i32>,
+}
+
+/// What Listmonk subscriber should look like according to our data
+#[derive(Debug)]
+struct Expected {
+    email: Option<String>,
+    name: String,
+    lists: Vec<u32>,
+    left: bool,
+}
+
+impl SubscribedMember {
+    fn expected(&self, routing: &Routing) -> Expected {
+        let name = [self.first_name.as_deref(), self.last_name.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Expected {
+            email: self.email.clone(),
+            name,
+            lists: routing.lists_for(&Subscriber {
+                city: self.city.as_deref(),
+                postal_code: self.postal_code.as_deref(),
+                language: self.language.as_deref(),
+                workplaces: &self.workplace_ids,
+            }),
+            left: self.left_at.is_some(),
+        }
+    }
+}
+
+impl SubscribedMember {
+    fn payload(
+        &self,
+        routing: &Routing,
+        status: ListMonkStatus,
+    ) -> Result<ListMonkSubscribe, Error> {
+        let expected = self.expected(routing);
+
+        Ok(ListMonkSubscribe {
+            email: expected.email.ok_or(Error::DataMissing("member.email"))?,
+            name: expected.name,
+            status,
+            lists: expected.lists,
+            attribs: ListMonkAtrribs {
+                lang: self.language.clone().unwrap_or_default(),
+                member_number: self.member_number,
+            },
+        })
+    }
+}
+
+impl Connection<'_> {
+    async fn create_subscriber(
+        &self,
+        payload: &ListMonkSubscribe,
+    ) -> Result<Option<RemoteSubscriber>, Error> {
+        let res = self
+            .request(Method::POST, "subscribers")
+            .json(payload)
+            .send()
+            .await?;
+
+        // Subscriber with the same email already exists
+        if res.status() == StatusCode::CONFLICT {
+            return Ok(None);
+        }
+
+        parse_subscriber(res).await.map(Some)
+    }
+
+    async fn find_subscriber_by_email(
+        &self,
+        email: &str,
+    ) -> Result<Option<RemoteSubscriber>, Error> {
+        let query = format!("subscribers.email = '{}'", email.replace('\'', "''"));
+        let mut url = Url::parse(&format!("{}/api/subscribers", self.host))
+            .map_err(|err| Error::Parsing(err.to_string()))?;
+        url.query_pairs_mut()
+            .append_pair("query", &query)
+            .append_pair("per_page", "1");
+
+        let res = self
+            .client
+            .get(url)
+            .basic_auth(self.username, Some(self.password))
+            .send()
+            .await?;
+
+        let text = response_text(res).await?;
+
+        json::from_str::<SubscribersResponse>(&text)
+            .map(|r| r.data.results.into_iter().next())
+            .map_err(|err| Error::Parsing(err.to_string()))
+    }
+
+    async fn list_subscribers_page(&self, page: usize) -> Result<Vec<RemoteSubscriber>, Error> {
+        let res = self
+            .request(
+                Method::GET,
+                &format!("subscribers?page={page}&per_page={PAGE_SIZE}"),
+            )
+            .send()
+            .await?;
+
+        let text = response_text(res).await?;
+
+        json::from_str::<SubscribersResponse>(&text)
+            .map(|r| r.data.results)
+            .map_err(|err| Error::Parsing(err.to_string()))
+    }
+
+    /// All subscribers in Listmonk by their id, fetched page by page
+    async fn list_subscribers(&self) -> Result<HashMap<i32, RemoteSubscriber>, Error> {
+        let mut subscribers = HashMap::new();
+
+        for page in 1.. {
+            let results = self.list_subscribers_page(page).await?;
+            let is_last = results.len() < PAGE_SIZE;
+            subscribers.extend(results.into_iter().map(|remote| (remote.id, remote)));
+
+            if is_last {
+                break;
+            }
+        }
+
+        Ok(subscribers)
+    }
+
+    async fn get_subscriber(&self, id: i32) -> Result<Option<RemoteSubscriber>, Error> {
+        let res = self
+            .request(Method::GET, &format!("subscribers/{id}"))
+            .send()
+            .await?;
+
+        if res.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        parse_subscriber(res).await.map(Some)
+    }
+
+    async fn update_subscriber(
+        &self,
+        id: i32,
+        payload: &ListMonkSubscribe,
+    ) -> Result<RemoteSubscriber, Error> {
+        let res = self
+            .request(Method::PUT, &format!("subscribers/{id}"))
+            .json(payload)
+            .send()
+            .await?;
+
+        parse_subscriber(res).await
+    }
+
+    async fn blocklist_subscriber(&self, id: i32) -> Result<(), Error> {
+        let res = self
+            .request(Method::PUT, &format!("subscribers/{id}/blocklist"))
+            .send()
+            .await?;
+
+        response_text(res).await?;
+        Ok(())
+    }
+}
+
+async fn response_text(res: reqwest::Response) -> Result<String, Error> {
+    let status = res.status();
+    let text = res.text().await?;
+
+    if !status.is_success() {
+        error!("Listmonk request failed with {status}: {text}");
+        return Err(Error::Response(status, text));
+    }
+
+    Ok(text)
+}
+
+async fn parse_subscriber(res: reqwest::Response) -> Result<RemoteSubscriber, Error> {
+    let text = response_text(res).await?;
+
+    json::from_str::<SubscriberResponse>(&text)
+        .map(|r| r.data)
+        .map_err(|err| Error::Parsing(err.to_string()))
+}
+
+/// Store statuses of subscriber lists into `email_subscriptions`
+async fn store_statuses(
+    db_pool: &DbPool,
+    member_id: Id<Member>,
+    remote: &RemoteSubscriber,
+) -> Result<(), Error> {
+    let mut tx = db_pool.begin().await?;
+
+    let lists: Vec<String> = remote.lists.iter().map(|l| l.id.to_string()).collect();
+    query::mark_email_subscriptions_removed(member_id, lists, REMOVED)
+        .execute(&mut *tx)
+        .await?;
+
+    for list in &remote.lists {
+        query::upsert_email_subscription(
+            member_id,
+            list.id.to_string(),
+            remote.status_for(list),
+            remote.id,
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Status pushed together with data of current member.
+///
+/// Status in Listmonk is kept so we never undo blocklisting done there,
+/// except on `resubscribe` of re-joined member who was blocklisted when they left.
+fn pushed_status(remote: ListMonkStatus, resubscribe: bool) -> ListMonkStatus {
+    if resubscribe && remote == ListMonkStatus::Blocklisted {
+        ListMonkStatus::Enabled
+    } else {
+        remote
+    }
+}
+
+/// Push current data of member to Listmonk.
+///
+/// Members who left are blocklisted.
+/// Others get their email, name, attributes and lists updated,
+/// see `pushed_status` for their status.
+pub(crate) async fn push_member(
+    connection: &Connection<'_>,
+    routing: &Routing,
+    db_pool: &DbPool,
+    member_id: Id<Member>,
+    resubscribe: bool,
+) -> Result<(), Error> {
+    let member = query::get_subscribed_member(member_id)
+        .fetch_one(db_pool)
+        .await?;
+
+    let Some(listmonk_id) = member.listmonk_id else {
+        info!("Member {member_id} is not subscribed to Listmonk, nothing to sync");
+        return Ok(());
+    };
+
+    if member.left_at.is_some() {
+        connection.blocklist_subscriber(listmonk_id).await?;
+        query::set_email_subscriptions_status(member_id, BLOCKLISTED)
+            .execute(db_pool)
+            .await?;
+        return Ok(());
+    }
+
+    let Some(remote) = connection.get_subscriber(listmonk_id).await? else {
+        warn!("Listmonk subscriber {listmonk_id} of member {member_id} no longer exists");
+        query::set_email_subscriptions_status(member_id, MISSING)
+            .execute(db_pool)
+            .await?;
+        return Ok(());
+    };
+
+    let payload = member.payload(routing, pushed_status(remote.status, resubscribe))?;
+    let updated = connection.update_subscriber(listmonk_id, &payload).await?;
+    store_statuses(db_pool, member_id, &updated).await
+}
+
+/// Set email of member's subscriber to `email` without touching our data.
+///
+/// Used while changing email of member before the change is committed
+/// and to revert it when some other part of the change fails.
+pub(crate) async fn set_member_email(
+    connection: &Connection<'_>,
+    routing: &Routing,
+    db_pool: &DbPool,
+    member_id: Id<Member>,
+    email: &str,
+) -> Result<(), Error> {
+    let mut member = query::get_subscribed_member(member_id)
+        .fetch_one(db_pool)
+        .await?;
+
+    let Some(listmonk_id) = member.listmonk_id else {
+        return Ok(());
+    };
+
+    let Some(remote) = connection.get_subscriber(listmonk_id).await? else {
+        warn!("Listmonk subscriber {listmonk_id} of member {member_id} no longer exists");
+        return Ok(());
+    };
+
+    member.email = Some(email.to_string());
+    let payload = member.payload(routing, remote.status)?;
+    connection.update_subscriber(listmonk_id, &payload).await?;
+
+    Ok(())
+}
+
+/// Update replaces all lists of subscriber, lists person subscribed to
+/// on their own are added to the routed ones
+fn keep_own_lists(lists: &mut Vec<u32>, remote: &RemoteSubscriber) {
+    for list in &remote.lists {
+        if !lists.contains(&list.id) {
+            lists.push(list.id);
+        }
+    }
+}
+
+/// Create Listmonk subscriber for member.
+///
+/// Member who already has subscriber (e.g. from previous attempt
+/// which failed after subscriber was created) is just linked to it.
+pub(crate) async fn subscribe_member(
+    connection: &Connection<'_>,
+    routing: &Routing,
+    db_pool: &DbPool,
+    member_id: Id<Member>,
+) -> Result<(), Error> {
+    let member = query::get_subscribed_member(member_id)
+        .fetch_one(db_pool)
+        .await?;
+
+    if member.listmonk_id.is_some() {
+        info!("Member {member_id} is already subscribed to Listmonk");
+    } else {
+        let payload = member.payload(routing, ListMonkStatus::Enabled)?;
+
+        let remote = if let Some(remote) = connection.create_subscriber(&payload).await? {
+            remote
+        } else {
+            let existing = connection
+                .find_subscriber_by_email(&payload.email)
+                .await?
+                .ok_or(Error::DataMissing("Listmonk subscriber"))?;
+            info!(
+                "Linking member {member_id} to existing Listmonk subscriber {}",
+                existing.id
+            );
+            let mut payload = member.payload(routing, existing.status)?;
+            keep_own_lists(&mut payload.lists, &existing);
+            connection.update_subscriber(existing.id, &payload).await?
+        };
+
+        store_statuses(db_pool, member_id, &remote).await?;
+    }
+
+    query::set_subscription_request_status(member_id, SUBSCRIBED)
+        .execute(db_pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Track failed subscription attempt.
+/// Request is marked as failed once `max_attempts` is reached.
+///
+/// Returns number of attempts made so far.
+pub(crate) async fn record_subscription_failure(
+    db_pool: &DbPool,
+    member_id: Id<Member>,
+    err: &Error,
+    max_attempts: u32,
+) -> Result<i32, Error> {
+    let (attempts,) = query::record_subscription_failure(member_id, err.to_string(), max_attempts)
+        .fetch_one(db_pool)
+        .await?;
+
+    Ok(attempts)
+}
+
+/// Track failed push of member, see `record_subscription_failure`
+pub(crate) async fn record_sync_failure(
+    db_pool: &DbPool,
+    member_id: Id<Member>,
+    err: &Error,
+    max_attempts: u32,
+) -> Result<i32, Error> {
+    let (attempts,) = query::record_sync_failure(member_id, err.to_string(), max_attempts)
+        .fetch_one(db_pool)
+        .await?;
+
+    Ok(attempts)
+}
+
+/// Forget previous failures once member was pushed successfully
+pub(crate) async fn clear_sync_failures(
+    db_pool: &DbPool,
+    member_id: Id<Member>,
+) -> Result<(), Error> {
+    query::delete_sync_request(member_id)
+        .execute(db_pool)
+        .await?;
+    Ok(())
+}
+
+/// Pull statuses of all known subscribers from Listmonk into `email_subscriptions`.
+///
+/// Subscribers are listed in pages the same way as for `reconcile`.
+/// Returns number of subscribers which were updated.
+pub(crate) async fn pull_statuses(
+    connection: &Connection<'_>,
+    db_pool: &DbPool,
+) -> Result<usize, Error> {
+    let members = query::list_subscribed_members().fetch_all(db_pool).await?;
+    let subscribers = connection.list_subscribers().await?;
+    let mut updated = 0;
+
+    for member in members {
+        let Some(listmonk_id) = member.listmonk_id else {
+            continue;
+        };
+
+        if let Some(remote) = subscribers.get(&listmonk_id) {
+            store_statuses(db_pool, member.member_id, remote).await?;
+            updated += 1;
+        } else {
+            warn!(
+                "Listmonk subscriber {listmonk_id} of member {} no longer exists",
+                member.member_id
+            );
+            query::set_email_subscriptions_status(member.member_id, MISSING)
+                .execute(db_pool)
+                .await?;
+        }
+    }
+
+    Ok(updated)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum MismatchKind {
+    /// Current member without Listmonk subscriber
+    NotSubscribed,
+    /// Subscriber we know about doesn't exist in Listmonk
+    SubscriberMissing,
+    /// Member left but subscriber is not blocklisted
+    LeftButSubscribed,
+    /// Current member is blocklisted in Listmonk
+    Blocklisted,
+    EmailDiffers,
+    NameDiffers,
+    ListsDiffer,
+    /// Member unsubscribed from some of the lists in Listmonk
+    Unsubscribed,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub(crate) struct Difference {
+    kind: MismatchKind,
+    orca: Option<String>,
+    listmonk: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Mismatch {
+    member_id: Id<Member>,
+    member_number: MemberNumber,
+    listmonk_id: Option<i32>,
+    #[serde(flatten)]
+    difference: Difference,
+}
+
+fn difference(kind: MismatchKind, orca: Option<String>, listmonk: Option<String>) -> Difference {
+    Difference {
+        kind,
+        orca,
+        listmonk,
+    }
+}
+
+fn format_lists(lists: &[u32]) -> String {
+    lists
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn differences(expected: &Expected, remote: &RemoteSubscriber) -> Vec<Difference> {
+    use MismatchKind::{
+        Blocklisted, EmailDiffers, LeftButSubscribed, ListsDiffer, NameDiffers, Unsubscribed,
+    };
+
+    let is_blocklisted = remote.status == ListMonkStatus::Blocklisted;
+
+    if expected.left {
+        return if is_blocklisted {
+            Vec::new()
+        } else {
+            vec![difference(
+                LeftButSubscribed,
+                Some("left".to_string()),
+                Some(remote.status.as_str().to_string()),
+            )]
+        };
+    }
+
+    let mut result = Vec::new();
+
+    if is_blocklisted {
+        result.push(difference(Blocklisted, None, Some(BLOCKLISTED.to_string())));
+    }
+
+    if !expected
+        .email
+        .as_deref()
+        .is_some_and(|email| email.eq_ignore_ascii_case(&remote.email))
+    {
+        result.push(difference(
+            EmailDiffers,
+            expected.email.clone(),
+            Some(remote.email.clone()),
+        ));
+    }
+
+    if expected.name != remote.name {
+        result.push(difference(
+            NameDiffers,
+            Some(expected.name.clone()),
+            Some(remote.name.clone()),
+        ));
+    }
+
+    let mut remote_lists: Vec<u32> = remote.lists.iter().map(|l| l.id).collect();
+    remote_lists.sort_unstable();
+    if remote_lists != expected.lists {
+        result.push(difference(
+            ListsDiffer,
+            Some(format_lists(&expected.lists)),
+            Some(format_lists(&remote_lists)),
+        ));
+    }
+
+    let unsubscribed: Vec<u32> = remote
+        .lists
+        .iter()
+        .filter(|l| l.subscription_status == UNSUBSCRIBED)
+        .map(|l| l.id)
+        .collect();
+    if !unsubscribed.is_empty() {
+        result.push(difference(
+            Unsubscribed,
+            None,
+            Some(format_lists(&unsubscribed)),
+        ));
+    }
+
+    result
+}
+
+/// Compare members with their Listmonk subscribers
+/// and list everything which doesn't match.
+///
+/// All subscribers are listed from Listmonk in pages
+/// so comparing doesn't take a request per member.
+pub(crate) async fn reconcile(
+    connection: &Connection<'_>,
+    routing: &Routing,
+    db_pool: &DbPool,
+) -> Result<Vec<Mismatch>, Error> {
+    let members = query::list_subscribed_members().fetch_all(db_pool).await?;
+    let subscribers = connection.list_subscribers().await?;
+
+    Ok(compare(&members, &subscribers, routing))
+}
+
+fn compare(
+    members: &[SubscribedMember],
+    subscribers: &HashMap<i32, RemoteSubscriber>,
+    routing: &Routing,
+) -> Vec<Mismatch> {
+    let mut report = Vec::new();
+
+    for member in members {
+        let expected = member.expected(routing);
+
+        let differences = match member.listmonk_id {
+            None if expected.left => Vec::new(),
+            None => vec![difference(MismatchKind::NotSubscribed, None, None)],
+            Some(listmonk_id) => match subscribers.get(&listmonk_id) {
+                None => vec![difference(
+                    MismatchKind::SubscriberMissing,
+                    Some(listmonk_id.to_string()),
+                    None,
+                )],
+                Some(remote) => differences(&expected, remote),
+            },
+        };
+
+        report.extend(differences.into_iter().map(|difference| Mismatch {
+            member_id: member.member_id,
+            member_number: member.member_number,
+            listmonk_id: member.listmonk_id,
+            difference,
+        }));
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote(status: &str, lists: &str) -> RemoteSubscriber {
+        json::from_str(&format!(
+            r#"{{"id": 1, "email": "jane@example.com", "name": "Jane Doe", "status": "{status}", "lists": {lists}}}"#
+        ))
+        .unwrap()
+    }
+
+    fn expected(left: bool) -> Expected {
+        Expected {
+            email: Some("Jane@example.com".to_string()),
+            name: "Jane Doe".to_string(),
+            lists: vec![3, 5],
+            left,
+        }
+    }
+
+    const LISTS: &str = r#"[{"id": 5, "subscription_status": "confirmed"}, {"id": 3, "subscription_status": "unconfirmed"}]"#;
+
+    #[test]
+    fn matching_subscriber_has_no_differences() {
+        assert!(differences(&expected(false), &remote("enabled", LISTS)).is_empty());
+    }
+
+    #[test]
+    fn left_member_must_be_blocklisted() {
+        assert!(differences(&expected(true), &remote("blocklisted", LISTS)).is_empty());
+
+        let result = differences(&expected(true), &remote("enabled", LISTS));
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].kind, MismatchKind::LeftButSubscribed);
+    }
+
+    #[test]
+    fn reports_lists_and_unsubscribes() {
+        let lists = r#"[{"id": 5, "subscription_status": "unsubscribed"}]"#;
+        let kinds: Vec<MismatchKind> = differences(&expected(false), &remote("enabled", lists))
+            .into_iter()
+            .map(|d| d.kind)
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![MismatchKind::ListsDiffer, MismatchKind::Unsubscribed]
+        );
+    }
+
+    #[test]
+    fn reports_changed_details() {
+        let mut member = expected(false);
+        member.email = Some("jane.doe@example.com".to_string());
+        member.name = "Jane Smith".to_string();
+
+        let kinds: Vec<MismatchKind> = differences(&member, &remote("enabled", LISTS))
+            .into_iter()
+            .map(|d| d.kind)
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![MismatchKind::EmailDiffers, MismatchKind::NameDiffers]
+        );
+    }
+
+    fn member(listmonk_id: Option<i32>) -> SubscribedMember {
+        SubscribedMember {
+            member_id: Id::from(Uuid::new_v4()),
+            member_number: json::from_str("7").unwrap(),
+            email: Some("jane@example.com".to_string()),
+            first_name: Some("Jane".to_string()),
+            last_name: Some("Doe".to_string()),
+            language: None,
+            city: None,
+            postal_code: None,
+            left_at: None,
+            workplace_ids: Vec::new(),
+            listmonk_id,
+        }
+    }
+
+    #[test]
+    fn compares_members_with_listed_subscribers() {
+        let subscribers = HashMap::from([(
+            1,
+            remote(
+                "enabled",
+                r#"[{"id": 5, "subscription_status": "confirmed"}]"#,
+            ),
+        )]);
+        let members = [member(Some(1)), member(Some(2)), member(None)];
+
+        let kinds: Vec<MismatchKind> = compare(&members, &subscribers, &Routing::default())
+            .into_iter()
+            .map(|m| m.difference.kind)
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![MismatchKind::SubscriberMissing, MismatchKind::NotSubscribed]
+        );
+    }
+
+    #[test]
+    fn rejoined_member_is_unblocked() {
+        // Member left and was blocklisted
+        let member = expected(true);
+        assert!(differences(&member, &remote("blocklisted", LISTS)).is_empty());
+
+        // After re-joining the blocklisting is lifted
+        let status = pushed_status(ListMonkStatus::Blocklisted, true);
+        assert_eq!(status, ListMonkStatus::Enabled);
+        assert!(differences(&expected(false), &remote(status.as_str(), LISTS)).is_empty());
+    }
+
+    #[test]
+    fn regular_push_keeps_blocklisting_done_in_listmonk() {
+        assert_eq!(
+            pushed_status(ListMonkStatus::Blocklisted, false),
+            ListMonkStatus::Blocklisted
+        );
+        assert_eq!(
+            pushed_status(ListMonkStatus::Disabled, true),
+            ListMonkStatus::Disabled
+        );
+    }
+
+    #[test]
+    fn linked_subscriber_keeps_own_lists() {
+        let mut lists = vec![3, 7];
+        keep_own_lists(&mut lists, &remote("enabled", LISTS));
+
+        assert_eq!(lists, vec![3, 7, 5]);
+    }
+}
@@ -3,10 +3,12 @@ use chrono::{DateTime, NaiveDate, Utc};
 use rocket::serde::{Deserialize, Serialize};
 use tokio::fs;
 use tokio::io;
+use tokio::process;
 
 use tokio::io::AsyncWriteExt;
 // usage of this std lib functions means blocking
 use std::io::Cursor as BlockingCursor;
+use std::process::{ExitStatus, Stdio};
 
 use image::ImageReader;
 use thiserror::Error;
@@ -196,6 +198,24 @@ where
     }
 }
 
+/// Print `file` in `dir` to PDF with xelatex.
+/// Shell escape is disabled so that TeX can't run arbitrary commands.
+pub async fn xelatex(tex_exe: &str, dir: &str, file: &str) -> Result<ExitStatus, io::Error> {
+    let mut child = process::Command::new(tex_exe)
+        .current_dir(dir)
+        .arg(file)
+        .arg("-halt-on-error")
+        .arg("-no-shell-escape")
+        .stdout(Stdio::null())
+        .spawn()?;
+
+    // Await until command completes
+    // There is a problem with tokio detecting the exist status of the process
+    // at least in cases where xelatex fails to find font in OSFONTDIR
+    // like similar to https://users.rust-lang.org/t/tokio-child-wait-never-returning/96657
+    child.wait().await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
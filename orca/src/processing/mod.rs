@@ -3,30 +3,38 @@
 //! This also might be called `queue` or `worker`
 use phf::phf_map;
 
-use chrono::NaiveDate;
+use chrono::{Days, NaiveDate, Utc};
 use tokio::fs;
 use tokio::io::{self, AsyncWriteExt};
-use tokio::process;
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::Sender;
+use tokio::time;
 
 use lettre::{
     AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor, address, message::Attachment,
     message::Mailbox, message::MultiPart, message::SinglePart,
     transport::smtp::authentication::Credentials,
 };
-use log::{error, info};
-use std::process::Stdio;
+use log::{error, info, warn};
+use std::collections::HashMap;
+use std::time::Duration;
 use thiserror::Error;
 
 mod query;
 
+use crate::api::workplaces;
 use crate::config::Config;
 use crate::config::templates;
-use crate::data::{Id, Member, RegistrationRequest};
+use crate::data::{EmailChange, Id, InfoRequest, Member, MemberNumber, RegistrationRequest};
 use crate::db::DbPool;
-use crate::media::{ImageData, TexEscape};
+use crate::dues::status::{
+    DUES_CURRENCY, DuesStatus, current_statuses, member_status, payment_request,
+};
+use crate::dues::{ReminderLevel, format_amount, next_reminder};
+use crate::listmonk::{self, Connection};
+use crate::media::{self, ImageData, TexEscape};
 use crate::server::oid::{JwtToken, Provider};
+use crate::spayd;
 
 #[derive(Debug)]
 pub enum Command {
@@ -35,6 +43,18 @@ pub enum Command {
     SendEmailAsTreasurer(String, String, String, String),
     NewMemberCreated(Id<Member>, Option<String>),
     SendNotificationToTreasurer,
+    SendInfoRequest(Id<InfoRequest>),
+    SubscribeToListmonk(Id<Member>),
+    SyncListmonkSubscriber(Id<Member>),
+    /// Sync of re-joined member which also lifts blocklisting done when they left
+    ResubscribeListmonkSubscriber(Id<Member>),
+    PullListmonkStatuses,
+    ReconcileWorkplaceGroups,
+    SendWelcomeEmail(Id<Member>),
+    SendDuesReminder(Id<Member>),
+    SendDuesReminders,
+    SendEmailChangeConfirmation(Id<EmailChange>),
+    SendEmailChangedNotification(Id<EmailChange>),
 }
 
 impl std::fmt::Display for Command {
@@ -55,6 +75,39 @@ impl std::fmt::Display for Command {
             Self::SendNotificationToTreasurer => {
                 write!(f, "SendNotificationToTreasurer")
             }
+            Self::SendInfoRequest(id) => {
+                write!(f, "SendInfoRequest id: {id}")
+            }
+            Self::SubscribeToListmonk(id) => {
+                write!(f, "SubscribeToListmonk member id: {id}")
+            }
+            Self::SyncListmonkSubscriber(id) => {
+                write!(f, "SyncListmonkSubscriber member id: {id}")
+            }
+            Self::ResubscribeListmonkSubscriber(id) => {
+                write!(f, "ResubscribeListmonkSubscriber member id: {id}")
+            }
+            Self::PullListmonkStatuses => {
+                write!(f, "PullListmonkStatuses")
+            }
+            Self::ReconcileWorkplaceGroups => {
+                write!(f, "ReconcileWorkplaceGroups")
+            }
+            Self::SendWelcomeEmail(id) => {
+                write!(f, "SendWelcomeEmail member id: {id}")
+            }
+            Self::SendDuesReminder(id) => {
+                write!(f, "SendDuesReminder member id: {id}")
+            }
+            Self::SendDuesReminders => {
+                write!(f, "SendDuesReminders")
+            }
+            Self::SendEmailChangeConfirmation(id) => {
+                write!(f, "SendEmailChangeConfirmation id: {id}")
+            }
+            Self::SendEmailChangedNotification(id) => {
+                write!(f, "SendEmailChangedNotification id: {id}")
+            }
         }
     }
 }
@@ -77,13 +130,46 @@ pub fn start(config: &Config, db_pool: DbPool, oid_provider: &Provider) -> Queue
     let (sender, mut receiver) = mpsc::channel::<Command>(config.processing_queue_size);
     let our_conf = config.clone();
     let cloned_oid_provider = oid_provider.clone();
+    let retry_sender = sender.clone();
+
+    if config.listmonk_sync_interval > 0 {
+        schedule_periodically(
+            sender.clone(),
+            Duration::from_secs(config.listmonk_sync_interval),
+            || Command::PullListmonkStatuses,
+        );
+    }
+
+    if config.workplace_groups_sync_interval > 0 {
+        schedule_periodically(
+            sender.clone(),
+            Duration::from_secs(config.workplace_groups_sync_interval),
+            || Command::ReconcileWorkplaceGroups,
+        );
+    }
+
+    if config.dues_reminder_interval > 0 {
+        schedule_periodically(
+            sender.clone(),
+            Duration::from_secs(config.dues_reminder_interval),
+            || Command::SendDuesReminders,
+        );
+    }
 
     info!("Starting processing queue");
     tokio::spawn(async move {
         while let Some(cmd) = receiver.recv().await {
             let cmd_info = cmd.to_string();
             info!("Processiong command: {cmd_info}");
-            match process(cmd, &our_conf, &db_pool, &cloned_oid_provider).await {
+            match process(
+                cmd,
+                &our_conf,
+                &db_pool,
+                &cloned_oid_provider,
+                &retry_sender,
+            )
+            .await
+            {
                 Ok(()) => info!("Command processed successfully"),
                 Err(err) => error!("Processing of cmd: {cmd_info} failed with: {err:?}"),
             }
@@ -93,6 +179,64 @@ pub fn start(config: &Config, db_pool: DbPool, oid_provider: &Provider) -> Queue
     QueueSender(sender)
 }
 
+/// Periodically enqueue command
+fn schedule_periodically(sender: Sender<Command>, period: Duration, command: fn() -> Command) {
+    tokio::spawn(async move {
+        let mut interval = time::interval(period);
+        loop {
+            interval.tick().await;
+            if sender.send(command()).await.is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Enqueue command again after delay
+fn schedule_retry(sender: Sender<Command>, cmd: Command, delay: Duration) {
+    tokio::spawn(async move {
+        time::sleep(delay).await;
+        if let Err(e) = sender.send(cmd).await {
+            error!("Failed to enqueue retry: {e}");
+        }
+    });
+}
+
+pub async fn ensure_listmonk_subscriptions(db_pool: &DbPool, queue: &QueueSender) {
+    match query::get_pending_listmonk_subscriptions()
+        .fetch_all(db_pool)
+        .await
+    {
+        Ok(members) => {
+            info!(
+                "Resuming Listmonk subscription of {} member(s)",
+                members.len()
+            );
+            for (member_id,) in members {
+                if let Err(e) = queue.send(Command::SubscribeToListmonk(member_id)).await {
+                    error!("Failed to enqueue SubscribeToListmonk for member {member_id}: {e}");
+                }
+            }
+        }
+        Err(e) => error!("Failed to load pending Listmonk subscriptions: {e:?}"),
+    }
+}
+
+/// Resume pushes to Listmonk which failed before restart
+pub async fn ensure_listmonk_syncs(db_pool: &DbPool, queue: &QueueSender) {
+    match query::get_pending_listmonk_syncs().fetch_all(db_pool).await {
+        Ok(members) => {
+            info!("Resuming Listmonk sync of {} member(s)", members.len());
+            for (member_id,) in members {
+                if let Err(e) = queue.send(Command::SyncListmonkSubscriber(member_id)).await {
+                    error!("Failed to enqueue SyncListmonkSubscriber for member {member_id}: {e}");
+                }
+            }
+        }
+        Err(e) => error!("Failed to load pending Listmonk syncs: {e:?}"),
+    }
+}
+
 pub async fn ensure_member_subs(db_pool: &DbPool, queue: &QueueSender) {
     match query::get_members_without_sub().fetch_all(db_pool).await {
         Ok(members) => {
@@ -126,14 +270,24 @@ enum ProcessingError {
     Smtp(#[from] lettre::transport::smtp::Error),
     #[error("Confirmation token missing")]
     MissingConfirmationToken,
+    #[error("Status token missing")]
+    MissingStatusToken,
     #[error("Failed to parse MJML template: {0}")]
     MjmlParse(String),
     #[error("Failed to render MJML template: {0}")]
     MjmlRender(String),
     #[error("OID provider error: {0}")]
     Oid(#[from] crate::server::oid::Error),
+    #[error("Listmonk error: {0}")]
+    Listmonk(#[from] listmonk::Error),
+    #[error("Workplace groups reconciliation error: {0}")]
+    WorkplaceSync(#[from] workplaces::sync::Error),
     #[error("NewMemberCreated command is missing OID token")]
     MissingOidToken,
+    #[error("Member {0} has no email")]
+    MissingEmail(Id<Member>),
+    #[error("Payment QR code error: {0}")]
+    Spayd(#[from] spayd::Error),
 }
 
 async fn process(
@@ -141,10 +295,14 @@ async fn process(
     config: &Config,
     db_pool: &DbPool,
     oid_provider: &Provider,
+    queue: &Sender<Command>,
 ) -> Result<(), ProcessingError> {
     use Command::{
-        NewMemberCreated, NewRegistrationRequest, ResentRegistrationEmail, SendEmailAsTreasurer,
-        SendNotificationToTreasurer,
+        NewMemberCreated, NewRegistrationRequest, PullListmonkStatuses, ReconcileWorkplaceGroups,
+        ResentRegistrationEmail, ResubscribeListmonkSubscriber, SendDuesReminder,
+        SendDuesReminders, SendEmailAsTreasurer, SendEmailChangeConfirmation,
+        SendEmailChangedNotification, SendInfoRequest, SendNotificationToTreasurer,
+        SendWelcomeEmail, SubscribeToListmonk, SyncListmonkSubscriber,
     };
 
     match command {
@@ -196,6 +354,162 @@ async fn process(
         SendNotificationToTreasurer => {
             process_confirmation_email_for_treasurer(config).await?;
         }
+        SendInfoRequest(info_request_id) => {
+            let info_request = query::query_info_request(info_request_id)
+                .fetch_one(db_pool)
+                .await?;
+            send_info_request_email(config, &info_request).await?;
+        }
+        SubscribeToListmonk(member_id) => {
+            process_listmonk_subscription(member_id, config, db_pool, queue).await?;
+        }
+        SyncListmonkSubscriber(member_id) => {
+            process_listmonk_sync(member_id, false, config, db_pool, queue).await?;
+        }
+        ResubscribeListmonkSubscriber(member_id) => {
+            process_listmonk_sync(member_id, true, config, db_pool, queue).await?;
+        }
+        PullListmonkStatuses => {
+            if let Some(connection) = Connection::from_config(config)? {
+                let updated = listmonk::sync::pull_statuses(&connection, db_pool).await?;
+                info!("Pulled statuses of {updated} Listmonk subscriber(s)");
+            }
+        }
+        ReconcileWorkplaceGroups => {
+            reconcile_workplace_groups(db_pool, oid_provider).await?;
+        }
+        SendWelcomeEmail(member_id) => {
+            let status = member_status(db_pool, member_id).await?;
+            send_dues_email(config, db_pool, &status, DuesEmail::Welcome).await?;
+        }
+        SendDuesReminder(member_id) => {
+            send_manual_dues_reminder(member_id, config, db_pool).await?;
+        }
+        SendDuesReminders => {
+            process_dues_reminders(config, db_pool).await?;
+        }
+        SendEmailChangeConfirmation(id) => {
+            send_email_change_email(config, db_pool, id, EmailChangeEmail::Confirmation).await?;
+        }
+        SendEmailChangedNotification(id) => {
+            send_email_change_email(config, db_pool, id, EmailChangeEmail::Notification).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn process_listmonk_subscription(
+    member_id: Id<Member>,
+    config: &Config,
+    db_pool: &DbPool,
+    queue: &Sender<Command>,
+) -> Result<(), ProcessingError> {
+    let Some(connection) = Connection::from_config(config)? else {
+        return Ok(());
+    };
+
+    let Err(err) =
+        listmonk::sync::subscribe_member(&connection, &config.listmonk_routing, db_pool, member_id)
+            .await
+    else {
+        return Ok(());
+    };
+
+    let attempts = listmonk::sync::record_subscription_failure(
+        db_pool,
+        member_id,
+        &err,
+        config.listmonk_subscribe_attempts,
+    )
+    .await?;
+    retry_listmonk(
+        config,
+        queue,
+        Command::SubscribeToListmonk(member_id),
+        attempts,
+    );
+
+    Err(err.into())
+}
+
+/// Failed pushes are retried the same way as subscriptions.
+/// Subscriber of re-joined member is enabled again with `resubscribe`.
+async fn process_listmonk_sync(
+    member_id: Id<Member>,
+    resubscribe: bool,
+    config: &Config,
+    db_pool: &DbPool,
+    queue: &Sender<Command>,
+) -> Result<(), ProcessingError> {
+    let Some(connection) = Connection::from_config(config)? else {
+        return Ok(());
+    };
+
+    let Err(err) = listmonk::sync::push_member(
+        &connection,
+        &config.listmonk_routing,
+        db_pool,
+        member_id,
+        resubscribe,
+    )
+    .await
+    else {
+        listmonk::sync::clear_sync_failures(db_pool, member_id).await?;
+        return Ok(());
+    };
+
+    let attempts = listmonk::sync::record_sync_failure(
+        db_pool,
+        member_id,
+        &err,
+        config.listmonk_subscribe_attempts,
+    )
+    .await?;
+    let command = if resubscribe {
+        Command::ResubscribeListmonkSubscriber(member_id)
+    } else {
+        Command::SyncListmonkSubscriber(member_id)
+    };
+    retry_listmonk(config, queue, command, attempts);
+
+    Err(err.into())
+}
+
+/// Exponential backoff: delay, 2 * delay, 4 * delay...
+///
+/// Returns `None` once `max_attempts` were made.
+fn retry_delay(delay: u64, attempts: u32, max_attempts: u32) -> Option<u64> {
+    (attempts < max_attempts)
+        .then(|| delay.saturating_mul(2u64.saturating_pow(attempts.saturating_sub(1))))
+}
+
+fn retry_listmonk(config: &Config, queue: &Sender<Command>, command: Command, attempts: i32) {
+    let attempts = u32::try_from(attempts).unwrap_or(u32::MAX);
+    if let Some(delay) = retry_delay(
+        config.listmonk_retry_delay,
+        attempts,
+        config.listmonk_subscribe_attempts,
+    ) {
+        info!("{command} will be retried in {delay}s");
+        schedule_retry(queue.clone(), command, Duration::from_secs(delay));
+    } else {
+        error!("Giving up {command} after {attempts} attempts");
+    }
+}
+
+/// Differences are only reported, fixing them is up to board
+async fn reconcile_workplace_groups(
+    db_pool: &DbPool,
+    oid_provider: &Provider,
+) -> Result<(), ProcessingError> {
+    let service_token = oid_provider.service_token().await?;
+    let report =
+        workplaces::sync::reconcile(db_pool, oid_provider, &JwtToken::new(&service_token), None)
+            .await?;
+
+    for drift in report {
+        warn!("Keycloak group of workplace differs: {drift:?}");
     }
 
     Ok(())
@@ -290,6 +604,10 @@ async fn process_new_registration(
     Ok(())
 }
 
+#[expect(
+    clippy::unnecessary_trailing_comma,
+    reason = "trailing comma predates the lint"
+)]
 async fn process_confirmation_email_for_treasurer(config: &Config) -> Result<(), ProcessingError> {
     info!("Send notification email to the treasurer",);
 
@@ -368,6 +686,10 @@ fn prepare_new_application_notification_message(
         .multipart(MultiPart::related().singlepart(SinglePart::html(message_html)))?)
 }
 
+#[expect(
+    clippy::unnecessary_trailing_comma,
+    reason = "trailing comma predates the lint"
+)]
 async fn send_verification_email(
     config: &Config,
     db_pool: &DbPool,
@@ -383,6 +705,17 @@ async fn send_verification_email(
 
     let verify_link = format!("{}/registration/{}/confirm", config.host, token,);
 
+    let status_link = application_details
+        .status_token
+        .as_ref()
+        .map(|status_token| {
+            config.application_status_link_for_local(
+                &application_details.registration_local,
+                status_token,
+            )
+        })
+        .unwrap_or_default();
+
     let sender_info: Mailbox = format!(
         "{} <{}>",
         config.email_sender_name.clone().unwrap_or_default(),
@@ -412,7 +745,8 @@ async fn send_verification_email(
             "last_name",
             application_details.last_name.as_deref().unwrap_or(""),
         )
-        .bind("verify_link", &verify_link);
+        .bind("verify_link", &verify_link)
+        .bind("status_link", &status_link);
 
     let message_html = config.templates.render(&renderer)?;
 
@@ -442,6 +776,362 @@ async fn send_verification_email(
     Ok(())
 }
 
+#[derive(Debug, sqlx::FromRow)]
+pub struct InfoRequestDetails {
+    pub question: String,
+    pub email: String,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub status_token: Option<String>,
+    pub registration_local: String,
+}
+
+async fn send_info_request_email(
+    config: &Config,
+    info_request: &InfoRequestDetails,
+) -> Result<(), ProcessingError> {
+    info!("Send info request email to {}", info_request.email);
+
+    // Applicant answers using the status page
+    let status_token = info_request
+        .status_token
+        .as_ref()
+        .ok_or(ProcessingError::MissingStatusToken)?;
+    let answer_link =
+        config.application_status_link_for_local(&info_request.registration_local, status_token);
+
+    let sender_info: Mailbox = format!(
+        "{} <{}>",
+        config.email_sender_name.clone().unwrap_or_default(),
+        config.email_sender_email
+    )
+    .parse()?;
+
+    let full_name = format!(
+        "{} {}",
+        info_request.first_name.as_deref().unwrap_or(""),
+        info_request.last_name.as_deref().unwrap_or("")
+    );
+    let subject = config.info_request_subject_for_local(&info_request.registration_local);
+
+    let mut renderer = config
+        .templates
+        .renderer(&templates::INFO_REQUEST, &info_request.registration_local);
+
+    renderer
+        .bind(
+            "first_name",
+            info_request.first_name.as_deref().unwrap_or(""),
+        )
+        .bind("last_name", info_request.last_name.as_deref().unwrap_or(""))
+        .bind("question", &info_request.question)
+        .bind("answer_link", &answer_link);
+
+    let message_html = config.templates.render(&renderer)?;
+
+    let message = Message::builder()
+        .from(sender_info.clone())
+        .reply_to(sender_info)
+        .to(format!("{} <{}>", full_name, info_request.email).parse()?)
+        .subject(subject)
+        .multipart(MultiPart::related().singlepart(SinglePart::html(message_html)))?;
+
+    send_email(config, message).await
+}
+
+/// Latest reminder of every member sent after their last payment
+async fn last_reminders(
+    db_pool: &DbPool,
+) -> Result<HashMap<Id<Member>, (ReminderLevel, NaiveDate)>, ProcessingError> {
+    Ok(query::list_last_reminders()
+        .fetch_all(db_pool)
+        .await?
+        .into_iter()
+        .filter_map(|(member_id, level, sent_at)| {
+            Some((
+                member_id,
+                (ReminderLevel::parse(&level)?, sent_at.date_naive()),
+            ))
+        })
+        .collect())
+}
+
+async fn send_dues_reminder(
+    config: &Config,
+    db_pool: &DbPool,
+    status: &DuesStatus,
+    level: ReminderLevel,
+    automatic: bool,
+) -> Result<(), ProcessingError> {
+    send_dues_email(config, db_pool, status, DuesEmail::Reminder(level)).await?;
+
+    query::create_reminder(status.member_id, level.as_str(), status.arrears, automatic)
+        .execute(db_pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Reminder requested by treasurer escalates immediately
+/// and final notice is repeated
+async fn send_manual_dues_reminder(
+    member_id: Id<Member>,
+    config: &Config,
+    db_pool: &DbPool,
+) -> Result<(), ProcessingError> {
+    let status = member_status(db_pool, member_id).await?;
+    if status.arrears <= 0 {
+        info!("Member {member_id} paid their dues in the meantime, skipping reminder");
+        return Ok(());
+    }
+
+    let last_level = query::get_last_reminder(member_id)
+        .fetch_optional(db_pool)
+        .await?
+        .and_then(|(level,)| ReminderLevel::parse(&level));
+    let level = match last_level {
+        Some(level) => level.next().unwrap_or(ReminderLevel::Final),
+        None => ReminderLevel::First,
+    };
+
+    send_dues_reminder(config, db_pool, &status, level, false).await
+}
+
+/// Remind current members who owe at least `dues_reminder_min_months` monthly fees
+/// unless their reminders are paused
+async fn process_dues_reminders(config: &Config, db_pool: &DbPool) -> Result<(), ProcessingError> {
+    let today = Utc::now().date_naive();
+    let interval = Days::new(config.dues_reminder_escalation_days);
+    let last_reminders = last_reminders(db_pool).await?;
+
+    let mut sent = 0;
+    for status in current_statuses(db_pool).await? {
+        if status.arrears <= 0
+            || status.months_in_arrears < config.dues_reminder_min_months
+            || status.reminders_paused
+        {
+            continue;
+        }
+
+        let last = last_reminders.get(&status.member_id).copied();
+        let Some(level) = next_reminder(last, today, interval) else {
+            continue;
+        };
+
+        // One failing member shouldn't stop the whole campaign
+        match send_dues_reminder(config, db_pool, &status, level, true).await {
+            Ok(()) => sent += 1,
+            Err(err) => error!(
+                "Failed to send dues reminder to member {}: {err}",
+                status.member_id
+            ),
+        }
+    }
+
+    info!("Sent {sent} dues reminder(s)");
+    Ok(())
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct MemberContact {
+    pub member_number: MemberNumber,
+    pub email: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub language: Option<String>,
+}
+
+/// Emails asking member to pay dues, all include payment QR code
+#[derive(Debug, Clone, Copy)]
+enum DuesEmail {
+    /// Sent to accepted member, asks for monthly fee
+    Welcome,
+    /// Asks for all arrears
+    Reminder(ReminderLevel),
+}
+
+async fn send_dues_email(
+    config: &Config,
+    db_pool: &DbPool,
+    status: &DuesStatus,
+    kind: DuesEmail,
+) -> Result<(), ProcessingError> {
+    let member_id = status.member_id;
+    let member = query::query_member_contact(member_id)
+        .fetch_one(db_pool)
+        .await?;
+    let email = member
+        .email
+        .as_deref()
+        .ok_or(ProcessingError::MissingEmail(member_id))?;
+    info!("Send {kind:?} email to {email}");
+
+    let lang = member.language.as_deref().unwrap_or("default");
+
+    let (template, subject, amount) = match kind {
+        DuesEmail::Welcome => (
+            &templates::WELCOME,
+            config.welcome_email_subject_for_local(lang),
+            status.monthly_fee,
+        ),
+        DuesEmail::Reminder(level) => (
+            match level {
+                ReminderLevel::First => &templates::DUES_REMINDER,
+                ReminderLevel::Second => &templates::DUES_SECOND_REMINDER,
+                ReminderLevel::Final => &templates::DUES_FINAL_REMINDER,
+            },
+            config.dues_reminder_subject_for_local(level, lang),
+            status.arrears,
+        ),
+    };
+
+    // QR code is left out when there is no account configured
+    let qr_png = payment_request(config, member.member_number, amount)
+        .map(|payment| payment.qr_png())
+        .transpose()?;
+
+    let sender_info: Mailbox = format!(
+        "{} <{}>",
+        config.email_sender_name.clone().unwrap_or_default(),
+        config.email_sender_email
+    )
+    .parse()?;
+
+    let reply_info: Mailbox = format!(
+        "{} <{}>",
+        config.treasurer_reply_name, config.treasurer_reply_email
+    )
+    .parse()?;
+
+    let full_name = format!(
+        "{} {}",
+        member.first_name.as_deref().unwrap_or(""),
+        member.last_name.as_deref().unwrap_or("")
+    );
+    let member_number = member.member_number.to_string();
+    let amount = format_amount(amount);
+
+    let mut renderer = config.templates.renderer(template, lang);
+    renderer
+        .bind("first_name", member.first_name.as_deref().unwrap_or(""))
+        .bind("last_name", member.last_name.as_deref().unwrap_or(""))
+        .bind("member_number", &member_number)
+        .bind("amount", &amount)
+        .bind("currency", DUES_CURRENCY)
+        .bind("account", config.payment_account.as_deref().unwrap_or(""))
+        .bind(
+            "payment_qr",
+            if qr_png.is_some() {
+                "cid:payment_qr"
+            } else {
+                ""
+            },
+        );
+
+    let message_html = config.templates.render(&renderer)?;
+
+    let mut body = MultiPart::related().singlepart(SinglePart::html(message_html));
+    if let Some(png) = qr_png {
+        body = body.singlepart(
+            Attachment::new_inline(String::from("payment_qr"))
+                // This should never fail, it's a valid content type
+                .body(png, "image/png".parse().unwrap()),
+        );
+    }
+
+    let message = Message::builder()
+        .from(sender_info)
+        .reply_to(reply_info)
+        .to(format!("{full_name} <{email}>").parse()?)
+        .subject(subject)
+        .multipart(body)?;
+
+    send_email(config, message).await
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct EmailChangeDetails {
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub language: Option<String>,
+    pub old_email: Option<String>,
+    pub new_email: String,
+    pub confirmation_token: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum EmailChangeEmail {
+    /// Sent to the new address with confirmation link
+    Confirmation,
+    /// Sent to the old address once change is confirmed
+    Notification,
+}
+
+async fn send_email_change_email(
+    config: &Config,
+    db_pool: &DbPool,
+    id: Id<EmailChange>,
+    kind: EmailChangeEmail,
+) -> Result<(), ProcessingError> {
+    let change = query::query_email_change(id).fetch_one(db_pool).await?;
+    let lang = change.language.as_deref().unwrap_or("default");
+
+    let (template, subject, email) = match kind {
+        EmailChangeEmail::Confirmation => (
+            &templates::EMAIL_CHANGE,
+            config.email_change_confirmation_subject_for_local(lang),
+            change.new_email.as_str(),
+        ),
+        EmailChangeEmail::Notification => {
+            let Some(old_email) = change.old_email.as_deref() else {
+                return Ok(());
+            };
+            (
+                &templates::EMAIL_CHANGED,
+                config.email_change_notification_subject_for_local(lang),
+                old_email,
+            )
+        }
+    };
+    info!("Send {kind:?} email of email change to {email}");
+
+    let confirm_link = format!(
+        "{}/members/email-changes/{}/confirm",
+        config.host, change.confirmation_token
+    );
+
+    let sender_info: Mailbox = format!(
+        "{} <{}>",
+        config.email_sender_name.clone().unwrap_or_default(),
+        config.email_sender_email
+    )
+    .parse()?;
+
+    let full_name = format!(
+        "{} {}",
+        change.first_name.as_deref().unwrap_or(""),
+        change.last_name.as_deref().unwrap_or("")
+    );
+
+    let mut renderer = config.templates.renderer(template, lang);
+    renderer
+        .bind("first_name", change.first_name.as_deref().unwrap_or(""))
+        .bind("last_name", change.last_name.as_deref().unwrap_or(""))
+        .bind("new_email", &change.new_email)
+        .bind("confirm_link", &confirm_link);
+
+    let message_html = config.templates.render(&renderer)?;
+
+    let message = Message::builder()
+        .from(sender_info.clone())
+        .reply_to(sender_info)
+        .to(format!("{full_name} <{email}>").parse()?)
+        .subject(subject)
+        .multipart(MultiPart::related().singlepart(SinglePart::html(message_html)))?;
+
+    send_email(config, message).await
+}
+
 #[derive(Debug, sqlx::FromRow)]
 pub struct RegistrationDetails {
     pub id: Id<RegistrationRequest>,
@@ -456,6 +1146,7 @@ pub struct RegistrationDetails {
     pub company_name: Option<String>,
     pub occupation: Option<String>,
     pub confirmation_token: Option<String>,
+    pub status_token: Option<String>,
     pub registration_local: String,
 }
 
@@ -537,20 +1228,28 @@ async fn print_pdf(
     fs::write(format!("{dir}/logo.png"), logo_png).await?;
 
     // Spawn xelatex process to print the pdf
-    let mut child = process::Command::new(config.tex_exe.as_str())
-        .current_dir(dir)
-        .arg("registration.tex")
-        .arg("-halt-on-error")
-        .arg("-no-shell-escape")
-        .stdout(Stdio::null())
-        .spawn()?;
-
-    // Await until command completes
-    // There is a problem with tokio detecting the exist status of the process
-    // at least in cases where xelatex fails to find font in OSFONTDIR
-    // like similar to https://users.rust-lang.org/t/tokio-child-wait-never-returning/96657
-    let status = child.wait().await?;
+    let status = media::xelatex(&config.tex_exe, dir, "registration.tex").await?;
     info!("Tex command exited successfully: {status}");
 
     Ok(format!("{dir}/registration.pdf"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listmonk_retries_back_off_exponentially() {
+        let delays: Vec<Option<u64>> = (1..=4)
+            .map(|attempts| retry_delay(60, attempts, 4))
+            .collect();
+
+        assert_eq!(delays, vec![Some(60), Some(120), Some(240), None]);
+    }
+
+    #[test]
+    fn listmonk_retry_delay_does_not_overflow() {
+        assert_eq!(retry_delay(u64::MAX, 3, 5), Some(u64::MAX));
+        assert_eq!(retry_delay(60, 70, u32::MAX), Some(u64::MAX));
+    }
+}
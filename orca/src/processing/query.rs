@@ -1,10 +1,11 @@
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
 use crate::db::{Query, QueryAs};
 use crate::server::oid;
 
-use super::RegistrationDetails;
-use crate::data::{Id, Member, RegistrationRequest};
+use super::{EmailChangeDetails, InfoRequestDetails, MemberContact, RegistrationDetails};
+use crate::data::{EmailChange, Id, InfoRequest, Member, RegistrationRequest};
 
 pub fn query_registration<'a>(id: Id<RegistrationRequest>) -> QueryAs<'a, RegistrationDetails> {
     sqlx::query_as(
@@ -21,6 +22,7 @@ SELECT id
 , company_name
 , occupation
 , confirmation_token
+, status_token
 , registration_local
 FROM registration_requests WHERE id = $1
 ",
@@ -92,3 +94,124 @@ LIMIT 1
     )
     .bind(id)
 }
+
+pub fn query_info_request<'a>(id: Id<InfoRequest>) -> QueryAs<'a, InfoRequestDetails> {
+    sqlx::query_as(
+        "
+SELECT ri.question
+, rr.email
+, rr.first_name
+, rr.last_name
+, rr.status_token
+, rr.registration_local
+FROM registration_info_requests AS ri
+INNER JOIN registration_requests AS rr ON rr.id = ri.registration_request_id
+WHERE ri.id = $1
+",
+    )
+    .bind(id)
+}
+
+pub fn get_pending_listmonk_subscriptions<'a>() -> QueryAs<'a, (Id<Member>,)> {
+    sqlx::query_as(
+        "
+SELECT member_id
+FROM listmonk_subscription_requests
+WHERE status = 'pending'
+",
+    )
+}
+
+pub fn get_pending_listmonk_syncs<'a>() -> QueryAs<'a, (Id<Member>,)> {
+    sqlx::query_as(
+        "
+SELECT member_id
+FROM listmonk_sync_requests
+WHERE status = 'pending'
+",
+    )
+}
+
+pub fn query_member_contact<'a>(id: Id<Member>) -> QueryAs<'a, MemberContact> {
+    sqlx::query_as(
+        "
+SELECT member_number
+    , email
+    , first_name
+    , last_name
+    , language
+FROM members
+WHERE id = $1
+",
+    )
+    .bind(id)
+}
+
+pub fn query_email_change<'a>(id: Id<EmailChange>) -> QueryAs<'a, EmailChangeDetails> {
+    sqlx::query_as(
+        "
+SELECT m.first_name
+    , m.last_name
+    , m.language
+    , ec.old_email
+    , ec.new_email
+    , ec.confirmation_token
+FROM email_changes AS ec
+INNER JOIN members AS m ON m.id = ec.member_id
+WHERE ec.id = $1
+",
+    )
+    .bind(id)
+}
+
+/// Latest reminder of every member sent after their last payment
+pub fn list_last_reminders<'a>() -> QueryAs<'a, (Id<Member>, String, DateTime<Utc>)> {
+    sqlx::query_as(
+        "
+SELECT DISTINCT ON (r.member_id) r.member_id
+    , r.level
+    , r.sent_at
+FROM dues_reminders r
+JOIN members m ON m.id = r.member_id
+WHERE r.sent_at::DATE > COALESCE(
+    (SELECT MAX(p.paid_on) FROM payments p WHERE p.member_number = m.member_number),
+    '-infinity'::DATE)
+ORDER BY r.member_id, r.sent_at DESC
+",
+    )
+}
+
+/// Latest reminder of member sent after their last payment
+pub fn get_last_reminder<'a>(member_id: Id<Member>) -> QueryAs<'a, (String,)> {
+    sqlx::query_as(
+        "
+SELECT r.level
+FROM dues_reminders r
+JOIN members m ON m.id = r.member_id
+WHERE r.member_id = $1
+    AND r.sent_at::DATE > COALESCE(
+        (SELECT MAX(p.paid_on) FROM payments p WHERE p.member_number = m.member_number),
+        '-infinity'::DATE)
+ORDER BY r.sent_at DESC
+LIMIT 1
+",
+    )
+    .bind(member_id)
+}
+
+pub fn create_reminder(id: Id<Member>, level: &str, arrears: i64, automatic: bool) -> Query<'_> {
+    sqlx::query(
+        "
+INSERT INTO dues_reminders
+    ( member_id
+    , level
+    , arrears
+    , automatic
+    ) VALUES ($1, $2, $3, $4)
+",
+    )
+    .bind(id)
+    .bind(level)
+    .bind(arrears)
+    .bind(automatic)
+}
@@ -56,6 +56,7 @@ pub enum Role {
     ManageMembers,
     ListWorkplaces,
     ManageWorkplaces,
+    Treasurer,
     SuperPowers,
 }
 
@@ -70,6 +71,7 @@ impl Role {
             Self::ManageMembers => "manage-members",
             Self::ListWorkplaces => "list-workplaces",
             Self::ManageWorkplaces => "manage-workplaces",
+            Self::Treasurer => "treasurer",
             Self::SuperPowers => "super-powers",
         }
     }
@@ -144,6 +146,14 @@ trait OidProvider {
         token: &JwtToken<'_>,
         group_id: Uuid,
     ) -> Result<Vec<Uuid>, Error>;
+    /// Token of orca itself for actions which don't happen on behalf of a user
+    async fn service_token(&self) -> Result<String, Error>;
+    async fn update_user_email(
+        &self,
+        token: &JwtToken<'_>,
+        id: Uuid,
+        email: &str,
+    ) -> Result<(), Error>;
 }
 
 #[derive(Clone)]
@@ -156,7 +166,13 @@ impl Provider {
             &config.keycloak_realm,
             &config.keycloak_client_id,
         ) {
-            let k = KeycloakProvider::fetch(host, realm, client_id.clone()).await?;
+            let k = KeycloakProvider::fetch(
+                host,
+                realm,
+                client_id.clone(),
+                config.keycloak_client_secret.clone(),
+            )
+            .await?;
             return Ok(Provider(ProviderState::Keycloak(Box::new(k))));
         }
 
@@ -279,6 +295,25 @@ impl Provider {
             ProviderState::Disconnected => Err(Error::Disabled),
         }
     }
+
+    pub async fn service_token(&self) -> Result<String, Error> {
+        match &self.0 {
+            ProviderState::Keycloak(k) => k.service_token().await,
+            ProviderState::Disconnected => Err(Error::Disabled),
+        }
+    }
+
+    pub async fn update_user_email(
+        &self,
+        token: &JwtToken<'_>,
+        id: Uuid,
+        email: &str,
+    ) -> Result<(), Error> {
+        match &self.0 {
+            ProviderState::Keycloak(k) => k.update_user_email(token, id, email).await,
+            ProviderState::Disconnected => Err(Error::Disabled),
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -301,6 +336,8 @@ pub enum Error {
     Parsing(String),
     #[error("Proxy error: {0}")]
     Proxy(reqwest::StatusCode),
+    #[error("Keycloak client secret is not configured")]
+    MissingClientSecret,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
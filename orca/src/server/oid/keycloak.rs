@@ -20,12 +20,19 @@ pub struct KeycloakProvider {
     key: DecodingKey,
     validation: Validation,
     client_id: String,
+    /// Secret of confidential client with service account, required for actions without user token
+    client_secret: Option<String>,
     host: String,
     realm: String,
 }
 
 impl KeycloakProvider {
-    pub async fn fetch(host: &str, realm: &str, client_id: String) -> Result<Self, Error> {
+    pub async fn fetch(
+        host: &str,
+        realm: &str,
+        client_id: String,
+        client_secret: Option<String>,
+    ) -> Result<Self, Error> {
         let url = keycloak_url(host, realm);
         let key = jwk::fetch_jwk(&format!("{url}/protocol/openid-connect/certs")).await?;
 
@@ -38,6 +45,7 @@ impl KeycloakProvider {
             key,
             validation,
             client_id,
+            client_secret,
             host: host.into(),
             realm: realm.into(),
         })
@@ -295,4 +303,74 @@ impl OidProvider for KeycloakProvider {
             Err(Error::Proxy(status))
         }
     }
+
+    async fn service_token(&self) -> Result<String, Error> {
+        #[derive(serde::Deserialize)]
+        struct TokenResponse {
+            access_token: String,
+        }
+
+        let secret = self
+            .client_secret
+            .as_deref()
+            .ok_or(Error::MissingClientSecret)?;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!(
+                "{}/protocol/openid-connect/token",
+                keycloak_url(&self.host, &self.realm)
+            ))
+            .form(&[
+                ("grant_type", "client_credentials"),
+                ("client_id", &self.client_id),
+                ("client_secret", secret),
+            ])
+            .send()
+            .await?;
+
+        let status = response.status();
+        debug!("Keycloak response status: {status}");
+
+        if status.is_success() {
+            Ok(response.json::<TokenResponse>().await?.access_token)
+        } else {
+            Err(Error::Proxy(status))
+        }
+    }
+
+    async fn update_user_email(
+        &self,
+        token: &JwtToken<'_>,
+        id: Uuid,
+        email: &str,
+    ) -> Result<(), Error> {
+        // Address was confirmed by the member before we got here
+        let json = json!({
+            "email": email,
+            "emailVerified": true
+        });
+
+        let client = reqwest::Client::new();
+        let response = client
+            .put(format!(
+                "{}/admin/realms/{}/users/{}",
+                self.host, self.realm, id
+            ))
+            .json(&json)
+            .header("Authorization", format!("Bearer {}", token.string))
+            .send()
+            .await?;
+
+        let status = response.status();
+
+        debug!("Keycloak response status: {status}");
+        debug!("Keycloak response: {response:?}");
+
+        if status.is_success() {
+            Ok(())
+        } else {
+            Err(Error::Proxy(status))
+        }
+    }
 }
@@ -0,0 +1,174 @@
+//! Short Payment Descriptor (SPAYD)
+//!
+//! Czech standard for QR payment codes which is understood
+//! by all Czech banking apps. Payload is a list of `KEY:value`
+//! pairs separated by `*` like `SPD*1.0*ACC:CZ...*AM:150.00*CC:CZK*X-VS:42`.
+use std::fmt::Display;
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageFormat, Luma};
+use qrcode::QrCode;
+use thiserror::Error;
+
+use crate::data::MemberNumber;
+use crate::dues::format_amount;
+
+/// Longest message banks are required to accept
+const MAX_MESSAGE_LENGTH: usize = 60;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("QR code error: {0}")]
+    Qr(#[from] qrcode::types::QrError),
+    #[error("Image error: {0}")]
+    Image(#[from] image::ImageError),
+}
+
+/// Payment of dues of member. Member number is used as variable symbol.
+#[derive(Debug, Clone)]
+pub struct PaymentRequest<'a> {
+    /// IBAN optionally followed by `+BIC`
+    pub account: &'a str,
+    /// Amount in minor units, not included when zero
+    pub amount: i64,
+    pub currency: &'a str,
+    pub member_number: MemberNumber,
+    pub message: Option<&'a str>,
+}
+
+/// `*` separates the fields so it needs to be percent encoded
+fn escape_char(c: char) -> Option<&'static str> {
+    match c {
+        '%' => Some("%25"),
+        '*' => Some("%2A"),
+        _ => None,
+    }
+}
+
+fn escape(value: &str) -> String {
+    truncate_escaped(value, usize::MAX)
+}
+
+/// Escape `value` keeping whole characters (with their escapes)
+/// as long as the escaped value fits into `max_length`
+fn truncate_escaped(value: &str, max_length: usize) -> String {
+    let mut escaped = String::new();
+    let mut length = 0;
+
+    for c in value.trim().chars() {
+        let escape = escape_char(c);
+        let char_length = escape.map_or(1, str::len);
+        if length + char_length > max_length {
+            break;
+        }
+        length += char_length;
+        match escape {
+            Some(escape) => escaped.push_str(escape),
+            None => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+impl Display for PaymentRequest<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "SPD*1.0*ACC:{}", escape(self.account))?;
+
+        if self.amount > 0 {
+            write!(f, "*AM:{}", format_amount(self.amount))?;
+        }
+
+        write!(
+            f,
+            "*CC:{}*X-VS:{}",
+            escape(self.currency).to_uppercase(),
+            self.member_number
+        )?;
+
+        if let Some(message) = self.message {
+            write!(f, "*MSG:{}", truncate_escaped(message, MAX_MESSAGE_LENGTH))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl PaymentRequest<'_> {
+    /// Render QR code as PNG image
+    ///
+    /// # Errors
+    ///
+    /// When payload doesn't fit into QR code or PNG can't be encoded
+    pub fn qr_png(&self) -> Result<Vec<u8>, Error> {
+        qr_png(&self.to_string())
+    }
+}
+
+/// Render any `payload` as QR code PNG image
+///
+/// # Errors
+///
+/// When payload doesn't fit into QR code or PNG can't be encoded
+pub fn qr_png(payload: &str) -> Result<Vec<u8>, Error> {
+    let code = QrCode::new(payload.as_bytes())?;
+    let image = code.render::<Luma<u8>>().min_dimensions(240, 240).build();
+
+    let mut png = Vec::new();
+    DynamicImage::ImageLuma8(image).write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member_number(number: i32) -> MemberNumber {
+        rocket::serde::json::from_str(&number.to_string()).unwrap()
+    }
+
+    #[test]
+    fn encodes_payment() {
+        let payment = PaymentRequest {
+            account: "CZ6508000000192000145399",
+            amount: 15_000,
+            currency: "czk",
+            member_number: member_number(42),
+            message: Some("Dues * 2024"),
+        };
+
+        assert_eq!(
+            payment.to_string(),
+            "SPD*1.0*ACC:CZ6508000000192000145399*AM:150.00*CC:CZK*X-VS:42*MSG:Dues %2A 2024"
+        );
+    }
+
+    #[test]
+    fn omits_zero_amount_and_missing_message() {
+        let payment = PaymentRequest {
+            account: "CZ6508000000192000145399+GIBACZPX",
+            amount: 0,
+            currency: "CZK",
+            member_number: member_number(7),
+            message: None,
+        };
+
+        assert_eq!(
+            payment.to_string(),
+            "SPD*1.0*ACC:CZ6508000000192000145399+GIBACZPX*CC:CZK*X-VS:7"
+        );
+        assert!(payment.qr_png().unwrap().starts_with(b"\x89PNG"));
+    }
+
+    #[test]
+    fn truncates_message_without_splitting_escapes() {
+        let message = format!("{}*tail", "a".repeat(MAX_MESSAGE_LENGTH - 2));
+
+        assert_eq!(
+            truncate_escaped(&message, MAX_MESSAGE_LENGTH),
+            "a".repeat(MAX_MESSAGE_LENGTH - 2)
+        );
+        assert_eq!(truncate_escaped(" 100% ", MAX_MESSAGE_LENGTH), "100%25");
+    }
+}